@@ -0,0 +1,60 @@
+#[cfg(feature="ffi")]
+extern crate cbindgen;
+
+#[cfg(feature="ffi")]
+fn generate_header() {
+    use std::env;
+    use std::path::PathBuf;
+
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("invalid cbindgen.toml");
+
+    // parse the module alone, cbindgen would run cargo metadata otherwise
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(crate_dir.join("src").join("ffi.rs"))
+        .generate()
+        .expect("failed to generate netkeeper.h")
+        .write_to_file(out_dir.join("netkeeper.h"));
+
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+}
+
+#[cfg(not(feature="ffi"))]
+fn generate_header() {}
+
+/// `pppd_version` of the pppd plugin, `PPPD_VERSION` defaults to 2.4.7.
+#[cfg(feature="pppd")]
+fn generate_pppd_version() {
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    let version = env::var("PPPD_VERSION").unwrap_or_else(|_| "2.4.7".to_string());
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') {
+        panic!("invalid PPPD_VERSION {:?}", version);
+    }
+
+    let path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("pppd_version.rs");
+    let mut file = File::create(path).unwrap();
+    write!(file,
+           "#[no_mangle]\n#[allow(non_upper_case_globals)]\n\
+            pub static pppd_version: [u8; {}] = *b\"{}\\0\";\n",
+           version.len() + 1,
+           version)
+        .unwrap();
+
+    println!("cargo:rerun-if-env-changed=PPPD_VERSION");
+}
+
+#[cfg(not(feature="pppd"))]
+fn generate_pppd_version() {}
+
+fn main() {
+    generate_header();
+    generate_pppd_version();
+}
@@ -0,0 +1,195 @@
+/*
+ * C interface of libnetkeeper, generated from src/ffi.rs by cbindgen. Build
+ * the shared library with
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * and link against target/release/libnetkeeper.so. Functions of protocols
+ * disabled at build time are absent from the library.
+ *
+ * Strings are NUL terminated UTF-8. Builders write to caller provided
+ * buffers and return the number of bytes written, not counting the string
+ * terminator, or a negative NK_ERROR_* code. Parsers return NK_OK or a
+ * negative NK_ERROR_* code. IPv4 addresses are passed as 4 octets in
+ * network order. A timestamp of 0 means the current time.
+ */
+
+#ifndef NETKEEPER_H
+#define NETKEEPER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define NK_OK 0
+
+#define NK_ERROR_NULL_POINTER -1
+
+#define NK_ERROR_INVALID_UTF8 -2
+
+#define NK_ERROR_UNKNOWN_CONFIGURATION -3
+
+#define NK_ERROR_BUFFER_TOO_SMALL -4
+
+#define NK_ERROR_INVALID_ARGUMENT -5
+
+#define NK_ERROR_INVALID_PACKET -6
+
+#define NK_ERROR_LOGIN_FAILED -7
+
+#define NK_ERROR_PANIC -8
+
+#define NK_DRCOM_KEEP_ALIVE_SUCCEED 0
+
+#define NK_DRCOM_KEEP_ALIVE_FILE_RESPONSE 1
+
+#define NK_DRCOM_KEEP_ALIVE_UNRECOGNIZED 2
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Message of an `NK_ERROR_*` code, the returned string is static.
+ */
+const char *nk_strerror(int code);
+
+/**
+ * `configuration` is the name of a built-in configuration such as "zhejiang".
+ */
+ptrdiff_t nk_netkeeper_encrypt_account(const char *configuration,
+                                       const char *username,
+                                       uint32_t timestamp,
+                                       char *output,
+                                       size_t output_length);
+
+/**
+ * `configuration` is the name of a built-in configuration such as "hainan".
+ */
+ptrdiff_t nk_singlenet_encrypt_account(const char *configuration,
+                                       const char *username,
+                                       uint32_t timestamp,
+                                       char *output,
+                                       size_t output_length);
+
+/**
+ * `configuration` is the name of a built-in configuration such as "sichuan_mac".
+ */
+ptrdiff_t nk_ghca_encrypt_account(const char *configuration,
+                                  const char *username,
+                                  const char *password,
+                                  uint32_t timestamp,
+                                  char *output,
+                                  size_t output_length);
+
+/**
+ * SRun3k v20 usernames, which carry no timestamp.
+ */
+ptrdiff_t nk_srun3k_encrypt_account(const char *username,
+                                    char *output,
+                                    size_t output_length);
+
+/**
+ * DrCOM wired, a sequence of 0 picks a random one.
+ */
+ptrdiff_t nk_drcom_wired_build_challenge_request(uint16_t sequence,
+                                                 uint8_t *output,
+                                                 size_t output_length);
+
+int nk_drcom_wired_parse_challenge_response(const uint8_t *packet,
+                                            size_t packet_length,
+                                            uint8_t *hash_salt);
+
+/**
+ * `hostname` may be NULL to keep the default one.
+ */
+ptrdiff_t nk_drcom_wired_build_login_request(const char *username,
+                                             const char *password,
+                                             const uint8_t *hash_salt,
+                                             const uint8_t *mac_address,
+                                             const uint8_t *ipaddress,
+                                             const char *hostname,
+                                             uint8_t *output,
+                                             size_t output_length);
+
+/**
+ * Returns `NK_ERROR_LOGIN_FAILED` when the server rejects the account.
+ */
+int nk_drcom_wired_parse_login_response(const uint8_t *packet,
+                                        size_t packet_length,
+                                        uint8_t *keep_alive_key);
+
+ptrdiff_t nk_drcom_wired_build_logout_request(const char *username,
+                                              const char *password,
+                                              const uint8_t *hash_salt,
+                                              const uint8_t *mac_address,
+                                              uint8_t *output,
+                                              size_t output_length);
+
+/**
+ * `keep_alive_key` is the first 4 bytes of the login response key.
+ */
+ptrdiff_t nk_drcom_wired_build_phase_one_request(const uint8_t *hash_salt,
+                                                 const char *password,
+                                                 const uint8_t *keep_alive_key,
+                                                 uint32_t timestamp,
+                                                 uint8_t *output,
+                                                 size_t output_length);
+
+int nk_drcom_wired_parse_phase_one_response(const uint8_t *packet,
+                                            size_t packet_length);
+
+/**
+ * `host_ip` is only carried by type 3 requests.
+ */
+ptrdiff_t nk_drcom_wired_build_phase_two_request(uint8_t sequence,
+                                                 const uint8_t *keep_alive_key,
+                                                 int first,
+                                                 uint8_t type_id,
+                                                 const uint8_t *host_ip,
+                                                 uint8_t *output,
+                                                 size_t output_length);
+
+int nk_drcom_wired_parse_phase_two_response(const uint8_t *packet,
+                                            size_t packet_length,
+                                            uint8_t *sequence,
+                                            uint8_t *keep_alive_key);
+
+/**
+ * DrCOM PPPoE challenge, the sequence is sent as is.
+ */
+ptrdiff_t nk_drcom_pppoe_build_challenge_request(uint8_t sequence,
+                                                 uint8_t *output,
+                                                 size_t output_length);
+
+int nk_drcom_pppoe_parse_challenge_response(const uint8_t *packet,
+                                            size_t packet_length,
+                                            uint32_t *challenge_seed,
+                                            uint8_t *source_ip);
+
+ptrdiff_t nk_drcom_pppoe_build_heartbeat_request(uint8_t sequence,
+                                                 const uint8_t *source_ip,
+                                                 int first,
+                                                 uint32_t challenge_seed,
+                                                 uint8_t *output,
+                                                 size_t output_length);
+
+ptrdiff_t nk_drcom_pppoe_build_keep_alive_request(uint8_t sequence,
+                                                  int first,
+                                                  uint8_t type_id,
+                                                  const uint8_t *source_ip,
+                                                  uint32_t keep_alive_seed,
+                                                  uint8_t *output,
+                                                  size_t output_length);
+
+/**
+ * `response_type` is set to one of `NK_DRCOM_KEEP_ALIVE_*`.
+ */
+int nk_drcom_pppoe_parse_keep_alive_response(const uint8_t *packet,
+                                             size_t packet_length,
+                                             int *response_type);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* NETKEEPER_H */
@@ -0,0 +1,474 @@
+extern crate getopts;
+extern crate netkeeper;
+
+use std::{env, process};
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet"))]
+use std::thread;
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet", feature="ipclient"))]
+use std::net::Ipv4Addr;
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k",
+          feature="drcom", feature="ipclient"))]
+use std::str::FromStr;
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet"))]
+use std::time::Duration;
+
+use getopts::{Matches, Options};
+
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet"))]
+use netkeeper::common::daemon::{DaemonEvent, KeepAliveDaemon, KeepAliveSession};
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+use netkeeper::common::dialer::ConfigurableDialer;
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+use netkeeper::common::registry::ConfigurationRegistry;
+use netkeeper::error::describe;
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet", feature="ipclient"))]
+use netkeeper::common::transport::UdpTransport;
+
+type CliResult = Result<(), String>;
+
+const USAGE: &'static str = "Usage: netkeeper <command> [options]
+
+Commands:
+    encode      encrypt a PPPoE username
+    decode      recover and verify an encrypted PPPoE username
+    heartbeat   keep a session alive
+    macopen     send an IPClient MACOpen packet
+
+Run `netkeeper <command> --help` for the options of each command.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|command| command.as_str()) {
+        Some("encode") => encode(&args[1..]),
+        Some("decode") => decode(&args[1..]),
+        Some("heartbeat") => heartbeat(&args[1..]),
+        Some("macopen") => macopen(&args[1..]),
+        Some("help") | Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    };
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+fn parse_options(options: &mut Options, args: &[String], brief: &str) -> Result<Matches, String> {
+    options.optflag("h", "help", "print this help");
+    let matches = try!(options.parse(args).map_err(|e| describe(&e)));
+    if matches.opt_present("help") {
+        println!("{}", options.usage(brief));
+        process::exit(0);
+    }
+    Ok(matches)
+}
+
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k",
+          feature="drcom", feature="ipclient"))]
+fn parse_opt<T>(matches: &Matches, name: &str) -> Result<Option<T>, String>
+    where T: FromStr
+{
+    match matches.opt_str(name) {
+        Some(value) => {
+            value.parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value {:?} of --{}", value, name))
+        }
+        None => Ok(None),
+    }
+}
+
+fn require_opt(matches: &Matches, name: &str) -> Result<String, String> {
+    matches.opt_str(name).ok_or_else(|| format!("missing --{}", name))
+}
+
+#[cfg(feature="drcom")]
+fn parse_mac_address(mac_address: &str) -> Result<[u8; 6], String> {
+    let parts: Vec<&str> = mac_address.split(|c| c == ':' || c == '-').collect();
+    let mut result = [0u8; 6];
+    if parts.len() != result.len() {
+        return Err(format!("invalid mac address {:?}", mac_address));
+    }
+    for (byte, part) in result.iter_mut().zip(parts) {
+        *byte = try!(u8::from_str_radix(part, 16)
+            .map_err(|_| format!("invalid mac address {:?}", mac_address)));
+    }
+    Ok(result)
+}
+
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k"))]
+fn unescape(account: &str) -> String {
+    account.replace("\\r", "\r").replace("\\n", "\n")
+}
+
+fn print_account(account: &str, escape: bool) {
+    if escape {
+        println!("{}", account.escape_default());
+    } else {
+        println!("{}", account);
+    }
+}
+
+/// Load the named configuration from `--config-file` or the built-in ones,
+/// then apply the parameters given as flags.
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+fn load_dialer<D>(matches: &Matches, default_name: &str, flags: &[&str]) -> Result<D, String>
+    where D: ConfigurableDialer
+{
+    let registry = match matches.opt_str("config-file") {
+        Some(path) => try!(ConfigurationRegistry::from_file(path).map_err(|e| describe(&e))),
+        None => ConfigurationRegistry::new(),
+    };
+    let name = matches.opt_str("config").unwrap_or_else(|| default_name.to_string());
+    let mut parameters = registry.parameters::<D>(&name).unwrap_or_default();
+    for flag in flags {
+        if let Some(value) = matches.opt_str(flag) {
+            parameters.insert(flag.replace('-', "_"), unescape(&value));
+        }
+    }
+    D::from_parameters(&parameters)
+        .ok_or_else(|| format!("incomplete {} configuration {:?}", D::section(), name))
+}
+
+fn encode(args: &[String]) -> CliResult {
+    let mut options = Options::new();
+    options.optopt("a", "algo", "netkeeper, singlenet, ghca or srun3k", "ALGO");
+    options.optopt("c", "config", "name of the configuration", "NAME");
+    options.optopt("f", "config-file", "TOML or JSON configuration file", "PATH");
+    options.optopt("t", "timestamp", "timestamp embedded in the username", "TIMESTAMP");
+    options.optopt("p", "password", "password, required by ghca", "PASSWORD");
+    options.optopt("", "share-key", "override the share key", "KEY");
+    options.optopt("", "secret-key", "override the secret key", "KEY");
+    options.optopt("", "key-table", "override the key table", "TABLE");
+    options.optopt("", "prefix", "override the prefix, \\r and \\n are unescaped", "PREFIX");
+    options.optopt("", "version", "override the client version", "VERSION");
+    options.optflag("e", "escape", "print control characters escaped");
+    let brief = "Usage: netkeeper encode [options] USERNAME";
+    let matches = try!(parse_options(&mut options, args, brief));
+
+    let username = try!(matches.free.first().ok_or("missing USERNAME"));
+    let algo = try!(require_opt(&matches, "algo"));
+    let encrypted = try!(encrypt_account(&matches, &algo, username));
+
+    print_account(&encrypted, matches.opt_present("escape"));
+    Ok(())
+}
+
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k"))]
+fn encrypt_account(matches: &Matches, algo: &str, username: &str) -> Result<String, String> {
+    let timestamp: Option<u32> = try!(parse_opt(matches, "timestamp"));
+    match algo {
+        #[cfg(feature="netkeeper")]
+        "netkeeper" => {
+            use netkeeper::netkeeper::dialer::NetkeeperDialer;
+            let dialer: NetkeeperDialer =
+                try!(load_dialer(matches, "zhejiang", &["share-key", "prefix"]));
+            Ok(dialer.encrypt_account(username, timestamp))
+        }
+        #[cfg(feature="singlenet")]
+        "singlenet" => {
+            use netkeeper::singlenet::dialer::SingleNetDialer;
+            let dialer: SingleNetDialer =
+                try!(load_dialer(matches, "hainan", &["share-key", "secret-key", "key-table"]));
+            Ok(dialer.encrypt_account(username, timestamp))
+        }
+        #[cfg(feature="ghca")]
+        "ghca" => {
+            use netkeeper::ghca::dialer::GhcaDialer;
+            let dialer: GhcaDialer =
+                try!(load_dialer(matches, "sichuan_mac", &["share-key", "prefix", "version"]));
+            let password = try!(require_opt(matches, "password"));
+            dialer.encrypt_account(username, &password, timestamp, timestamp)
+                .map_err(|e| describe(&e))
+        }
+        #[cfg(feature="srun3k")]
+        "srun3k" => {
+            use netkeeper::srun3k::dialer::{Srun3kDialer, Configuration};
+            if timestamp.is_some() {
+                return Err("srun3k usernames carry no timestamp".to_string());
+            }
+            let name = matches.opt_str("config").unwrap_or_else(|| "talimu".to_string());
+            let configuration = try!(Configuration::all()
+                .iter()
+                .find(|configuration| configuration.name() == name)
+                .ok_or_else(|| format!("unknown srun3k configuration {:?}", name)));
+            Ok(Srun3kDialer::new(Some(*configuration)).encrypt_account_v20(username))
+        }
+        _ => Err(format!("unsupported algorithm {:?}", algo)),
+    }
+}
+
+#[cfg(not(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k")))]
+fn encrypt_account(_: &Matches, algo: &str, _: &str) -> Result<String, String> {
+    Err(format!("unsupported algorithm {:?}", algo))
+}
+
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k"))]
+fn decode(args: &[String]) -> CliResult {
+    use netkeeper::detect::detect_configuration;
+
+    let mut options = Options::new();
+    options.optopt("p", "password", "password, required to verify ghca", "PASSWORD");
+    options.optopt("w", "window", "accepted timestamps, inclusive", "START:END");
+    options.optflag("e", "escape", "the username is given with \\r and \\n escaped");
+    let brief = "Usage: netkeeper decode [options] ENCRYPTED";
+    let matches = try!(parse_options(&mut options, args, brief));
+
+    let mut encrypted = try!(matches.free.first().ok_or("missing ENCRYPTED")).clone();
+    if matches.opt_present("escape") {
+        encrypted = unescape(&encrypted);
+    }
+    let window = match matches.opt_str("window") {
+        Some(window) => {
+            let parts: Vec<&str> = window.splitn(2, ':').collect();
+            match (parts.get(0).and_then(|s| s.parse().ok()),
+                   parts.get(1).and_then(|s| s.parse().ok())) {
+                (Some(start), Some(end)) => Some((start, end)),
+                _ => return Err(format!("invalid window {:?}", window)),
+            }
+        }
+        None => None,
+    };
+
+    let password = matches.opt_str("password");
+    let detections = detect_configuration(&encrypted,
+                                          password.as_ref().map(|p| p.as_str()),
+                                          window);
+    if detections.is_empty() {
+        return Err("no configuration matches".to_string());
+    }
+    for detection in detections {
+        println!("configuration: {:?}", detection.configuration);
+        println!("username: {}", detection.username);
+        if let Some(timestamp) = detection.timestamp {
+            println!("timestamp: {}", timestamp);
+        }
+        if let Some(pin) = detection.pin {
+            println!("pin: {}", pin.escape_default());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k")))]
+fn decode(_: &[String]) -> CliResult {
+    Err("no dialer is enabled in this build".to_string())
+}
+
+fn heartbeat_options() -> Options {
+    let mut options = Options::new();
+    options.optopt("s", "server", "address of the heartbeat server", "HOST:PORT");
+    options.optopt("b", "bind", "local address, defaults to 0.0.0.0:0", "ADDR");
+    options.optopt("i",
+                   "interval",
+                   "seconds between heartbeats, defaults to the protocol interval",
+                   "SECONDS");
+    options.optopt("n", "count", "stop after COUNT heartbeats", "COUNT");
+    options.optopt("",
+                   "max-failures",
+                   "give up after COUNT consecutive failures, retries forever by default",
+                   "COUNT");
+    options.optopt("u", "username", "username", "USERNAME");
+    options.optopt("p", "password", "password", "PASSWORD");
+    options.optopt("", "ip", "ip address of this host", "IP");
+    options.optopt("", "mac", "mac address of this host", "MAC");
+    options
+}
+
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet"))]
+fn connect(matches: &Matches) -> Result<UdpTransport, String> {
+    let bind = matches.opt_str("bind").unwrap_or_else(|| "0.0.0.0:0".to_string());
+    let server = try!(require_opt(matches, "server"));
+    let mut transport = try!(UdpTransport::connect(bind.as_str(), server.as_str())
+        .map_err(|e| describe(&e)));
+    {
+        use netkeeper::common::transport::Transport;
+        try!(transport.set_timeout(Some(Duration::from_secs(5))).map_err(|e| describe(&e)));
+    }
+    Ok(transport)
+}
+
+/// Keep `session` alive until `--count` heartbeats succeeded, logging in again
+/// with backoff after a failure. `report` is called after every step.
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet"))]
+fn run_daemon<S, F>(matches: &Matches, session: S, mut report: F) -> CliResult
+    where S: KeepAliveSession,
+          F: FnMut(&mut S)
+{
+    let interval: Option<u64> = try!(parse_opt(matches, "interval"));
+    let count: Option<usize> = try!(parse_opt(matches, "count"));
+    let max_failures: Option<u32> = try!(parse_opt(matches, "max-failures"));
+
+    let mut daemon = KeepAliveDaemon::new(session);
+    daemon.max_failures(max_failures).on_event(print_event);
+    let mut heartbeats = 0;
+    while count.map_or(true, |count| heartbeats < count) {
+        let mut delay = try!(daemon.step().map_err(|e| describe(&e)));
+        report(daemon.session_mut());
+        if daemon.is_logged_in() {
+            heartbeats += 1;
+            if let Some(interval) = interval {
+                delay = Duration::from_secs(interval);
+            }
+        }
+        if count.map_or(true, |count| heartbeats < count) {
+            thread::sleep(delay);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet"))]
+fn print_event(event: &DaemonEvent) {
+    match *event {
+        DaemonEvent::LoggedIn => println!("logged in"),
+        DaemonEvent::KeptAlive(rounds) => println!("heartbeat {} succeeded", rounds),
+        DaemonEvent::LoginFailed(e) => eprintln!("login failed: {}", describe(e)),
+        DaemonEvent::HeartbeatFailed(e) => eprintln!("heartbeat failed: {}", describe(e)),
+        DaemonEvent::Retrying(delay) => eprintln!("retrying in {} seconds", delay.as_secs()),
+    }
+}
+
+fn heartbeat(args: &[String]) -> CliResult {
+    let mut options = heartbeat_options();
+    options.optopt("", "secret", "singlenet authenticator secret", "SECRET");
+    options.optopt("", "client-type", "singlenet client type sent with --mac", "TYPE");
+    options.optopt("", "profile", "netkeeper heartbeat profile, defaults to zhejiang", "NAME");
+    let brief = "Usage: netkeeper heartbeat <drcom-wired|drcom-pppoe|netkeeper|singlenet> \
+                 [options]";
+    let matches = try!(parse_options(&mut options, args, brief));
+    let protocol = try!(matches.free.first().ok_or(brief)).clone();
+
+    match protocol.as_str() {
+        #[cfg(feature="drcom")]
+        "drcom-wired" => heartbeat_drcom_wired(&matches),
+        #[cfg(feature="drcom")]
+        "drcom-pppoe" => heartbeat_drcom_pppoe(&matches),
+        #[cfg(feature="netkeeper")]
+        "netkeeper" => heartbeat_netkeeper(&matches),
+        #[cfg(feature="singlenet")]
+        "singlenet" => heartbeat_singlenet(&matches),
+        _ => Err(format!("unsupported heartbeat protocol {:?}", protocol)),
+    }
+}
+
+#[cfg(feature="drcom")]
+fn heartbeat_drcom_wired(matches: &Matches) -> CliResult {
+    use netkeeper::drcom::wired::dialer::LoginAccount;
+    use netkeeper::drcom::wired::session::DrCOMWiredSession;
+
+    let username = try!(require_opt(matches, "username"));
+    let password = try!(require_opt(matches, "password"));
+    let ipaddress: Ipv4Addr = try!(try!(parse_opt(matches, "ip")).ok_or("missing --ip"));
+    let mut account = LoginAccount::new(&username, &password, [0u8; 4]);
+    account.ipaddresses(&[ipaddress]);
+    if let Some(mac_address) = matches.opt_str("mac") {
+        account.mac_address(try!(parse_mac_address(&mac_address)));
+    }
+
+    let session = DrCOMWiredSession::new(try!(connect(matches)), account, ipaddress);
+    run_daemon(matches, session, |_| {})
+}
+
+#[cfg(feature="drcom")]
+fn heartbeat_drcom_pppoe(matches: &Matches) -> CliResult {
+    use netkeeper::drcom::pppoe::session::DrCOMPPPoESession;
+
+    run_daemon(matches, DrCOMPPPoESession::new(try!(connect(matches))), |_| {})
+}
+
+#[cfg(feature="netkeeper")]
+fn heartbeat_netkeeper(matches: &Matches) -> CliResult {
+    use netkeeper::netkeeper::dialer::Configuration;
+    use netkeeper::netkeeper::frames::HeartbeatAccount;
+    use netkeeper::netkeeper::session::NetkeeperSession;
+
+    let name = matches.opt_str("profile").unwrap_or_else(|| "zhejiang".to_string());
+    let configuration = try!(Configuration::all()
+        .iter()
+        .find(|configuration| configuration.name() == name)
+        .ok_or_else(|| format!("unknown heartbeat profile {:?}", name)));
+    let profile = try!(configuration.heartbeat_profile()
+        .ok_or_else(|| format!("heartbeat aes key of {:?} is unknown", name)));
+
+    let username = try!(require_opt(matches, "username"));
+    let password = try!(require_opt(matches, "password"));
+    let ipaddress: Ipv4Addr = try!(try!(parse_opt(matches, "ip")).ok_or("missing --ip"));
+    let mac_address = try!(require_opt(matches, "mac"));
+    let account = HeartbeatAccount::new(&username, &password, ipaddress, &mac_address);
+
+    let session = try!(NetkeeperSession::new(try!(connect(matches)), profile, account)
+        .map_err(|e| describe(&e)));
+    run_daemon(matches, session, |session| {
+        for (title, content) in session.take_notices() {
+            println!("notice {}: {}", title, content);
+        }
+    })
+}
+
+#[cfg(feature="singlenet")]
+fn heartbeat_singlenet(matches: &Matches) -> CliResult {
+    use netkeeper::singlenet::heartbeater::{ClientFingerprint, PacketAuthenticator};
+    use netkeeper::singlenet::session::SingleNetSession;
+
+    let username = try!(require_opt(matches, "username"));
+    let ipaddress: Ipv4Addr = try!(try!(parse_opt(matches, "ip")).ok_or("missing --ip"));
+    let secret = matches.opt_str("secret").unwrap_or_else(|| "LLWLXA_TPSHARESECRET".to_string());
+    let fingerprint = ClientFingerprint {
+        client_type: try!(require_opt(matches, "client-type")),
+        mac_address: try!(require_opt(matches, "mac")),
+        ..Default::default()
+    };
+
+    let authenticator = PacketAuthenticator::new(&secret);
+    let session = SingleNetSession::new(try!(connect(matches)),
+                                        authenticator,
+                                        &username,
+                                        ipaddress,
+                                        fingerprint);
+    let mut last_update = None;
+    run_daemon(matches, session, |session| {
+        if session.update() != last_update.as_ref() {
+            last_update = session.update().cloned();
+            if let Some(ref update) = last_update {
+                println!("update {} available at {}", update.version, update.download_url);
+            }
+        }
+    })
+}
+
+#[cfg(feature="ipclient")]
+fn macopen(args: &[String]) -> CliResult {
+    use netkeeper::common::transport::Transport;
+    use netkeeper::ipclient::dialer::{Configuration, ISPCode, MACOpenPacket};
+
+    let mut options = heartbeat_options();
+    options.optopt("", "isp", "unicom, telecom or mobile", "ISP");
+    let matches = try!(parse_options(&mut options, args, "Usage: netkeeper macopen [options]"));
+
+    let username = try!(require_opt(&matches, "username"));
+    let ipaddress: Ipv4Addr = try!(try!(parse_opt(&matches, "ip")).ok_or("missing --ip"));
+    let mac_address = try!(require_opt(&matches, "mac"));
+    let isp = match matches.opt_str("isp").as_ref().map(|isp| isp.as_str()) {
+        Some("unicom") => ISPCode::CChinaUnicom,
+        Some("telecom") | None => ISPCode::CChinaTelecom,
+        Some("mobile") => ISPCode::CChinaMobile,
+        Some(isp) => return Err(format!("unknown isp {:?}", isp)),
+    };
+
+    let packet = MACOpenPacket::new(&username, ipaddress, &mac_address, isp);
+    let bytes = try!(packet.as_bytes(Configuration::GUET.hash_key()).map_err(|e| describe(&e)));
+    let bind = matches.opt_str("bind").unwrap_or_else(|| "0.0.0.0:0".to_string());
+    let server = try!(require_opt(&matches, "server"));
+    let mut transport = try!(UdpTransport::connect(bind.as_str(), server.as_str())
+        .map_err(|e| describe(&e)));
+    transport.send(&bytes).map_err(|e| describe(&e))
+}
+
+#[cfg(not(feature="ipclient"))]
+fn macopen(_: &[String]) -> CliResult {
+    Err("ipclient is not enabled in this build".to_string())
+}
@@ -0,0 +1,253 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use futures::{future, Async, Future, Poll};
+use tokio::io::{read_exact, AsyncRead};
+use tokio::net::UdpSocket;
+use tokio::timer::Delay;
+
+use error::{Error, Result};
+
+const MAX_PACKET_SIZE: usize = 4096;
+const DEFAULT_RETRIES: usize = 3;
+const DEFAULT_TIMEOUT: u64 = 5;
+
+pub type BoxFuture<T> = Box<Future<Item = T, Error = Error> + Send>;
+
+/// Non-blocking counterpart of `Transport`, polled from a tokio task.
+pub trait AsyncTransport {
+    fn poll_send(&mut self, bytes: &[u8]) -> Poll<(), io::Error>;
+    fn poll_recv(&mut self) -> Poll<Vec<u8>, io::Error>;
+}
+
+#[derive(Debug)]
+pub struct AsyncUdpTransport {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+}
+
+/// Send a request and wait for its response, resending the request every
+/// `timeout` without one.
+///
+/// Methods take the client by value and hand it back with the response, so
+/// that the returned futures can be spawned on any tokio executor.
+#[derive(Debug)]
+pub struct AsyncTransportClient<T: AsyncTransport> {
+    transport: T,
+    retries: usize,
+    timeout: Duration,
+}
+
+/// Future of `AsyncTransportClient::exchange`.
+#[derive(Debug)]
+pub struct Exchange<T: AsyncTransport> {
+    client: Option<AsyncTransportClient<T>>,
+    request: Vec<u8>,
+    attempts: usize,
+    sent: bool,
+    delay: Delay,
+}
+
+impl AsyncUdpTransport {
+    pub fn connect(local_addr: &SocketAddr, remote_addr: &SocketAddr) -> io::Result<Self> {
+        let socket = try!(UdpSocket::bind(local_addr));
+        try!(socket.connect(remote_addr));
+        Ok(Self::from_socket(socket))
+    }
+
+    pub fn from_socket(socket: UdpSocket) -> Self {
+        AsyncUdpTransport {
+            socket: socket,
+            buffer: vec![0u8; MAX_PACKET_SIZE],
+        }
+    }
+}
+
+impl AsyncTransport for AsyncUdpTransport {
+    fn poll_send(&mut self, bytes: &[u8]) -> Poll<(), io::Error> {
+        try_ready!(self.socket.poll_send(bytes));
+        Ok(Async::Ready(()))
+    }
+
+    fn poll_recv(&mut self) -> Poll<Vec<u8>, io::Error> {
+        let length = try_ready!(self.socket.poll_recv(&mut self.buffer));
+        Ok(Async::Ready(self.buffer[..length].to_vec()))
+    }
+}
+
+impl<T> AsyncTransportClient<T>
+    where T: AsyncTransport
+{
+    pub fn new(transport: T) -> Self {
+        AsyncTransportClient {
+            transport: transport,
+            retries: DEFAULT_RETRIES,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+        }
+    }
+
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn retries(&mut self, retries: usize) -> &mut Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+
+    pub fn exchange(self, request: Vec<u8>) -> Exchange<T> {
+        Exchange {
+            client: Some(self),
+            request: request,
+            attempts: 0,
+            sent: false,
+            delay: Delay::new(Instant::now()),
+        }
+    }
+}
+
+impl<T> AsyncTransportClient<T>
+    where T: AsyncTransport + Send + 'static
+{
+    /// Exchange `request` and parse the response with one of the blocking
+    /// `from_bytes` parsers.
+    pub fn request<P, F>(self, request: Vec<u8>, parse: F) -> BoxFuture<(Self, P)>
+        where F: FnOnce(&mut io::BufReader<&[u8]>) -> Result<P> + Send + 'static,
+              P: Send + 'static
+    {
+        Box::new(self.exchange(request).and_then(move |(client, response)| {
+            let mut buffer = io::BufReader::new(&response as &[u8]);
+            let packet = try!(parse(&mut buffer));
+            Ok((client, packet))
+        }))
+    }
+}
+
+impl<T> Future for Exchange<T>
+    where T: AsyncTransport
+{
+    type Item = (AsyncTransportClient<T>, Vec<u8>);
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Error> {
+        loop {
+            {
+                let client = self.client.as_mut().expect("poll an exchange after it completed");
+                if !self.sent {
+                    try_ready!(client.transport.poll_send(&self.request));
+                    self.sent = true;
+                    self.attempts += 1;
+                    self.delay.reset(Instant::now() + client.timeout);
+                }
+                if let Async::Ready(response) = try!(client.transport.poll_recv()) {
+                    let client = self.client.take().unwrap();
+                    return Ok(Async::Ready((client, response)));
+                }
+                try_ready!(self.delay
+                    .poll()
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+                if self.attempts > client.retries {
+                    return Err(Error::Timeout(self.attempts));
+                }
+            }
+            self.sent = false;
+        }
+    }
+}
+
+/// Read one packet from a stream, `packet_length` tells the total length of
+/// the packet from its first `header_length` bytes.
+pub fn read_packet<R, F>(reader: R,
+                         header_length: usize,
+                         packet_length: F)
+                         -> BoxFuture<(R, Vec<u8>)>
+    where R: AsyncRead + Send + 'static,
+          F: FnOnce(&[u8]) -> usize + Send + 'static
+{
+    Box::new(read_exact(reader, vec![0u8; header_length])
+        .and_then(move |(reader, header)| {
+            let length = packet_length(&header);
+            let body = if length < header_length {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "packet shorter than its header"))
+            } else {
+                Ok(vec![0u8; length - header_length])
+            };
+            future::result(body)
+                .and_then(move |body| read_exact(reader, body))
+                .map(move |(reader, body)| {
+                    let mut packet = header;
+                    packet.extend(body);
+                    (reader, packet)
+                })
+        })
+        .map_err(Error::IO))
+}
+
+#[test]
+fn test_read_packet() {
+    let stream: &[u8] = &[0, 4, 1, 2, 0, 3, 3, 0, 9];
+    let (stream, packet) = read_packet(stream, 2, |header| header[1] as usize).wait().unwrap();
+    assert_eq!(packet, vec![0, 4, 1, 2]);
+    let (stream, packet) = read_packet(stream, 2, |header| header[1] as usize).wait().unwrap();
+    assert_eq!(packet, vec![0, 3, 3]);
+    assert!(read_packet(stream, 2, |header| header[1] as usize).wait().is_err());
+    assert!(read_packet(&[0u8, 1][..], 2, |header| header[1] as usize).wait().is_err());
+}
+
+#[test]
+fn test_async_transport_client() {
+    use std::thread;
+    use tokio::runtime::current_thread::Runtime;
+
+    let server = ::std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let mut buffer = [0u8; 16];
+        // drop the first request, answer the retry
+        let (_, _) = server.recv_from(&mut buffer).unwrap();
+        let (length, peer) = server.recv_from(&mut buffer).unwrap();
+        server.send_to(&buffer[..length], peer).unwrap();
+    });
+
+    let local_addr = "127.0.0.1:0".parse().unwrap();
+    let transport = AsyncUdpTransport::connect(&local_addr, &server_addr).unwrap();
+    let mut client = AsyncTransportClient::new(transport);
+    client.timeout(Duration::from_millis(200));
+
+    let mut runtime = Runtime::new().unwrap();
+    let (_, response) = runtime.block_on(client.exchange(b"ping".to_vec())).unwrap();
+    assert_eq!(response, b"ping".to_vec());
+    handle.join().unwrap();
+
+    // a peer that never answers
+    let silent = ::std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let transport = AsyncUdpTransport::connect(&local_addr, &silent.local_addr().unwrap())
+        .unwrap();
+    let mut client = AsyncTransportClient::new(transport);
+    client.timeout(Duration::from_millis(100)).retries(1);
+    match runtime.block_on(client.exchange(b"ping".to_vec())) {
+        Err(Error::Timeout(2)) => {}
+        other => panic!("unexpected result {:?}", other.map(|(_, response)| response)),
+    }
+    silent.set_nonblocking(true).unwrap();
+    let mut buffer = [0u8; 16];
+    let mut requests = 0;
+    while silent.recv_from(&mut buffer).is_ok() {
+        requests += 1;
+    }
+    assert_eq!(requests, 2);
+}
@@ -0,0 +1,120 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use common::utils::current_timestamp;
+
+/// Source of the unix timestamps carried by encrypted usernames and
+/// heartbeat packets.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> u32;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+/// Always tells the same time, for tests and replaying captured packets.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock {
+    timestamp: u32,
+}
+
+/// Another clock shifted by `offset` seconds, to follow a server whose
+/// clock is ahead of or behind the local one.
+#[derive(Debug, Clone)]
+pub struct OffsetClock<C: Clock> {
+    clock: C,
+    offset: i64,
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> u32 {
+        current_timestamp()
+    }
+}
+
+impl FixedClock {
+    pub fn new(timestamp: u32) -> Self {
+        FixedClock { timestamp: timestamp }
+    }
+
+    /// Stopped at `timestamp`, or at the current time when it is `None`.
+    pub fn from_timestamp(timestamp: Option<u32>) -> Self {
+        Self::new(timestamp.unwrap_or_else(current_timestamp))
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> u32 {
+        self.timestamp
+    }
+}
+
+impl<C> OffsetClock<C>
+    where C: Clock
+{
+    pub fn new(clock: C, offset: i64) -> Self {
+        OffsetClock {
+            clock: clock,
+            offset: offset,
+        }
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Take the skew from a timestamp just reported by the server.
+    pub fn synchronize(&mut self, server_timestamp: u32) {
+        self.offset = server_timestamp as i64 - self.clock.now() as i64;
+    }
+}
+
+impl<C> Clock for OffsetClock<C>
+    where C: Clock
+{
+    fn now(&self) -> u32 {
+        (self.clock.now() as i64 + self.offset) as u32
+    }
+}
+
+impl<'a, C> Clock for &'a C
+    where C: Clock + ?Sized
+{
+    fn now(&self) -> u32 {
+        (**self).now()
+    }
+}
+
+impl<C> Clock for Box<C>
+    where C: Clock + ?Sized
+{
+    fn now(&self) -> u32 {
+        (**self).now()
+    }
+}
+
+impl<C> Clock for Arc<C>
+    where C: Clock + ?Sized
+{
+    fn now(&self) -> u32 {
+        (**self).now()
+    }
+}
+
+#[test]
+fn test_clocks() {
+    let fixed = FixedClock::new(1472483020);
+    assert_eq!(fixed.now(), 1472483020);
+    assert_eq!(FixedClock::from_timestamp(Some(1472483020)).now(), 1472483020);
+    assert!(FixedClock::from_timestamp(None).now() > 1472483020);
+
+    let mut skewed = OffsetClock::new(fixed, -20);
+    assert_eq!(skewed.now(), 1472483000);
+    skewed.synchronize(1472483080);
+    assert_eq!(skewed.offset(), 60);
+    assert_eq!(skewed.now(), 1472483080);
+
+    let shared: Arc<Clock> = Arc::new(skewed);
+    assert_eq!((&shared).now(), 1472483080);
+    assert!(SystemClock.now() > 1472483020);
+}
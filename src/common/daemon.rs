@@ -0,0 +1,291 @@
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+
+use error::{Error, Result};
+
+const DEFAULT_BACKOFF_INITIAL: u64 = 5;
+const DEFAULT_BACKOFF_MAXIMUM: u64 = 300;
+const DEFAULT_BACKOFF_FACTOR: u32 = 2;
+
+/// A protocol session the `KeepAliveDaemon` can drive.
+pub trait KeepAliveSession {
+    /// Establish the session, e.g. run the challenge and login steps. Called
+    /// again after any failure.
+    fn login(&mut self) -> Result<()>;
+
+    /// Run one heartbeat round of an established session.
+    fn keep_alive(&mut self) -> Result<()>;
+
+    /// Wait between two heartbeat rounds.
+    fn interval(&self) -> Duration;
+}
+
+#[derive(Debug)]
+pub enum DaemonEvent<'a> {
+    LoggedIn,
+    // Heartbeat round {} since the last login succeeded
+    KeptAlive(usize),
+    LoginFailed(&'a Error),
+    HeartbeatFailed(&'a Error),
+    // Log in again after {:?}
+    Retrying(Duration),
+}
+
+/// Exponential backoff, the n-th consecutive failure waits
+/// `initial * factor ^ (n - 1)`, capped at `maximum`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    maximum: Duration,
+    factor: u32,
+    failures: u32,
+}
+
+/// Keep a session alive: log in, run heartbeats at the session interval and
+/// log in again with exponential backoff whenever a step fails.
+///
+/// Status is reported through the `on_event` callback. Agents with their own
+/// event loop can call `step` and schedule the returned delay themselves
+/// instead of blocking in `run`.
+pub struct KeepAliveDaemon<S: KeepAliveSession> {
+    session: S,
+    backoff: Backoff,
+    max_failures: Option<u32>,
+    logged_in: bool,
+    rounds: usize,
+    observer: Option<Box<FnMut(&DaemonEvent) + Send>>,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, maximum: Duration) -> Self {
+        Backoff {
+            initial: initial,
+            maximum: maximum,
+            factor: DEFAULT_BACKOFF_FACTOR,
+            failures: 0,
+        }
+    }
+
+    pub fn factor(&mut self, factor: u32) -> &mut Self {
+        self.factor = factor;
+        self
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Record a failure and return how long to wait before retrying.
+    pub fn next_delay(&mut self) -> Duration {
+        let mut delay = self.initial;
+        for _ in 0..self.failures {
+            if delay >= self.maximum {
+                break;
+            }
+            delay = delay.checked_mul(self.factor).unwrap_or(self.maximum);
+        }
+        self.failures = self.failures.saturating_add(1);
+        cmp::min(delay, self.maximum)
+    }
+
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::new(Duration::from_secs(DEFAULT_BACKOFF_INITIAL),
+                     Duration::from_secs(DEFAULT_BACKOFF_MAXIMUM))
+    }
+}
+
+impl<S> KeepAliveDaemon<S>
+    where S: KeepAliveSession
+{
+    pub fn new(session: S) -> Self {
+        KeepAliveDaemon {
+            session: session,
+            backoff: Backoff::default(),
+            max_failures: None,
+            logged_in: false,
+            rounds: 0,
+            observer: None,
+        }
+    }
+
+    pub fn backoff(&mut self, backoff: Backoff) -> &mut Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up after `max_failures` consecutive failures, retry forever by default.
+    pub fn max_failures(&mut self, max_failures: Option<u32>) -> &mut Self {
+        self.max_failures = max_failures;
+        self
+    }
+
+    pub fn on_event<F>(&mut self, observer: F) -> &mut Self
+        where F: FnMut(&DaemonEvent) + Send + 'static
+    {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    pub fn session(&self) -> &S {
+        &self.session
+    }
+
+    pub fn session_mut(&mut self) -> &mut S {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> S {
+        self.session
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.logged_in
+    }
+
+    /// Log in if needed and run one heartbeat round, returning how long to
+    /// wait before the next step. Fails with the last error once
+    /// `max_failures` is reached.
+    pub fn step(&mut self) -> Result<Duration> {
+        if !self.logged_in {
+            if let Err(e) = self.session.login() {
+                self.notify(&DaemonEvent::LoginFailed(&e));
+                return self.retry(e);
+            }
+            self.logged_in = true;
+            self.rounds = 0;
+            self.notify(&DaemonEvent::LoggedIn);
+        }
+
+        if let Err(e) = self.session.keep_alive() {
+            self.logged_in = false;
+            self.notify(&DaemonEvent::HeartbeatFailed(&e));
+            return self.retry(e);
+        }
+        self.backoff.reset();
+        self.rounds += 1;
+        self.notify(&DaemonEvent::KeptAlive(self.rounds));
+        Ok(self.session.interval())
+    }
+
+    /// Step and sleep until `keep_running` returns false.
+    pub fn run<F>(&mut self, mut keep_running: F) -> Result<()>
+        where F: FnMut(&S) -> bool
+    {
+        while keep_running(&self.session) {
+            let delay = try!(self.step());
+            thread::sleep(delay);
+        }
+        Ok(())
+    }
+
+    fn retry(&mut self, e: Error) -> Result<Duration> {
+        if let Some(max_failures) = self.max_failures {
+            if self.backoff.failures() + 1 >= max_failures {
+                return Err(e);
+            }
+        }
+        let delay = self.backoff.next_delay();
+        self.notify(&DaemonEvent::Retrying(delay));
+        Ok(delay)
+    }
+
+    fn notify(&mut self, event: &DaemonEvent) {
+        if let Some(ref mut observer) = self.observer {
+            observer(event);
+        }
+    }
+}
+
+#[test]
+fn test_backoff() {
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(10));
+    let delays: Vec<u64> = (0..6).map(|_| backoff.next_delay().as_secs()).collect();
+    assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+    assert_eq!(backoff.failures(), 6);
+    backoff.reset();
+    assert_eq!(backoff.factor(3).next_delay(), Duration::from_secs(1));
+    assert_eq!(backoff.next_delay(), Duration::from_secs(3));
+}
+
+#[test]
+fn test_keep_alive_daemon() {
+    use std::sync::{Arc, Mutex};
+
+    struct ScriptedSession {
+        results: Vec<bool>,
+    }
+
+    impl ScriptedSession {
+        fn next(&mut self) -> Result<()> {
+            if self.results.remove(0) {
+                Ok(())
+            } else {
+                Err(Error::Timeout(1))
+            }
+        }
+    }
+
+    impl KeepAliveSession for ScriptedSession {
+        fn login(&mut self) -> Result<()> {
+            self.next()
+        }
+
+        fn keep_alive(&mut self) -> Result<()> {
+            self.next()
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::from_secs(20)
+        }
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    // login fails, login, two heartbeats, heartbeat fails, login, heartbeat
+    let session = ScriptedSession { results: vec![false, true, true, true, false, true, true] };
+    let mut daemon = KeepAliveDaemon::new(session);
+    {
+        let events = events.clone();
+        daemon.backoff(Backoff::new(Duration::from_secs(1), Duration::from_secs(60)))
+            .on_event(move |event| events.lock().unwrap().push(format!("{:?}", event)));
+    }
+
+    assert_eq!(daemon.step().unwrap(), Duration::from_secs(1));
+    assert!(!daemon.is_logged_in());
+    assert_eq!(daemon.step().unwrap(), Duration::from_secs(20));
+    assert_eq!(daemon.step().unwrap(), Duration::from_secs(20));
+    assert_eq!(daemon.step().unwrap(), Duration::from_secs(1));
+    assert_eq!(daemon.step().unwrap(), Duration::from_secs(20));
+    assert!(daemon.is_logged_in());
+    assert_eq!(*events.lock().unwrap(),
+               vec!["LoginFailed(Timeout(1))",
+                    "Retrying(1s)",
+                    "LoggedIn",
+                    "KeptAlive(1)",
+                    "KeptAlive(2)",
+                    "HeartbeatFailed(Timeout(1))",
+                    "Retrying(1s)",
+                    "LoggedIn",
+                    "KeptAlive(1)"]);
+
+    let session = ScriptedSession { results: vec![false, false, false] };
+    let mut daemon = KeepAliveDaemon::new(session);
+    daemon.backoff(Backoff::new(Duration::from_secs(0), Duration::from_secs(0)))
+        .max_failures(Some(2));
+    assert!(daemon.step().is_ok());
+    assert!(daemon.step().is_err());
+    assert_eq!(daemon.into_session().results.len(), 1);
+
+    // a daemon with an observer runs on a worker thread
+    let session = ScriptedSession { results: vec![true, true] };
+    let mut daemon = KeepAliveDaemon::new(session);
+    daemon.on_event(|_| {});
+    let worker = thread::spawn(move || daemon.step().is_ok());
+    assert!(worker.join().unwrap());
+}
@@ -1,11 +1,65 @@
+use std::{error, fmt, result};
+use std::collections::BTreeMap;
+
 pub trait Dialer {
     type C;
 
     fn load_from_config(config: Self::C) -> Self;
 }
 
+pub type DialerParameters = BTreeMap<String, String>;
+
+/// Dialer whose parameters can be loaded by name from a
+/// `common::registry::ConfigurationRegistry`.
+pub trait ConfigurableDialer: Dialer + Sized {
+    /// Section of the dialer in configuration files.
+    fn section() -> &'static str;
+
+    /// Parameters of the built-in configurations, used when no file overrides them.
+    fn builtin_parameters() -> Vec<(&'static str, DialerParameters)>;
+
+    fn from_parameters(parameters: &DialerParameters) -> Option<Self>;
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    UnexpectedPrefix(String),
+    InvalidFormat(String),
+    PinMismatch(String),
+}
+
+pub type DecodeResult<T> = result::Result<T, DecodeError>;
+
+/// Fields recovered from an encrypted dialer username, `configuration` is only
+/// set once the PIN has been verified against it.
+#[derive(Debug, PartialEq)]
+pub struct DecodedAccount<C> {
+    pub username: String,
+    pub timestamp: Option<u32>,
+    pub pin: Option<String>,
+    pub configuration: Option<C>,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnexpectedPrefix(ref account) => {
+                write!(f, "unexpected prefix of account {:?}", account)
+            }
+            DecodeError::InvalidFormat(ref account) => {
+                write!(f, "invalid encrypted account {:?}", account)
+            }
+            DecodeError::PinMismatch(ref account) => {
+                write!(f, "pin of account {:?} matches no configuration", account)
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
 pub fn load_dialer<D>(config: D::C) -> D
     where D: Dialer
 {
     D::load_from_config(config)
-}
\ No newline at end of file
+}
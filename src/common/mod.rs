@@ -1,4 +1,12 @@
 pub mod dialer;
 pub mod reader;
 pub mod utils;
-pub mod bytes;
\ No newline at end of file
+pub mod clock;
+pub mod bytes;
+pub mod transport;
+#[cfg(feature="async")]
+pub mod async_transport;
+pub mod registry;
+pub mod daemon;
+pub mod server;
+pub mod pcap;
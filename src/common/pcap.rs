@@ -0,0 +1,615 @@
+use std::{error, fmt, io, result};
+use std::fs::File;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::Path;
+use std::time::Duration;
+
+use byteorder::{BigEndian, LittleEndian, NetworkEndian, ByteOrder};
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b23c4d;
+const PCAP_HEADER_LENGTH: usize = 24;
+const PCAP_RECORD_HEADER_LENGTH: usize = 16;
+// the default snaplen of tcpdump, lengths read from a file are trusted up to it
+const MAX_RECORD_LENGTH: usize = 262144;
+// room for the block headers and options around a record
+const MAX_BLOCK_LENGTH: usize = MAX_RECORD_LENGTH + 4096;
+
+const PCAPNG_SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+const PCAPNG_INTERFACE_DESCRIPTION_BLOCK: u32 = 0x1;
+const PCAPNG_SIMPLE_PACKET_BLOCK: u32 = 0x3;
+const PCAPNG_ENHANCED_PACKET_BLOCK: u32 = 0x6;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const ETHERTYPE_PPPOE_SESSION: u16 = 0x8864;
+const PPP_PROTOCOL_IPV4: u16 = 0x0021;
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+#[derive(Debug)]
+pub enum PcapError {
+    IO(io::Error),
+    // Unknown file magic number {:#x}
+    UnknownMagicNumber(u32),
+    // Truncated {}
+    Truncated(&'static str),
+    // Packet of interface {} comes before its description
+    UnknownInterface(u32),
+    // {} of {} bytes exceeds the length limit
+    Oversized(&'static str, usize),
+}
+
+type PcapResult<T> = result::Result<T, PcapError>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkType {
+    Null,
+    Ethernet,
+    Raw,
+    LinuxSLL,
+    Unknown(u32),
+}
+
+/// A captured frame, `timestamp` is the time since the UNIX epoch.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub timestamp: Duration,
+    pub link_type: LinkType,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransportProtocol {
+    Udp,
+    Tcp,
+}
+
+/// Payload of an IPv4 UDP datagram or TCP segment.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub protocol: TransportProtocol,
+    pub source: SocketAddrV4,
+    pub destination: SocketAddrV4,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Interface {
+    link_type: LinkType,
+    ticks_per_second: u64,
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap {
+        big_endian: bool,
+        ticks_per_second: u64,
+        link_type: LinkType,
+        max_length: usize,
+    },
+    PcapNG {
+        big_endian: bool,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// Reads the records of a pcap or pcapng capture, as written by tcpdump or
+/// Wireshark.
+#[derive(Debug)]
+pub struct PcapReader<R> {
+    input: R,
+    format: Format,
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PcapError::IO(_) => write!(f, "io error"),
+            PcapError::UnknownMagicNumber(magic) => {
+                write!(f, "unknown file magic number {:#x}", magic)
+            }
+            PcapError::Truncated(what) => write!(f, "truncated {}", what),
+            PcapError::UnknownInterface(id) => {
+                write!(f, "packet of interface {} comes before its description", id)
+            }
+            PcapError::Oversized(what, length) => {
+                write!(f, "{} of {} bytes exceeds the length limit", what, length)
+            }
+        }
+    }
+}
+
+impl error::Error for PcapError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            PcapError::IO(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl LinkType {
+    fn from_u32(value: u32) -> Self {
+        match value {
+            0 => LinkType::Null,
+            1 => LinkType::Ethernet,
+            101 | 228 => LinkType::Raw,
+            113 => LinkType::LinuxSLL,
+            _ => LinkType::Unknown(value),
+        }
+    }
+}
+
+impl PcapReader<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> PcapResult<Self> {
+        Self::new(try!(File::open(path).map_err(PcapError::IO)))
+    }
+}
+
+impl<R> PcapReader<R>
+    where R: io::Read
+{
+    /// Read the file header, the format is detected from its magic number.
+    pub fn new(mut input: R) -> PcapResult<Self> {
+        let mut magic_bytes = [0u8; 4];
+        if !try!(read_block(&mut input, &mut magic_bytes, "file header")) {
+            return Err(PcapError::Truncated("file header"));
+        }
+
+        let format;
+        if LittleEndian::read_u32(&magic_bytes) == PCAPNG_SECTION_HEADER_BLOCK {
+            let big_endian = try!(read_section_header(&mut input));
+            format = Format::PcapNG {
+                big_endian: big_endian,
+                interfaces: Vec::new(),
+            };
+        } else {
+            let (big_endian, ticks_per_second) = match (LittleEndian::read_u32(&magic_bytes),
+                                                        BigEndian::read_u32(&magic_bytes)) {
+                (PCAP_MAGIC_MICROS, _) => (false, 1_000_000),
+                (PCAP_MAGIC_NANOS, _) => (false, 1_000_000_000),
+                (_, PCAP_MAGIC_MICROS) => (true, 1_000_000),
+                (_, PCAP_MAGIC_NANOS) => (true, 1_000_000_000),
+                (magic, _) => return Err(PcapError::UnknownMagicNumber(magic)),
+            };
+            let mut header = [0u8; PCAP_HEADER_LENGTH - 4];
+            if !try!(read_block(&mut input, &mut header, "file header")) {
+                return Err(PcapError::Truncated("file header"));
+            }
+            let snaplen = read_u32(&header[12..16], big_endian) as usize;
+            format = Format::Pcap {
+                big_endian: big_endian,
+                ticks_per_second: ticks_per_second,
+                link_type: LinkType::from_u32(read_u32(&header[16..20], big_endian)),
+                max_length: match snaplen {
+                    0 => MAX_RECORD_LENGTH,
+                    snaplen => ::std::cmp::min(snaplen, MAX_RECORD_LENGTH),
+                },
+            };
+        }
+
+        Ok(PcapReader {
+            input: input,
+            format: format,
+        })
+    }
+
+    /// The next captured frame, `None` at the end of the file.
+    pub fn next_record(&mut self) -> PcapResult<Option<Record>> {
+        match self.format {
+            Format::Pcap { big_endian, ticks_per_second, link_type, max_length } => {
+                let mut header = [0u8; PCAP_RECORD_HEADER_LENGTH];
+                if !try!(read_block(&mut self.input, &mut header, "record header")) {
+                    return Ok(None);
+                }
+                let seconds = read_u32(&header[0..4], big_endian) as u64;
+                let fraction = read_u32(&header[4..8], big_endian) as u64;
+                let captured_length = read_u32(&header[8..12], big_endian) as usize;
+                if captured_length > max_length {
+                    return Err(PcapError::Oversized("record", captured_length));
+                }
+
+                let mut data = vec![0u8; captured_length];
+                if !try!(read_block(&mut self.input, &mut data, "record")) {
+                    return Err(PcapError::Truncated("record"));
+                }
+                Ok(Some(Record {
+                    timestamp: Duration::from_secs(seconds) +
+                               ticks_to_duration(fraction, ticks_per_second),
+                    link_type: link_type,
+                    data: data,
+                }))
+            }
+            Format::PcapNG { .. } => self.next_pcapng_record(),
+        }
+    }
+
+    fn next_pcapng_record(&mut self) -> PcapResult<Option<Record>> {
+        loop {
+            let mut header = [0u8; 8];
+            if !try!(read_block(&mut self.input, &mut header, "block header")) {
+                return Ok(None);
+            }
+            let big_endian = match self.format {
+                Format::PcapNG { big_endian, .. } => big_endian,
+                Format::Pcap { .. } => unreachable!(),
+            };
+
+            if LittleEndian::read_u32(&header[0..4]) == PCAPNG_SECTION_HEADER_BLOCK {
+                // a new section, which may switch the byte order and drops
+                // the interfaces of the previous one
+                let big_endian = try!(read_section_header_after(&mut self.input, &header[4..8]));
+                self.format = Format::PcapNG {
+                    big_endian: big_endian,
+                    interfaces: Vec::new(),
+                };
+                continue;
+            }
+
+            let block_type = read_u32(&header[0..4], big_endian);
+            let total_length = read_u32(&header[4..8], big_endian) as usize;
+            if total_length < 12 {
+                return Err(PcapError::Truncated("block"));
+            }
+            if total_length > MAX_BLOCK_LENGTH {
+                return Err(PcapError::Oversized("block", total_length));
+            }
+            // the block body and the trailing copy of the length
+            let mut body = vec![0u8; total_length - 8];
+            if !try!(read_block(&mut self.input, &mut body, "block")) {
+                return Err(PcapError::Truncated("block"));
+            }
+            let body_length = body.len() - 4;
+            body.truncate(body_length);
+
+            let interfaces = match self.format {
+                Format::PcapNG { ref mut interfaces, .. } => interfaces,
+                Format::Pcap { .. } => unreachable!(),
+            };
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION_BLOCK => {
+                    interfaces.push(try!(read_interface(&body, big_endian)));
+                }
+                PCAPNG_ENHANCED_PACKET_BLOCK => {
+                    if body.len() < 20 {
+                        return Err(PcapError::Truncated("enhanced packet block"));
+                    }
+                    let interface_id = read_u32(&body[0..4], big_endian);
+                    let interface = match interfaces.get(interface_id as usize) {
+                        Some(interface) => interface,
+                        None => return Err(PcapError::UnknownInterface(interface_id)),
+                    };
+                    let ticks = (read_u32(&body[4..8], big_endian) as u64) << 32 |
+                                read_u32(&body[8..12], big_endian) as u64;
+                    let captured_length = read_u32(&body[12..16], big_endian) as usize;
+                    if body.len() < 20 + captured_length {
+                        return Err(PcapError::Truncated("enhanced packet block"));
+                    }
+                    return Ok(Some(Record {
+                        timestamp: ticks_to_duration(ticks, interface.ticks_per_second),
+                        link_type: interface.link_type,
+                        data: body[20..20 + captured_length].to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET_BLOCK => {
+                    if body.len() < 4 {
+                        return Err(PcapError::Truncated("simple packet block"));
+                    }
+                    let interface = match interfaces.first() {
+                        Some(interface) => interface,
+                        None => return Err(PcapError::UnknownInterface(0)),
+                    };
+                    let original_length = read_u32(&body[0..4], big_endian) as usize;
+                    let captured_length = ::std::cmp::min(original_length, body.len() - 4);
+                    return Ok(Some(Record {
+                        timestamp: Duration::from_secs(0),
+                        link_type: interface.link_type,
+                        data: body[4..4 + captured_length].to_vec(),
+                    }));
+                }
+                // statistics, name resolution and other blocks
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R> Iterator for PcapReader<R>
+    where R: io::Read
+{
+    type Item = PcapResult<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl Record {
+    /// Decode the link, IPv4 and UDP or TCP headers. Fragmented datagrams and
+    /// other protocols give `None`.
+    pub fn segment(&self) -> Option<Segment> {
+        let packet = match self.link_type {
+            LinkType::Ethernet => ethernet_payload(&self.data),
+            LinkType::Raw => Some(&self.data[..]),
+            LinkType::Null => {
+                // the address family, in the byte order of the capturing host
+                if self.data.len() >= 4 &&
+                   (LittleEndian::read_u32(&self.data[..4]) == 2 ||
+                    BigEndian::read_u32(&self.data[..4]) == 2) {
+                    Some(&self.data[4..])
+                } else {
+                    None
+                }
+            }
+            LinkType::LinuxSLL => {
+                if self.data.len() >= 16 &&
+                   NetworkEndian::read_u16(&self.data[14..16]) == ETHERTYPE_IPV4 {
+                    Some(&self.data[16..])
+                } else {
+                    None
+                }
+            }
+            LinkType::Unknown(_) => None,
+        };
+        packet.and_then(ipv4_segment)
+    }
+}
+
+fn ethernet_payload(frame: &[u8]) -> Option<&[u8]> {
+    let mut offset = 12;
+    loop {
+        if frame.len() < offset + 2 {
+            return None;
+        }
+        match NetworkEndian::read_u16(&frame[offset..offset + 2]) {
+            ETHERTYPE_VLAN | ETHERTYPE_QINQ => offset += 4,
+            ETHERTYPE_IPV4 => return Some(&frame[offset + 2..]),
+            ETHERTYPE_PPPOE_SESSION => {
+                // 6 bytes of PPPoE header then the PPP protocol
+                let ppp = offset + 2 + 6;
+                if frame.len() < ppp + 2 ||
+                   NetworkEndian::read_u16(&frame[ppp..ppp + 2]) != PPP_PROTOCOL_IPV4 {
+                    return None;
+                }
+                return Some(&frame[ppp + 2..]);
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn ipv4_segment(packet: &[u8]) -> Option<Segment> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 {
+        return None;
+    }
+    let header_length = (packet[0] & 0x0f) as usize * 4;
+    let total_length = NetworkEndian::read_u16(&packet[2..4]) as usize;
+    // more fragments flag or a fragment offset
+    if NetworkEndian::read_u16(&packet[6..8]) & 0x3fff != 0 || header_length < 20 ||
+       total_length < header_length || packet.len() < total_length {
+        return None;
+    }
+    let source = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    let destination = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+    let transport = &packet[header_length..total_length];
+
+    let (protocol, payload_offset, payload_end) = match packet[9] {
+        IP_PROTOCOL_UDP if transport.len() >= 8 => {
+            let length = NetworkEndian::read_u16(&transport[4..6]) as usize;
+            if length < 8 || length > transport.len() {
+                return None;
+            }
+            (TransportProtocol::Udp, 8, length)
+        }
+        IP_PROTOCOL_TCP if transport.len() >= 20 => {
+            let offset = (transport[12] >> 4) as usize * 4;
+            if offset < 20 || offset > transport.len() {
+                return None;
+            }
+            (TransportProtocol::Tcp, offset, transport.len())
+        }
+        _ => return None,
+    };
+
+    Some(Segment {
+        protocol: protocol,
+        source: SocketAddrV4::new(source, NetworkEndian::read_u16(&transport[0..2])),
+        destination: SocketAddrV4::new(destination, NetworkEndian::read_u16(&transport[2..4])),
+        payload: transport[payload_offset..payload_end].to_vec(),
+    })
+}
+
+/// Fill `buffer`, returns false if the input ends before the first byte.
+fn read_block<R>(input: &mut R, buffer: &mut [u8], what: &'static str) -> PcapResult<bool>
+    where R: io::Read
+{
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(PcapError::Truncated(what)),
+            Ok(length) => filled += length,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(PcapError::IO(e)),
+        }
+    }
+    Ok(true)
+}
+
+/// Read a section header block whose type is already consumed, returns
+/// whether the section is big endian.
+fn read_section_header<R>(input: &mut R) -> PcapResult<bool>
+    where R: io::Read
+{
+    let mut length_bytes = [0u8; 4];
+    if !try!(read_block(input, &mut length_bytes, "section header block")) {
+        return Err(PcapError::Truncated("section header block"));
+    }
+    read_section_header_after(input, &length_bytes)
+}
+
+fn read_section_header_after<R>(input: &mut R, length_bytes: &[u8]) -> PcapResult<bool>
+    where R: io::Read
+{
+    let mut magic_bytes = [0u8; 4];
+    if !try!(read_block(input, &mut magic_bytes, "section header block")) {
+        return Err(PcapError::Truncated("section header block"));
+    }
+    let big_endian = match LittleEndian::read_u32(&magic_bytes) {
+        PCAPNG_BYTE_ORDER_MAGIC => false,
+        _ if BigEndian::read_u32(&magic_bytes) == PCAPNG_BYTE_ORDER_MAGIC => true,
+        magic => return Err(PcapError::UnknownMagicNumber(magic)),
+    };
+
+    let total_length = read_u32(length_bytes, big_endian) as usize;
+    if total_length < 12 {
+        return Err(PcapError::Truncated("section header block"));
+    }
+    if total_length > MAX_BLOCK_LENGTH {
+        return Err(PcapError::Oversized("section header block", total_length));
+    }
+    // versions, section length, options and the trailing length
+    let mut rest = vec![0u8; total_length - 12];
+    if !rest.is_empty() && !try!(read_block(input, &mut rest, "section header block")) {
+        return Err(PcapError::Truncated("section header block"));
+    }
+    Ok(big_endian)
+}
+
+fn read_interface(body: &[u8], big_endian: bool) -> PcapResult<Interface> {
+    if body.len() < 8 {
+        return Err(PcapError::Truncated("interface description block"));
+    }
+    let link_type = LinkType::from_u32(read_u16(&body[0..2], big_endian) as u32);
+
+    let mut ticks_per_second = 1_000_000u64;
+    let mut options = &body[8..];
+    while options.len() >= 4 {
+        let code = read_u16(&options[0..2], big_endian);
+        let length = read_u16(&options[2..4], big_endian) as usize;
+        if options.len() < 4 + length {
+            break;
+        }
+        if code == PCAPNG_OPTION_IF_TSRESOL && length >= 1 {
+            let resolution = options[4];
+            let exponent = (resolution & 0x7f) as u32;
+            ticks_per_second = if resolution & 0x80 == 0 {
+                10u64.checked_pow(exponent).unwrap_or(ticks_per_second)
+            } else {
+                2u64.checked_pow(exponent).unwrap_or(ticks_per_second)
+            };
+        }
+        // values are padded to 32 bits
+        let padded_length = (length + 3) / 4 * 4;
+        if options.len() < 4 + padded_length {
+            break;
+        }
+        options = &options[4 + padded_length..];
+    }
+
+    Ok(Interface {
+        link_type: link_type,
+        ticks_per_second: ticks_per_second,
+    })
+}
+
+fn ticks_to_duration(ticks: u64, ticks_per_second: u64) -> Duration {
+    let nanos = (ticks % ticks_per_second) as f64 * 1e9 / ticks_per_second as f64;
+    Duration::new(ticks / ticks_per_second, nanos as u32)
+}
+
+fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    if big_endian {
+        BigEndian::read_u16(bytes)
+    } else {
+        LittleEndian::read_u16(bytes)
+    }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    if big_endian {
+        BigEndian::read_u32(bytes)
+    } else {
+        LittleEndian::read_u32(bytes)
+    }
+}
+
+#[test]
+fn test_pcap_reader() {
+    let capture: &[u8] = include_bytes!("../../tests/fixtures/drcom_wired.pcap");
+    let records: Vec<Record> = PcapReader::new(capture).unwrap().map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 9);
+    assert_eq!(records[1].link_type, LinkType::Ethernet);
+    assert_eq!(records[1].timestamp, Duration::new(1476316800, 15_000_000));
+
+    let segment = records[0].segment().unwrap();
+    assert_eq!(segment.protocol, TransportProtocol::Udp);
+    assert_eq!(segment.source, "10.30.22.17:61440".parse().unwrap());
+    assert_eq!(segment.destination, "10.100.61.3:61440".parse().unwrap());
+    assert_eq!(segment.payload[0], 0x01);
+
+    let truncated = &capture[..capture.len() - 1];
+    let results: Vec<PcapResult<Record>> = PcapReader::new(truncated).unwrap().collect();
+    match results.last() {
+        Some(&Err(PcapError::Truncated(_))) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match PcapReader::new(&b"not a capture"[..]) {
+        Err(PcapError::UnknownMagicNumber(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // a record longer than the snaplen of the file is refused before reading it
+    let mut oversized = capture[..PCAP_HEADER_LENGTH + PCAP_RECORD_HEADER_LENGTH].to_vec();
+    LittleEndian::write_u32(&mut oversized[16..20], 1500);
+    LittleEndian::write_u32(&mut oversized[32..36], 1501);
+    match PcapReader::new(&oversized[..]).unwrap().next_record() {
+        Err(PcapError::Oversized("record", 1501)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    LittleEndian::write_u32(&mut oversized[16..20], 0);
+    LittleEndian::write_u32(&mut oversized[32..36], 0xffffffff);
+    match PcapReader::new(&oversized[..]).unwrap().next_record() {
+        Err(PcapError::Oversized("record", _)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_pcapng_reader() {
+    // nanosecond timestamps and PPPoE session frames
+    let capture: &[u8] = include_bytes!("../../tests/fixtures/drcom_pppoe.pcapng");
+    let records: Vec<Record> = PcapReader::new(capture).unwrap().map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 9);
+    assert_eq!(records[1].timestamp, Duration::new(1476316800, 2_500_000));
+    let segment = records[0].segment().unwrap();
+    assert_eq!(segment.destination, "172.16.0.1:61440".parse().unwrap());
+    assert_eq!(segment.payload, vec![7, 0, 8, 0, 1, 0, 0, 0]);
+
+    let capture: &[u8] = include_bytes!("../../tests/fixtures/heartbeat.pcapng");
+    let records: Vec<Record> = PcapReader::new(capture).unwrap().map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[4].timestamp, Duration::new(1476316860, 200_000_000));
+    assert_eq!(records[1].segment().unwrap().destination.port(), 53);
+
+    let mut oversized = capture.to_vec();
+    let section_length = LittleEndian::read_u32(&oversized[4..8]) as usize;
+    LittleEndian::write_u32(&mut oversized[section_length + 4..section_length + 8], 0x7fffffff);
+    match PcapReader::new(&oversized[..]).unwrap().next_record() {
+        Err(PcapError::Oversized("block", 0x7fffffff)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    LittleEndian::write_u32(&mut oversized[4..8], 0x7fffffff);
+    match PcapReader::new(&oversized[..]) {
+        Err(PcapError::Oversized("section header block", 0x7fffffff)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
@@ -1,4 +1,4 @@
-use std::io;
+use std::{io, error, fmt};
 
 #[derive(Debug)]
 pub enum ReadBytesError {
@@ -8,6 +8,26 @@ pub enum ReadBytesError {
     IOError(io::Error),
 }
 
+impl fmt::Display for ReadBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadBytesError::LengthMismatch(expect, got) => {
+                write!(f, "expect length {}, got {}", expect, got)
+            }
+            ReadBytesError::IOError(_) => write!(f, "io error"),
+        }
+    }
+}
+
+impl error::Error for ReadBytesError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            ReadBytesError::IOError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 pub trait ReaderHelper: io::Read {
     fn read_bytes(&mut self, required_length: usize) -> Result<Vec<u8>, ReadBytesError> {
         let mut bytes_container: Vec<u8> = vec![0; required_length];
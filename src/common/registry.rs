@@ -0,0 +1,275 @@
+use std::{error, fmt, io, result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use rustc_serialize::json::{self, Json};
+#[cfg(feature="toml")]
+use toml;
+
+use common::dialer::{ConfigurableDialer, DialerParameters};
+
+#[derive(Debug)]
+pub enum RegistryError {
+    IO(io::Error),
+    JsonParseError(json::ParserError),
+    TomlParseError(String),
+    // Expect a table of string parameters at {}
+    UnexpectedValue(String),
+}
+
+type RegistryResult<T> = result::Result<T, RegistryError>;
+
+/// Dialer parameters keyed by section and name, loaded from TOML or JSON files:
+///
+/// ```toml
+/// [netkeeper.zhejiang]
+/// share_key = "zjxinlisx01"
+/// prefix = "\r\n"
+/// ```
+///
+/// Names missing from the files fall back to the built-in configurations. TOML
+/// needs the `toml` feature, which is on by default.
+#[derive(Debug, Default)]
+pub struct ConfigurationRegistry {
+    sections: BTreeMap<String, BTreeMap<String, DialerParameters>>,
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistryError::IO(_) => write!(f, "io error"),
+            RegistryError::JsonParseError(_) => write!(f, "parse json failed"),
+            RegistryError::TomlParseError(_) => write!(f, "parse toml failed"),
+            RegistryError::UnexpectedValue(ref path) => {
+                write!(f, "expect a table of string parameters at {}", path)
+            }
+        }
+    }
+}
+
+impl error::Error for RegistryError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            RegistryError::IO(ref e) => Some(e),
+            RegistryError::JsonParseError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl ConfigurationRegistry {
+    pub fn new() -> Self {
+        ConfigurationRegistry::default()
+    }
+
+    pub fn from_json_str(content: &str) -> RegistryResult<Self> {
+        let root = try!(Json::from_str(content).map_err(RegistryError::JsonParseError));
+        let mut registry = Self::new();
+        for (section, entries) in try!(json_object(&root, "")) {
+            for (name, parameters) in try!(json_object(entries, section)) {
+                let path = format!("{}.{}", section, name);
+                let mut values = DialerParameters::new();
+                for (key, value) in try!(json_object(parameters, &path)) {
+                    let value = match value.as_string() {
+                        Some(value) => value,
+                        None => {
+                            let path = format!("{}.{}", path, key);
+                            return Err(RegistryError::UnexpectedValue(path));
+                        }
+                    };
+                    values.insert(key.to_string(), value.to_string());
+                }
+                registry.insert(section, name, values);
+            }
+        }
+        Ok(registry)
+    }
+
+    #[cfg(feature="toml")]
+    pub fn from_toml_str(content: &str) -> RegistryResult<Self> {
+        let mut parser = toml::Parser::new(content);
+        let root = match parser.parse() {
+            Some(root) => toml::Value::Table(root),
+            None => {
+                let errors: Vec<String> = parser.errors.iter().map(|e| e.to_string()).collect();
+                return Err(RegistryError::TomlParseError(errors.join(", ")));
+            }
+        };
+
+        let mut registry = Self::new();
+        for (section, entries) in try!(toml_table(&root, "")) {
+            for (name, parameters) in try!(toml_table(entries, section)) {
+                let path = format!("{}.{}", section, name);
+                let mut values = DialerParameters::new();
+                for (key, value) in try!(toml_table(parameters, &path)) {
+                    let value = match value.as_str() {
+                        Some(value) => value,
+                        None => {
+                            let path = format!("{}.{}", path, key);
+                            return Err(RegistryError::UnexpectedValue(path));
+                        }
+                    };
+                    values.insert(key.to_string(), value.to_string());
+                }
+                registry.insert(section, name, values);
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Load `*.toml` files as TOML and anything else as JSON.
+    pub fn from_file<P>(path: P) -> RegistryResult<Self>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut content = String::new();
+        {
+            let mut file = try!(File::open(path).map_err(RegistryError::IO));
+            try!(file.read_to_string(&mut content).map_err(RegistryError::IO));
+        }
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature="toml")]
+            Some("toml") => Self::from_toml_str(&content),
+            #[cfg(not(feature="toml"))]
+            Some("toml") => {
+                Err(RegistryError::TomlParseError("toml feature is disabled".to_string()))
+            }
+            _ => Self::from_json_str(&content),
+        }
+    }
+
+    pub fn insert(&mut self, section: &str, name: &str, parameters: DialerParameters) -> &mut Self {
+        self.sections
+            .entry(section.to_string())
+            .or_insert_with(BTreeMap::new)
+            .insert(name.to_string(), parameters);
+        self
+    }
+
+    /// Entries of `other` replace the entries with the same section and name.
+    pub fn merge(&mut self, other: ConfigurationRegistry) -> &mut Self {
+        for (section, entries) in other.sections {
+            for (name, parameters) in entries {
+                self.insert(&section, &name, parameters);
+            }
+        }
+        self
+    }
+
+    pub fn names<D>(&self) -> Vec<String>
+        where D: ConfigurableDialer
+    {
+        let mut names: Vec<String> = D::builtin_parameters()
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if let Some(entries) = self.sections.get(D::section()) {
+            for name in entries.keys() {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        names
+    }
+
+    pub fn parameters<D>(&self, name: &str) -> Option<DialerParameters>
+        where D: ConfigurableDialer
+    {
+        if let Some(parameters) = self.sections.get(D::section()).and_then(|e| e.get(name)) {
+            return Some(parameters.clone());
+        }
+        D::builtin_parameters()
+            .into_iter()
+            .find(|&(builtin_name, _)| builtin_name == name)
+            .map(|(_, parameters)| parameters)
+    }
+
+    pub fn load_dialer<D>(&self, name: &str) -> Option<D>
+        where D: ConfigurableDialer
+    {
+        self.parameters::<D>(name).and_then(|parameters| D::from_parameters(&parameters))
+    }
+}
+
+fn json_object<'a>(value: &'a Json, path: &str) -> RegistryResult<Vec<(&'a str, &'a Json)>> {
+    match value.as_object() {
+        Some(object) => Ok(object.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        None => Err(RegistryError::UnexpectedValue(path.to_string())),
+    }
+}
+
+#[cfg(feature="toml")]
+fn toml_table<'a>(value: &'a toml::Value,
+                  path: &str)
+                  -> RegistryResult<Vec<(&'a str, &'a toml::Value)>> {
+    match value.as_table() {
+        Some(table) => Ok(table.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        None => Err(RegistryError::UnexpectedValue(path.to_string())),
+    }
+}
+
+#[cfg(all(feature="netkeeper", feature="toml"))]
+#[test]
+fn test_registry_load_dialer() {
+    use netkeeper::dialer::NetkeeperDialer;
+
+    let registry = ConfigurationRegistry::from_toml_str("[netkeeper.zhejiang]\n\
+                                                         share_key = \"rotatedkey01\"\n\
+                                                         prefix = \"\\r\\n\"\n\
+                                                         [netkeeper.custom]\n\
+                                                         share_key = \"customkey01\"\n\
+                                                         prefix = \"\\r1\"\n")
+        .unwrap();
+    let dialer: NetkeeperDialer = registry.load_dialer("zhejiang").unwrap();
+    assert_eq!(dialer.share_key, "rotatedkey01");
+    let dialer: NetkeeperDialer = registry.load_dialer("custom").unwrap();
+    assert_eq!(dialer.prefix, "\r1");
+    let dialer: NetkeeperDialer = registry.load_dialer("chongqing").unwrap();
+    assert_eq!(dialer.share_key, "cqxinliradius002");
+    assert!(registry.load_dialer::<NetkeeperDialer>("unknown").is_none());
+    assert_eq!(registry.names::<NetkeeperDialer>().len(), 13);
+
+    let mut registry = ConfigurationRegistry::new();
+    let json = ConfigurationRegistry::from_json_str(r#"{"netkeeper": {"zhejiang":
+        {"share_key": "jsonkey01", "prefix": "\r\n"}}}"#)
+        .unwrap();
+    registry.merge(json);
+    let dialer: NetkeeperDialer = registry.load_dialer("zhejiang").unwrap();
+    assert_eq!(dialer.share_key, "jsonkey01");
+}
+
+#[test]
+fn test_registry_invalid_content() {
+    match ConfigurationRegistry::from_json_str(r#"{"netkeeper": {"zhejiang": {"prefix": 1}}}"#) {
+        Err(RegistryError::UnexpectedValue(ref path)) => {
+            assert_eq!(path, "netkeeper.zhejiang.prefix")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    #[cfg(feature="toml")]
+    assert!(ConfigurationRegistry::from_toml_str("[netkeeper").is_err());
+    assert!(ConfigurationRegistry::from_file("/nonexistent/registry.toml").is_err());
+}
+
+#[cfg(all(feature="singlenet", feature="ghca"))]
+#[test]
+fn test_registry_rejects_non_ascii_keys() {
+    use ghca::dialer::GhcaDialer;
+    use singlenet::dialer::SingleNetDialer;
+
+    let key_table = format!("{}é", "a".repeat(62));
+    let share_key = format!("{}é", "a".repeat(63));
+    let json = format!(r#"{{"singlenet": {{"hainan": {{"share_key": "a", "secret_key": "b",
+                                                      "key_table": "{}"}}}},
+                            "ghca": {{"sichuan_mac": {{"share_key": "{}", "prefix": "~ghca",
+                                                       "version": "2023"}}}}}}"#,
+                       key_table,
+                       share_key);
+    let registry = ConfigurationRegistry::from_json_str(&json).unwrap();
+    assert!(registry.load_dialer::<SingleNetDialer>("hainan").is_none());
+    assert!(registry.load_dialer::<GhcaDialer>("sichuan_mac").is_none());
+}
@@ -0,0 +1,102 @@
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const MAX_PACKET_SIZE: usize = 4096;
+const POLL_INTERVAL_MILLIS: u64 = 50;
+
+/// Server side of a protocol, answers one request with at most one response.
+pub trait Responder: Send + 'static {
+    fn respond(&mut self, request: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Serve a `Responder` over UDP from a background thread, which stops when
+/// the server is dropped.
+#[derive(Debug)]
+pub struct UdpServer {
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<F> Responder for F
+    where F: FnMut(&[u8]) -> Option<Vec<u8>> + Send + 'static
+{
+    fn respond(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        self(request)
+    }
+}
+
+impl UdpServer {
+    pub fn spawn<A, R>(addr: A, mut responder: R) -> io::Result<Self>
+        where A: ToSocketAddrs,
+              R: Responder
+    {
+        let socket = try!(UdpSocket::bind(addr));
+        try!(socket.set_read_timeout(Some(Duration::from_millis(POLL_INTERVAL_MILLIS))));
+        let local_addr = try!(socket.local_addr());
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let running = running.clone();
+            thread::spawn(move || {
+                let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+                while running.load(Ordering::SeqCst) {
+                    let (length, peer) = match socket.recv_from(&mut buffer) {
+                        Ok(received) => received,
+                        Err(_) => continue,
+                    };
+                    if let Some(response) = responder.respond(&buffer[..length]) {
+                        let _ = socket.send_to(&response, peer);
+                    }
+                }
+            })
+        };
+
+        Ok(UdpServer {
+            local_addr: local_addr,
+            running: running,
+            handle: Some(handle),
+        })
+    }
+
+    /// Serve on an ephemeral port of the loopback interface.
+    pub fn spawn_local<R>(responder: R) -> io::Result<Self>
+        where R: Responder
+    {
+        Self::spawn("127.0.0.1:0", responder)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for UdpServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[test]
+fn test_udp_server() {
+    use common::transport::{Transport, TransportClient, UdpTransport};
+
+    let server = UdpServer::spawn_local(|request: &[u8]| {
+            let mut response = request.to_vec();
+            response.reverse();
+            Some(response)
+        })
+        .unwrap();
+
+    let mut transport = UdpTransport::connect("127.0.0.1:0", server.local_addr()).unwrap();
+    transport.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut client = TransportClient::new(transport);
+    assert_eq!(client.exchange(&[1, 2, 3]).unwrap(), vec![3, 2, 1]);
+}
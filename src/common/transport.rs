@@ -0,0 +1,362 @@
+use std::io;
+use std::io::{Read, Write};
+use std::collections::VecDeque;
+use std::net::{UdpSocket, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use error::{Error, Result};
+
+const MAX_PACKET_SIZE: usize = 4096;
+const DEFAULT_RETRIES: usize = 3;
+const DEFAULT_TIMEOUT: u64 = 5;
+
+pub trait Transport {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()>;
+    fn recv(&mut self) -> io::Result<Vec<u8>>;
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Whether a request may be sent again after a timeout. Resending on a
+    /// stream would leave the late response in front of the next one.
+    fn resendable(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+/// Where packets end in a byte stream, `packet_length` tells the total length
+/// of a packet from its first `header_length` bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Framing {
+    header_length: usize,
+    packet_length: fn(&[u8]) -> usize,
+}
+
+#[derive(Debug)]
+pub struct TcpTransport {
+    stream: TcpStream,
+    framing: Framing,
+}
+
+/// In-memory transport, every sent packet is handed to `responder` and the
+/// returned bytes (if any) are queued for the next `recv`.
+pub struct LoopbackTransport {
+    responder: Box<FnMut(&[u8]) -> Option<Vec<u8>>>,
+    pending: VecDeque<Vec<u8>>,
+    sent: Vec<Vec<u8>>,
+}
+
+/// Send a request and wait for its response, resending the request when the
+/// transport timed out.
+#[derive(Debug)]
+pub struct TransportClient<T: Transport> {
+    transport: T,
+    retries: usize,
+}
+
+impl UdpTransport {
+    /// Timeouts default to 5 seconds, so that a lost datagram is resent.
+    pub fn connect<L, R>(local_addr: L, remote_addr: R) -> io::Result<Self>
+        where L: ToSocketAddrs,
+              R: ToSocketAddrs
+    {
+        let socket = try!(UdpSocket::bind(local_addr));
+        try!(socket.connect(remote_addr));
+        let mut transport = UdpTransport { socket: socket };
+        try!(transport.set_timeout(Some(Duration::from_secs(DEFAULT_TIMEOUT))));
+        Ok(transport)
+    }
+
+    /// Keeps the timeouts already set on `socket`.
+    pub fn from_socket(socket: UdpSocket) -> Self {
+        UdpTransport { socket: socket }
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        try!(self.socket.send(bytes));
+        Ok(())
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+        let length = try!(self.socket.recv(&mut buffer));
+        buffer.truncate(length);
+        Ok(buffer)
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        try!(self.socket.set_read_timeout(timeout));
+        self.socket.set_write_timeout(timeout)
+    }
+}
+
+impl Framing {
+    pub fn new(header_length: usize, packet_length: fn(&[u8]) -> usize) -> Self {
+        Framing {
+            header_length: header_length,
+            packet_length: packet_length,
+        }
+    }
+
+    pub fn header_length(&self) -> usize {
+        self.header_length
+    }
+
+    pub fn packet_length(&self, header: &[u8]) -> usize {
+        (self.packet_length)(header)
+    }
+}
+
+impl TcpTransport {
+    /// Timeouts default to 5 seconds like `UdpTransport`.
+    pub fn connect<A>(addr: A, framing: Framing) -> io::Result<Self>
+        where A: ToSocketAddrs
+    {
+        let mut transport = Self::from_stream(try!(TcpStream::connect(addr)), framing);
+        try!(transport.set_timeout(Some(Duration::from_secs(DEFAULT_TIMEOUT))));
+        Ok(transport)
+    }
+
+    pub fn from_stream(stream: TcpStream, framing: Framing) -> Self {
+        TcpTransport {
+            stream: stream,
+            framing: framing,
+        }
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stream.write_all(bytes)
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let header_length = self.framing.header_length();
+        let mut packet = vec![0u8; header_length];
+        try!(self.stream.read_exact(&mut packet));
+        let length = self.framing.packet_length(&packet);
+        if length < header_length || length > MAX_PACKET_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("unexpected packet length {}", length)));
+        }
+        packet.resize(length, 0);
+        try!(self.stream.read_exact(&mut packet[header_length..]));
+        Ok(packet)
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        try!(self.stream.set_read_timeout(timeout));
+        self.stream.set_write_timeout(timeout)
+    }
+
+    fn resendable(&self) -> bool {
+        false
+    }
+}
+
+impl LoopbackTransport {
+    pub fn new<F>(responder: F) -> Self
+        where F: FnMut(&[u8]) -> Option<Vec<u8>> + 'static
+    {
+        LoopbackTransport {
+            responder: Box::new(responder),
+            pending: VecDeque::new(),
+            sent: Vec::new(),
+        }
+    }
+
+    /// Answer the n-th sent packet with the n-th scripted response, requests
+    /// beyond the script get no response.
+    pub fn scripted(responses: Vec<Vec<u8>>) -> Self {
+        let mut responses: VecDeque<Vec<u8>> = responses.into_iter().collect();
+        Self::new(move |_| responses.pop_front())
+    }
+
+    pub fn sent(&self) -> &[Vec<u8>] {
+        &self.sent
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.sent.push(bytes.to_vec());
+        if let Some(response) = (self.responder)(bytes) {
+            self.pending.push_back(response);
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        self.pending
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "no response in loopback"))
+    }
+
+    #[allow(unused_variables)]
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T> TransportClient<T>
+    where T: Transport
+{
+    pub fn new(transport: T) -> Self {
+        TransportClient {
+            transport: transport,
+            retries: DEFAULT_RETRIES,
+        }
+    }
+
+    pub fn timeout(&mut self, timeout: Option<Duration>) -> Result<&mut Self> {
+        try!(self.transport.set_timeout(timeout));
+        Ok(self)
+    }
+
+    pub fn retries(&mut self, retries: usize) -> &mut Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+
+    pub fn exchange(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        let attempts = if self.transport.resendable() { self.retries + 1 } else { 1 };
+        for _ in 0..attempts {
+            try!(self.transport.send(request));
+            match self.transport.recv() {
+                Ok(response) => return Ok(response),
+                Err(ref e) if is_timeout(e) => continue,
+                Err(e) => return Err(Error::IO(e)),
+            }
+        }
+        Err(Error::Timeout(attempts))
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn test_loopback_transport() {
+    let mut transport = LoopbackTransport::new(|bytes| {
+        let mut response = bytes.to_vec();
+        response.reverse();
+        Some(response)
+    });
+    transport.send(&[1, 2, 3]).unwrap();
+    assert_eq!(transport.recv().unwrap(), vec![3, 2, 1]);
+    assert!(transport.recv().is_err());
+    assert_eq!(transport.sent(), &[vec![1, 2, 3]]);
+}
+
+#[test]
+fn test_transport_client_retries() {
+    let mut counter = 0;
+    let transport = LoopbackTransport::new(move |_| {
+        counter += 1;
+        if counter < 3 { None } else { Some(vec![counter]) }
+    });
+
+    let mut client = TransportClient::new(transport);
+    assert_eq!(client.retries(2).exchange(&[0]).unwrap(), vec![3]);
+    assert_eq!(client.transport().sent().len(), 3);
+
+    let mut client = TransportClient::new(LoopbackTransport::scripted(vec![]));
+    match client.retries(1).exchange(&[0]) {
+        Err(Error::Timeout(2)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_udp_transport() {
+    use std::thread;
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let mut buffer = [0u8; 16];
+        let (length, peer) = server.recv_from(&mut buffer).unwrap();
+        server.send_to(&buffer[..length], peer).unwrap();
+    });
+
+    let mut transport = UdpTransport::connect("127.0.0.1:0", server_addr).unwrap();
+    transport.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut client = TransportClient::new(transport);
+    assert_eq!(client.exchange(b"ping").unwrap(), b"ping".to_vec());
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_udp_transport_default_timeout() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let transport = UdpTransport::connect("127.0.0.1:0", silent.local_addr().unwrap()).unwrap();
+    assert_eq!(transport.socket.read_timeout().unwrap(),
+               Some(Duration::from_secs(DEFAULT_TIMEOUT)));
+
+    let mut client = TransportClient::new(transport);
+    client.timeout(Some(Duration::from_millis(50))).unwrap().retries(1);
+    match client.exchange(b"ping") {
+        Err(Error::Timeout(2)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_tcp_transport_framing() {
+    use std::thread;
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 3];
+        stream.read_exact(&mut request).unwrap();
+        // a packet split across writes, then two packets in one write
+        stream.write_all(&[0, 5, 1]).unwrap();
+        stream.flush().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        stream.write_all(&[2, 3]).unwrap();
+        stream.write_all(&[0, 3, 4, 0, 2]).unwrap();
+        stream.read_exact(&mut request).unwrap();
+        stream.read_to_end(&mut Vec::new()).unwrap();
+    });
+
+    let framing = Framing::new(2, |header| header[1] as usize);
+    let transport = TcpTransport::connect(server_addr, framing).unwrap();
+    assert!(!transport.resendable());
+    let mut client = TransportClient::new(transport);
+    assert_eq!(client.exchange(&[0, 3, 9]).unwrap(), vec![0, 5, 1, 2, 3]);
+    assert_eq!(client.transport_mut().recv().unwrap(), vec![0, 3, 4]);
+    assert_eq!(client.transport_mut().recv().unwrap(), vec![0, 2]);
+
+    // a stream is never resent on a timeout
+    client.timeout(Some(Duration::from_millis(50))).unwrap();
+    match client.exchange(&[0, 3, 9]) {
+        Err(Error::Timeout(1)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    drop(client);
+    handle.join().unwrap();
+}
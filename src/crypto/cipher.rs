@@ -1,3 +1,5 @@
+use std::{error, fmt};
+
 use rust_crypto::{aes, blockmodes, buffer, symmetriccipher};
 use rust_crypto::buffer::{WriteBuffer, ReadBuffer};
 
@@ -10,6 +12,21 @@ pub enum CipherError {
     BufferOverflow,
 }
 
+impl fmt::Display for CipherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CipherError::KeyLengthMismatch(expect, got) => {
+                write!(f, "expect key length {}, got {}", expect, got)
+            }
+            CipherError::EncryptError(ref e) => write!(f, "encrypt failed: {:?}", e),
+            CipherError::DecryptError(ref e) => write!(f, "decrypt failed: {:?}", e),
+            CipherError::BufferOverflow => write!(f, "output buffer overflow"),
+        }
+    }
+}
+
+impl error::Error for CipherError {}
+
 pub trait SimpleCipher {
     fn encrypt(&self, plain_bytes: &[u8]) -> Result<Vec<u8>, CipherError>;
     fn decrypt(&self, encrypted_bytes: &[u8]) -> Result<Vec<u8>, CipherError>;
@@ -0,0 +1,134 @@
+use common::dialer::{DecodedAccount, DecodeResult};
+#[cfg(feature="netkeeper")]
+use netkeeper::dialer::{NetkeeperDialer, Configuration as NetkeeperConfiguration};
+#[cfg(feature="singlenet")]
+use singlenet::dialer::{SingleNetDialer, Configuration as SingleNetConfiguration};
+#[cfg(feature="ghca")]
+use ghca::dialer::{GhcaDialer, Configuration as GhcaConfiguration};
+#[cfg(feature="srun3k")]
+use srun3k::dialer::{Srun3kDialer, Configuration as Srun3kConfiguration};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DetectedConfiguration {
+    #[cfg(feature="netkeeper")]
+    Netkeeper(NetkeeperConfiguration),
+    #[cfg(feature="singlenet")]
+    SingleNet(SingleNetConfiguration),
+    #[cfg(feature="ghca")]
+    Ghca(GhcaConfiguration),
+    #[cfg(feature="srun3k")]
+    Srun3k(Srun3kConfiguration),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Detection {
+    pub configuration: DetectedConfiguration,
+    pub username: String,
+    pub timestamp: Option<u32>,
+    pub pin: Option<String>,
+}
+
+/// Try every known configuration of every enabled algorithm against a captured
+/// username.
+///
+/// Matches whose embedded timestamp falls outside the inclusive `window` are
+/// dropped. Ghca PINs depend on the password, so Ghca is only tried when
+/// `password` is given.
+pub fn detect_configuration(encrypted: &str,
+                            password: Option<&str>,
+                            window: Option<(u32, u32)>)
+                            -> Vec<Detection> {
+    let mut detections = Vec::new();
+
+    #[cfg(feature="netkeeper")]
+    for configuration in NetkeeperConfiguration::all() {
+        // the timestamp is rounded down to a multiple of five seconds
+        let result = NetkeeperDialer::verify_account(encrypted, &[*configuration]);
+        push_detection(&mut detections, result, window, 5, DetectedConfiguration::Netkeeper);
+    }
+
+    #[cfg(feature="singlenet")]
+    for configuration in SingleNetConfiguration::all() {
+        let result = SingleNetDialer::verify_account(encrypted, &[*configuration]);
+        push_detection(&mut detections, result, window, 1, DetectedConfiguration::SingleNet);
+    }
+
+    #[cfg(not(feature="ghca"))]
+    let _ = password;
+    #[cfg(feature="ghca")]
+    for configuration in GhcaConfiguration::all() {
+        if let Some(password) = password {
+            let result = GhcaDialer::verify_account(encrypted, password, &[*configuration]);
+            push_detection(&mut detections, result, window, 1, DetectedConfiguration::Ghca);
+        }
+    }
+
+    #[cfg(feature="srun3k")]
+    for configuration in Srun3kConfiguration::all() {
+        let result = Srun3kDialer::verify_account_v20(encrypted, &[*configuration]);
+        push_detection(&mut detections, result, window, 1, DetectedConfiguration::Srun3k);
+    }
+
+    detections
+}
+
+fn push_detection<C, F>(detections: &mut Vec<Detection>,
+                        result: DecodeResult<DecodedAccount<C>>,
+                        window: Option<(u32, u32)>,
+                        precision: u32,
+                        wrap: F)
+    where F: FnOnce(C) -> DetectedConfiguration
+{
+    let decoded = match result {
+        Ok(decoded) => decoded,
+        Err(_) => return,
+    };
+    if let (Some((start, end)), Some(timestamp)) = (window, decoded.timestamp) {
+        if timestamp > end || timestamp.saturating_add(precision - 1) < start {
+            return;
+        }
+    }
+    if let Some(configuration) = decoded.configuration {
+        detections.push(Detection {
+            configuration: wrap(configuration),
+            username: decoded.username,
+            timestamp: decoded.timestamp,
+            pin: decoded.pin,
+        });
+    }
+}
+
+#[cfg(feature="netkeeper")]
+#[test]
+fn test_detect_netkeeper_configuration() {
+    let detections = detect_configuration("\r\n:R#(P 5005802278989@HYXY.XY", None, None);
+    assert_eq!(detections.len(), 1);
+    assert_eq!(detections[0].configuration,
+               DetectedConfiguration::Netkeeper(NetkeeperConfiguration::Zhejiang));
+    assert_eq!(detections[0].username, "05802278989@HYXY.XY");
+
+    let window = Some((1472483022, 1472483100));
+    assert_eq!(detect_configuration("\r\n:R#(P 5005802278989@HYXY.XY", None, window).len(),
+               1);
+    let window = Some((1472483100, 1472483200));
+    assert!(detect_configuration("\r\n:R#(P 5005802278989@HYXY.XY", None, window).is_empty());
+}
+
+#[cfg(all(feature="singlenet", feature="ghca", feature="srun3k"))]
+#[test]
+fn test_detect_other_configurations() {
+    let detections = detect_configuration("~LL_k6ecvpj2mrjA_05802278989@HYXY.XY", None, None);
+    assert_eq!(detections[0].configuration,
+               DetectedConfiguration::SingleNet(SingleNetConfiguration::Hainan));
+
+    let encrypted = "~ghca57F487192023484F1BD1D9AB5DC5013405802278989@HYXY.XY";
+    assert!(detect_configuration(encrypted, None, None).is_empty());
+    let detections = detect_configuration(encrypted, Some("123456"), None);
+    assert_eq!(detections[0].configuration,
+               DetectedConfiguration::Ghca(GhcaConfiguration::SichuanMac));
+
+    let detections = detect_configuration("{SRUN3}\r\nehqmr", None, Some((0, 1)));
+    assert_eq!(detections[0].configuration,
+               DetectedConfiguration::Srun3k(Srun3kConfiguration::TaLiMu));
+    assert_eq!(detections[0].username, "admin");
+}
@@ -0,0 +1,626 @@
+use std::{fmt, io};
+#[cfg(feature="drcom")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::SocketAddrV4;
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature="drcom")]
+use byteorder::{NativeEndian, ByteOrder};
+#[cfg(feature="drcom")]
+use rustc_serialize::hex::ToHex;
+
+use common::pcap::{PcapReader, Segment};
+#[cfg(feature="singlenet")]
+use common::pcap::TransportProtocol;
+use error::Result;
+#[cfg(feature="drcom")]
+use common::reader::ReaderHelper;
+#[cfg(feature="drcom")]
+use drcom::{DrCOMFlag, DrCOMValidateError};
+#[cfg(feature="drcom")]
+use drcom::wired::dialer::{LoginRequest, LoginResponse, LoginError, ChallengeResponse,
+                           LogoutResponse};
+#[cfg(feature="drcom")]
+use drcom::wired::heartbeater::{PhaseOneRequest, PhaseOneResponse, PhaseTwoRequest,
+                                PhaseTwoResponse, HeartbeatFlag};
+#[cfg(feature="drcom")]
+use drcom::pppoe::heartbeater::{ChallengeResponse as PPPoEChallengeResponse, HeartbeatRequest,
+                                KeepAliveRequest, KeepAliveResponse, DrCOMHeartbeatError};
+#[cfg(feature="netkeeper")]
+use netkeeper::heartbeater::{Packet as NetkeeperPacket, HeartbeatProfile};
+#[cfg(feature="singlenet")]
+use singlenet::heartbeater::{Packet as SingleNetPacket, PacketAuthenticator,
+                             SinglenetHeartbeatError};
+
+/// Port of both the DrCOM wired and PPPoE servers.
+pub const DRCOM_PORT: u16 = 61440;
+/// Port of the Netkeeper and SingleNet heartbeat servers.
+pub const HEARTBEAT_PORT: u16 = 443;
+
+#[cfg(feature="drcom")]
+const DRCOM_PPPOE_CHALLENGE_LENGTH: u16 = 8;
+#[cfg(feature="drcom")]
+const DRCOM_PPPOE_HEARTBEAT_LENGTH: u16 = 96;
+#[cfg(feature="drcom")]
+const DRCOM_PPPOE_KEEP_ALIVE_LENGTH: u16 = 40;
+#[cfg(feature="drcom")]
+const DRCOM_LOGIN_RESPONSE_MIN_LENGTH: usize = 29;
+#[cfg(feature="netkeeper")]
+const NETKEEPER_MAGIC: &'static [u8] = b"HR";
+#[cfg(feature="singlenet")]
+const SINGLENET_MAGIC: &'static [u8] = b"SN";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    #[cfg(feature="drcom")]
+    DrCOMWired,
+    #[cfg(feature="drcom")]
+    DrCOMPPPoE,
+    #[cfg(feature="netkeeper")]
+    Netkeeper,
+    #[cfg(feature="singlenet")]
+    SingleNet,
+}
+
+/// One decoded packet, `summary` holds the validation error of packets the
+/// parsers reject.
+#[derive(Debug)]
+pub struct TranscriptEntry {
+    // Position of the frame in the capture, starting from 1
+    pub index: usize,
+    pub timestamp: Duration,
+    pub protocol: Protocol,
+    pub source: SocketAddrV4,
+    pub destination: SocketAddrV4,
+    pub summary: Result<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Transcript {
+    entries: Vec<TranscriptEntry>,
+}
+
+/// Decodes the payloads of a capture with the parsers of this crate.
+///
+/// Segments are matched by the server port, DrCOM is expected on 61440 and
+/// dissected as the wired protocol unless `port` says otherwise. Netkeeper
+/// and SingleNet heartbeats share port 443 and are told apart by their magic
+/// number, servers on other ports have to be given with `port`.
+#[derive(Debug)]
+pub struct Dissector {
+    ports: Vec<(u16, Protocol)>,
+    servers: HashSet<SocketAddrV4>,
+    #[cfg(feature="netkeeper")]
+    netkeeper_profile: Option<HeartbeatProfile>,
+    #[cfg(feature="singlenet")]
+    singlenet_secret: Option<String>,
+    // length of the last DrCOM PPPoE request of each client, which tells
+    // apart the responses
+    #[cfg(feature="drcom")]
+    pppoe_requests: HashMap<SocketAddrV4, u16>,
+}
+
+impl Protocol {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            #[cfg(feature="drcom")]
+            Protocol::DrCOMWired => "DrCOM",
+            #[cfg(feature="drcom")]
+            Protocol::DrCOMPPPoE => "DrCOM PPPoE",
+            #[cfg(feature="netkeeper")]
+            Protocol::Netkeeper => "Netkeeper",
+            #[cfg(feature="singlenet")]
+            Protocol::SingleNet => "SingleNet",
+        }
+    }
+}
+
+impl fmt::Display for TranscriptEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f,
+                    "#{} {}.{:06} {} -> {} {}: ",
+                    self.index,
+                    self.timestamp.as_secs(),
+                    self.timestamp.subsec_nanos() / 1000,
+                    self.source,
+                    self.destination,
+                    self.protocol.name()));
+        match self.summary {
+            Ok(ref summary) => write!(f, "{}", summary),
+            Err(ref e) => write!(f, "INVALID {}", e),
+        }
+    }
+}
+
+impl Transcript {
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    /// Entries of the packets which failed validation.
+    pub fn failures(&self) -> Vec<&TranscriptEntry> {
+        self.entries.iter().filter(|entry| entry.summary.is_err()).collect()
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            try!(writeln!(f, "{}", entry));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Dissector {
+    fn default() -> Self {
+        Dissector {
+            ports: default_ports(),
+            servers: HashSet::new(),
+            #[cfg(feature="netkeeper")]
+            netkeeper_profile: None,
+            #[cfg(feature="singlenet")]
+            singlenet_secret: None,
+            #[cfg(feature="drcom")]
+            pppoe_requests: HashMap::new(),
+        }
+    }
+}
+
+impl Dissector {
+    pub fn new() -> Self {
+        Dissector::default()
+    }
+
+    /// Dissect segments from or to the server `port` as `protocol`.
+    pub fn port(&mut self, port: u16, protocol: Protocol) -> &mut Self {
+        self.ports.retain(|&(p, _)| p != port);
+        self.ports.push((port, protocol));
+        self
+    }
+
+    /// Profile whose AES key decrypts the Netkeeper heartbeats, which are
+    /// left encrypted without one.
+    #[cfg(feature="netkeeper")]
+    pub fn netkeeper_profile(&mut self, profile: HeartbeatProfile) -> &mut Self {
+        self.netkeeper_profile = Some(profile);
+        self
+    }
+
+    /// Verify the authorization of SingleNet packets with `secret`.
+    #[cfg(feature="singlenet")]
+    pub fn singlenet_secret(&mut self, secret: &str) -> &mut Self {
+        self.singlenet_secret = Some(secret.to_string());
+        self
+    }
+
+    /// Decode one segment, `None` if it belongs to none of the protocols.
+    pub fn dissect(&mut self, segment: &Segment) -> Option<(Protocol, Result<String>)> {
+        if segment.payload.is_empty() {
+            return None;
+        }
+        let (protocol, is_request) = match self.classify(segment) {
+            Some(classified) => classified,
+            None => return None,
+        };
+        let summary = self.summarize(protocol, is_request, segment);
+        Some((protocol, summary))
+    }
+
+    /// Dissect every record of a capture.
+    pub fn transcript<R>(&mut self, reader: PcapReader<R>) -> Result<Transcript>
+        where R: io::Read
+    {
+        let mut transcript = Transcript::default();
+        for (index, record) in reader.enumerate() {
+            let record = try!(record);
+            let segment = match record.segment() {
+                Some(segment) => segment,
+                None => continue,
+            };
+            if let Some((protocol, summary)) = self.dissect(&segment) {
+                transcript.entries.push(TranscriptEntry {
+                    index: index + 1,
+                    timestamp: record.timestamp,
+                    protocol: protocol,
+                    source: segment.source,
+                    destination: segment.destination,
+                    summary: summary,
+                });
+            }
+        }
+        Ok(transcript)
+    }
+
+    pub fn transcript_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Transcript> {
+        let reader = try!(PcapReader::open(path));
+        self.transcript(reader)
+    }
+
+    fn classify(&mut self, segment: &Segment) -> Option<(Protocol, bool)> {
+        let (source_port, destination_port) = (segment.source.port(), segment.destination.port());
+        let mapped = self.ports
+            .iter()
+            .find(|&&(port, _)| port == destination_port || port == source_port)
+            .cloned();
+        if let Some((port, protocol)) = mapped {
+            let is_request = match (destination_port == port, source_port == port) {
+                (true, false) => true,
+                (false, true) => false,
+                _ => self.is_request(protocol, segment),
+            };
+            return Some((protocol, is_request));
+        }
+
+        if destination_port == HEARTBEAT_PORT {
+            return heartbeat_protocol(segment).map(|protocol| (protocol, true));
+        }
+        if source_port == HEARTBEAT_PORT {
+            return heartbeat_protocol(segment).map(|protocol| (protocol, false));
+        }
+        None
+    }
+
+    /// Clients may use the server port too, then the direction comes from the
+    /// servers seen so far, or from packets only a client sends.
+    fn is_request(&mut self, protocol: Protocol, segment: &Segment) -> bool {
+        if self.servers.contains(&segment.destination) {
+            return true;
+        }
+        if self.servers.contains(&segment.source) {
+            return false;
+        }
+        let is_request = looks_like_request(protocol, &segment.payload);
+        if is_request {
+            self.servers.insert(segment.destination);
+        }
+        is_request
+    }
+
+    fn summarize(&mut self, protocol: Protocol, is_request: bool, segment: &Segment)
+                 -> Result<String> {
+        let payload = &segment.payload;
+        match (protocol, is_request) {
+            #[cfg(feature="drcom")]
+            (Protocol::DrCOMWired, true) => drcom_wired_request(payload),
+            #[cfg(feature="drcom")]
+            (Protocol::DrCOMWired, false) => drcom_wired_response(payload),
+            #[cfg(feature="drcom")]
+            (Protocol::DrCOMPPPoE, true) => {
+                let summary = drcom_pppoe_request(payload);
+                if payload.len() >= 4 {
+                    self.pppoe_requests
+                        .insert(segment.source, NativeEndian::read_u16(&payload[2..4]));
+                }
+                summary
+            }
+            #[cfg(feature="drcom")]
+            (Protocol::DrCOMPPPoE, false) => {
+                let request_length = self.pppoe_requests.get(&segment.destination).cloned();
+                drcom_pppoe_response(payload, request_length)
+            }
+            #[cfg(feature="netkeeper")]
+            (Protocol::Netkeeper, _) => {
+                let encrypter = match self.netkeeper_profile {
+                    Some(profile) => try!(profile.encrypter()),
+                    None => return Ok("encrypted heartbeat, no profile to decrypt it".to_string()),
+                };
+                let packet = try!(NetkeeperPacket::from_bytes(&mut io::BufReader::new(&payload[..]),
+                                                              &encrypter,
+                                                              None));
+                let frame = packet.frame();
+                let fields: Vec<String> = frame.fields()
+                    .map(|(name, value)| if name == "PASSWORD" {
+                        format!("{}=***", name)
+                    } else {
+                        format!("{}={}", name, value)
+                    })
+                    .collect();
+                Ok(format!("heartbeat version {}, code {:#06x}, {} {}",
+                           packet.version(),
+                           packet.code(),
+                           frame.type_name(),
+                           fields.join(" ")))
+            }
+            #[cfg(feature="singlenet")]
+            (Protocol::SingleNet, _) => {
+                if let Some(ref secret) = self.singlenet_secret {
+                    if !PacketAuthenticator::new(secret).verify(payload) {
+                        return Err(SinglenetHeartbeatError::AuthorizationMismatch.into());
+                    }
+                }
+                let mut input = io::BufReader::new(&payload[..]);
+                let packet = try!(SingleNetPacket::from_bytes(&mut input));
+                Ok(format!("{:?}, seq {}, {} attributes",
+                           packet.code(),
+                           packet.seq(),
+                           packet.attributes().len()))
+            }
+        }
+    }
+}
+
+#[cfg(feature="drcom")]
+fn default_ports() -> Vec<(u16, Protocol)> {
+    vec![(DRCOM_PORT, Protocol::DrCOMWired)]
+}
+
+#[cfg(not(feature="drcom"))]
+fn default_ports() -> Vec<(u16, Protocol)> {
+    Vec::new()
+}
+
+/// Heartbeats recognized by their magic number.
+#[cfg(any(feature="netkeeper", feature="singlenet"))]
+fn heartbeat_protocol(segment: &Segment) -> Option<Protocol> {
+    #[cfg(feature="netkeeper")]
+    {
+        if segment.payload.starts_with(NETKEEPER_MAGIC) {
+            return Some(Protocol::Netkeeper);
+        }
+    }
+    #[cfg(feature="singlenet")]
+    {
+        if segment.protocol == TransportProtocol::Udp &&
+           segment.payload.starts_with(SINGLENET_MAGIC) {
+            return Some(Protocol::SingleNet);
+        }
+    }
+    None
+}
+
+#[cfg(not(any(feature="netkeeper", feature="singlenet")))]
+fn heartbeat_protocol(_: &Segment) -> Option<Protocol> {
+    None
+}
+
+#[cfg(feature="drcom")]
+fn looks_like_request(protocol: Protocol, payload: &[u8]) -> bool {
+    if protocol == Protocol::DrCOMWired {
+        return match payload[0] {
+            0x01 | 0x03 | 0x06 | 0xff => true,
+            // phase two requests and responses share the code and length,
+            // only requests carry a heartbeat flag
+            0x07 => {
+                payload.len() >= 10 &&
+                [HeartbeatFlag::First, HeartbeatFlag::NotFirst]
+                    .iter()
+                    .any(|flag| flag.as_u32() == NativeEndian::read_u32(&payload[6..10]))
+            }
+            _ => false,
+        };
+    }
+    // requests start with their own length
+    if protocol == Protocol::DrCOMPPPoE {
+        return payload.len() >= 4 &&
+               NativeEndian::read_u16(&payload[2..4]) as usize == payload.len();
+    }
+    true
+}
+
+#[cfg(not(feature="drcom"))]
+fn looks_like_request(_: Protocol, _: &[u8]) -> bool {
+    true
+}
+
+#[cfg(feature="drcom")]
+fn drcom_wired_request(payload: &[u8]) -> Result<String> {
+    let mut input = io::BufReader::new(payload);
+    match payload[0] {
+        0x01 => {
+            let bytes = try!(input.read_bytes(4));
+            Ok(format!("challenge request, sequence {}", NativeEndian::read_u16(&bytes[2..4])))
+        }
+        0x03 => {
+            let request = try!(LoginRequest::from_bytes(&mut input));
+            Ok(format!("login request, username {:?}, hostname {:?}, mac {}, client version \
+                        {:#04x}",
+                       request.username(),
+                       request.hostname(),
+                       request.mac_address().to_hex(),
+                       request.client_version()))
+        }
+        0x06 => Ok("logout request".to_string()),
+        0xff => {
+            let request = try!(PhaseOneRequest::from_bytes(&mut input));
+            Ok(format!("heartbeat phase one, timestamp {}, key {}",
+                       request.timestamp(),
+                       request.keep_alive_key().to_hex()))
+        }
+        0x07 => {
+            let request = try!(PhaseTwoRequest::from_bytes(&mut input));
+            Ok(format!("heartbeat phase two, type {}, sequence {}, key {}",
+                       request.type_id(),
+                       request.sequence(),
+                       request.keep_alive_key().to_hex()))
+        }
+        code => Err(DrCOMValidateError::CodeMismatch(code).into()),
+    }
+}
+
+#[cfg(feature="drcom")]
+fn drcom_wired_response(payload: &[u8]) -> Result<String> {
+    let mut input = io::BufReader::new(payload);
+    match payload[0] {
+        0x02 => {
+            let response = try!(ChallengeResponse::from_bytes(&mut input));
+            Ok(format!("challenge response, salt {}", response.hash_salt.to_hex()))
+        }
+        0x04 if payload.len() < DRCOM_LOGIN_RESPONSE_MIN_LENGTH => {
+            try!(LogoutResponse::from_bytes(&mut input));
+            Ok("logout response".to_string())
+        }
+        0x04 | 0x05 => {
+            match LoginResponse::from_bytes(&mut input) {
+                Ok(response) => {
+                    Ok(format!("login response, key {}", response.keep_alive_key.to_hex()))
+                }
+                Err(LoginError::LoginFailed(failure)) => {
+                    Ok(format!("login failure, {}", failure))
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+        0x07 if payload.len() >= 4 && NativeEndian::read_u16(&payload[2..4]) == 0x28 => {
+            let response = try!(PhaseTwoResponse::from_bytes(&mut input));
+            Ok(format!("heartbeat phase two response, sequence {}, key {}",
+                       response.sequence,
+                       response.keep_alive_key.to_hex()))
+        }
+        0x07 => {
+            try!(PhaseOneResponse::from_bytes(&mut input));
+            Ok("heartbeat phase one response".to_string())
+        }
+        code => Err(DrCOMValidateError::CodeMismatch(code).into()),
+    }
+}
+
+#[cfg(feature="drcom")]
+fn drcom_pppoe_request(payload: &[u8]) -> Result<String> {
+    if payload[0] != 0x07 || payload.len() < 4 {
+        return Err(DrCOMValidateError::CodeMismatch(payload[0]).into());
+    }
+    let mut input = io::BufReader::new(payload);
+    match NativeEndian::read_u16(&payload[2..4]) {
+        DRCOM_PPPOE_CHALLENGE_LENGTH => Ok(format!("challenge request, sequence {}", payload[1])),
+        DRCOM_PPPOE_HEARTBEAT_LENGTH => {
+            let request = try!(HeartbeatRequest::from_bytes(&mut input));
+            Ok(format!("heartbeat request, sequence {}, source ip {}, seed {:#010x}",
+                       request.sequence(),
+                       request.source_ip(),
+                       request.challenge_seed()))
+        }
+        DRCOM_PPPOE_KEEP_ALIVE_LENGTH => {
+            let request = try!(KeepAliveRequest::from_bytes(&mut input));
+            Ok(format!("keep alive request, type {}, sequence {}, seed {:#010x}",
+                       request.type_id(),
+                       request.sequence(),
+                       request.keep_alive_seed()))
+        }
+        _ => Err(DrCOMHeartbeatError::UnexpectedBytes(payload[2..4].to_vec()).into()),
+    }
+}
+
+#[cfg(feature="drcom")]
+fn drcom_pppoe_response(payload: &[u8], request_length: Option<u16>) -> Result<String> {
+    let mut input = io::BufReader::new(payload);
+    if request_length == Some(DRCOM_PPPOE_CHALLENGE_LENGTH) {
+        let response = try!(PPPoEChallengeResponse::from_bytes(&mut input));
+        return Ok(format!("challenge response, source ip {}, seed {:#010x}",
+                          response.source_ip,
+                          response.challenge_seed));
+    }
+    let response = try!(KeepAliveResponse::from_bytes(&mut input));
+    Ok(format!("keep alive response, {:?}", response.response_type))
+}
+
+#[cfg(feature="drcom")]
+#[test]
+fn test_dissect_drcom_wired() {
+    let capture: &[u8] = include_bytes!("../tests/fixtures/drcom_wired.pcap");
+    let transcript = Dissector::new().transcript(PcapReader::new(capture).unwrap()).unwrap();
+    assert_eq!(transcript.entries().len(), 9);
+    assert_eq!(transcript.entries()[2].to_string(),
+               "#3 1476316800.031000 10.30.22.17:61440 -> 10.100.61.3:61440 DrCOM: login \
+                request, username \"usernameusername\", hostname \"drcom-client\", mac \
+                b888e3051680, client version 0x0a");
+    assert_eq!(transcript.entries()[7].to_string(),
+               "#8 1476316801.040000 10.100.61.3:61440 -> 10.30.22.17:61440 DrCOM: heartbeat \
+                phase two response, sequence 1, key 09080706");
+
+    let failures = transcript.failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].index, 9);
+}
+
+#[cfg(feature="drcom")]
+#[test]
+fn test_dissect_drcom_wired_packets() {
+    use std::net::Ipv4Addr;
+    use error::Error;
+
+    match drcom_wired_request(&[0x01, 0x02]) {
+        Err(Error::ReadBytes(_)) => {}
+        other => panic!("unexpected summary {:?}", other),
+    }
+
+    let request = PhaseTwoRequest::new(1,
+                                       [9, 8, 7, 6],
+                                       &HeartbeatFlag::NotFirst,
+                                       Ipv4Addr::new(10, 30, 22, 17),
+                                       Some(3))
+        .as_bytes();
+    let response = PhaseTwoResponse::new(1, [9, 8, 7, 6], Some(3)).as_bytes();
+    assert!(looks_like_request(Protocol::DrCOMWired, &request));
+    assert!(!looks_like_request(Protocol::DrCOMWired, &response));
+    assert_eq!(drcom_wired_request(&request).unwrap(),
+               "heartbeat phase two, type 3, sequence 1, key 09080706");
+}
+
+#[cfg(feature="drcom")]
+#[test]
+fn test_dissect_drcom_pppoe() {
+    let capture: &[u8] = include_bytes!("../tests/fixtures/drcom_pppoe.pcapng");
+    let mut dissector = Dissector::new();
+    dissector.port(DRCOM_PORT, Protocol::DrCOMPPPoE);
+    let transcript = dissector.transcript(PcapReader::new(capture).unwrap()).unwrap();
+    let summaries: Vec<String> = transcript.entries()
+        .iter()
+        .map(|entry| match entry.summary {
+            Ok(ref summary) => summary.clone(),
+            Err(_) => "INVALID".to_string(),
+        })
+        .collect();
+    assert_eq!(summaries,
+               vec!["challenge request, sequence 0",
+                    "challenge response, source ip 172.16.5.9, seed 0x5a1c9e03",
+                    "heartbeat request, sequence 1, source ip 172.16.5.9, seed 0x5a1c9e03",
+                    "keep alive response, KeepAliveSucceed",
+                    "keep alive request, type 1, sequence 2, seed 0x11223344",
+                    "keep alive response, KeepAliveSucceed",
+                    "keep alive request, type 3, sequence 3, seed 0x11223344",
+                    "keep alive response, KeepAliveSucceed",
+                    "INVALID"]);
+}
+
+#[cfg(all(feature="netkeeper", feature="singlenet"))]
+#[test]
+fn test_dissect_heartbeats() {
+    let capture: &[u8] = include_bytes!("../tests/fixtures/heartbeat.pcapng");
+    let mut dissector = Dissector::new();
+    let transcript = dissector.transcript(PcapReader::new(capture).unwrap()).unwrap();
+    assert_eq!(transcript.entries()[0].to_string(),
+               "#1 1476316800.000000 10.0.0.2:50000 -> 61.164.1.10:443 Netkeeper: encrypted \
+                heartbeat, no profile to decrypt it");
+
+    let mut dissector = Dissector::new();
+    dissector.netkeeper_profile(HeartbeatProfile::Zhejiang)
+        .singlenet_secret("LLWLXA_TPSHARESECRET");
+    let transcript = dissector.transcript(PcapReader::new(capture).unwrap()).unwrap();
+
+    // the DNS query is skipped
+    let indexes: Vec<usize> = transcript.entries().iter().map(|entry| entry.index).collect();
+    assert_eq!(indexes, vec![1, 3, 4, 5]);
+    assert_eq!(transcript.entries()[0].protocol, Protocol::Netkeeper);
+    let netkeeper = transcript.entries()[0].to_string();
+    assert!(netkeeper.contains("USER_NAME=05802278989@HYXY.XY PASSWORD=*** IP=10.0.0.2"));
+    assert!(transcript.entries()[2]
+        .to_string()
+        .ends_with("CKeepAliveResponse, seq 132, 1 attributes"));
+
+    let failures = transcript.failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].index, 5);
+    assert_eq!(failures[0].protocol, Protocol::SingleNet);
+
+    // heartbeats on other ports are only dissected once the port is given
+    let mut segment = PcapReader::new(capture).unwrap().next().unwrap().unwrap().segment().unwrap();
+    segment.destination.set_port(8080);
+    assert!(Dissector::new().dissect(&segment).is_none());
+    match Dissector::new().port(8080, Protocol::Netkeeper).dissect(&segment) {
+        Some((Protocol::Netkeeper, Ok(_))) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
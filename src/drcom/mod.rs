@@ -1,5 +1,5 @@
 // copy from https://github.com/drcoms/drcom-generic
-use std::io;
+use std::{io, error, fmt};
 use std::fmt::Debug;
 
 use common::reader::{ReadBytesError, ReaderHelper};
@@ -24,6 +24,26 @@ pub enum DrCOMValidateError {
     PacketReadError(ReadBytesError),
 }
 
+impl fmt::Display for DrCOMValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DrCOMValidateError::CodeMismatch(code) => {
+                write!(f, "unexpected packet code {:#04x}", code)
+            }
+            DrCOMValidateError::PacketReadError(_) => write!(f, "read packet failed"),
+        }
+    }
+}
+
+impl error::Error for DrCOMValidateError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            DrCOMValidateError::PacketReadError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 pub trait DrCOMCommon {
     fn code() -> u8 {
         7u8
@@ -0,0 +1,48 @@
+use futures::Future;
+
+use common::async_transport::{AsyncTransport, AsyncTransportClient, BoxFuture};
+use drcom::pppoe::heartbeater::{ChallengeRequest, ChallengeResponse, HeartbeatRequest,
+                                KeepAliveRequest, KeepAliveResponse};
+
+/// Non-blocking `DrCOMPPPoEClient`, every method hands the client back with
+/// the parsed response.
+#[derive(Debug)]
+pub struct AsyncDrCOMPPPoEClient<T: AsyncTransport> {
+    inner: AsyncTransportClient<T>,
+}
+
+impl<T> AsyncDrCOMPPPoEClient<T>
+    where T: AsyncTransport + Send + 'static
+{
+    pub fn new(transport: T) -> Self {
+        AsyncDrCOMPPPoEClient { inner: AsyncTransportClient::new(transport) }
+    }
+
+    pub fn inner(&mut self) -> &mut AsyncTransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn challenge(self, request: &ChallengeRequest) -> BoxFuture<(Self, ChallengeResponse)> {
+        Self::wrap(self.inner.request(request.as_bytes(), |input| {
+            Ok(try!(ChallengeResponse::from_bytes(input)))
+        }))
+    }
+
+    pub fn heartbeat(self, request: &HeartbeatRequest) -> BoxFuture<(Self, KeepAliveResponse)> {
+        Self::wrap(self.inner.request(request.as_bytes(), |input| {
+            Ok(try!(KeepAliveResponse::from_bytes(input)))
+        }))
+    }
+
+    pub fn keep_alive(self, request: &KeepAliveRequest) -> BoxFuture<(Self, KeepAliveResponse)> {
+        Self::wrap(self.inner.request(request.as_bytes(), |input| {
+            Ok(try!(KeepAliveResponse::from_bytes(input)))
+        }))
+    }
+
+    fn wrap<P>(future: BoxFuture<(AsyncTransportClient<T>, P)>) -> BoxFuture<(Self, P)>
+        where P: Send + 'static
+    {
+        Box::new(future.map(|(inner, response)| (AsyncDrCOMPPPoEClient { inner: inner }, response)))
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use futures::{future, stream, Future, Stream};
+
+use common::async_transport::{AsyncTransport, BoxFuture};
+use drcom::pppoe::async_client::AsyncDrCOMPPPoEClient;
+use drcom::pppoe::heartbeater::ChallengeResponse;
+use drcom::pppoe::session::{check_response, SessionContext, KEEP_ALIVE_INTERVAL};
+
+/// Async counterpart of [`DrCOMPPPoESession`], driven the same way as
+/// `AsyncDrCOMWiredSession`.
+#[derive(Debug)]
+pub struct AsyncDrCOMPPPoESession<T: AsyncTransport> {
+    client: AsyncDrCOMPPPoEClient<T>,
+    context: SessionContext,
+}
+
+impl<T> AsyncDrCOMPPPoESession<T>
+    where T: AsyncTransport + Send + 'static
+{
+    pub fn new(transport: T) -> Self {
+        AsyncDrCOMPPPoESession {
+            client: AsyncDrCOMPPPoEClient::new(transport),
+            context: SessionContext::default(),
+        }
+    }
+
+    pub fn client(&mut self) -> &mut AsyncDrCOMPPPoEClient<T> {
+        &mut self.client
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.context.rounds()
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(KEEP_ALIVE_INTERVAL)
+    }
+
+    /// Async counterpart of [`DrCOMPPPoESession::start`].
+    pub fn start(self) -> BoxFuture<Self> {
+        let AsyncDrCOMPPPoESession { client, mut context } = self;
+        let request = context.challenge_request();
+        Box::new(client.challenge(&request).map(move |(client, challenge)| {
+            context.started(challenge);
+            Self::from_parts(client, context)
+        }))
+    }
+
+    /// Async counterpart of [`DrCOMPPPoESession::keep_alive`].
+    pub fn keep_alive(self) -> BoxFuture<Self> {
+        let AsyncDrCOMPPPoESession { client, mut context } = self;
+        let challenge: BoxFuture<_> = match context.take_challenge() {
+            Some(challenge) => Box::new(future::ok((client, challenge))),
+            None => client.challenge(&context.challenge_request()),
+        };
+
+        Box::new(challenge.and_then(move |(client, challenge)| {
+                let request = context.heartbeat_request(&challenge);
+                client.heartbeat(&request).and_then(move |(client, response)| {
+                    try!(check_response(response));
+                    Ok((Self::from_parts(client, context), challenge))
+                })
+            })
+            .and_then(|(session, challenge)| {
+                stream::iter_ok(vec![1u8, 3u8]).fold(session, move |session, type_id| {
+                    session.keep_alive_request(&challenge, type_id)
+                })
+            })
+            .map(|mut session| {
+                session.context.kept_alive();
+                session
+            }))
+    }
+
+    fn keep_alive_request(self, challenge: &ChallengeResponse, type_id: u8) -> BoxFuture<Self> {
+        let AsyncDrCOMPPPoESession { client, mut context } = self;
+        let request = context.keep_alive_request(challenge, type_id);
+        Box::new(client.keep_alive(&request).and_then(move |(client, response)| {
+            try!(check_response(response));
+            Ok(Self::from_parts(client, context))
+        }))
+    }
+
+    fn from_parts(client: AsyncDrCOMPPPoEClient<T>, context: SessionContext) -> Self {
+        AsyncDrCOMPPPoESession {
+            client: client,
+            context: context,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::io;
+
+use common::transport::{Transport, TransportClient};
+use drcom::pppoe::heartbeater::{ChallengeRequest, ChallengeResponse, HeartbeatRequest,
+                                KeepAliveRequest, KeepAliveResponse};
+use error::Result;
+
+#[derive(Debug)]
+pub struct DrCOMPPPoEClient<T: Transport> {
+    inner: TransportClient<T>,
+}
+
+impl<T> DrCOMPPPoEClient<T>
+    where T: Transport
+{
+    pub fn new(transport: T) -> Self {
+        DrCOMPPPoEClient { inner: TransportClient::new(transport) }
+    }
+
+    pub fn inner(&mut self) -> &mut TransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn challenge(&mut self, request: &ChallengeRequest) -> Result<ChallengeResponse> {
+        let response = try!(self.inner.exchange(&request.as_bytes()));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(ChallengeResponse::from_bytes(&mut buffer)))
+    }
+
+    pub fn heartbeat(&mut self, request: &HeartbeatRequest) -> Result<KeepAliveResponse> {
+        let response = try!(self.inner.exchange(&request.as_bytes()));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(KeepAliveResponse::from_bytes(&mut buffer)))
+    }
+
+    pub fn keep_alive(&mut self, request: &KeepAliveRequest) -> Result<KeepAliveResponse> {
+        let response = try!(self.inner.exchange(&request.as_bytes()));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(KeepAliveResponse::from_bytes(&mut buffer)))
+    }
+}
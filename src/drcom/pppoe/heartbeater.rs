@@ -1,20 +1,26 @@
-use std::{marker, io, result};
+use std::{marker, io, result, error, fmt};
 use std::net::Ipv4Addr;
 use std::num::Wrapping;
 
 use byteorder::{NativeEndian, NetworkEndian, ByteOrder};
+use rustc_serialize::hex::ToHex;
 
 use crypto::hash::{HasherBuilder, Hasher, HasherType};
 use common::reader::{ReadBytesError, ReaderHelper};
 use common::bytes::BytesAbleNum;
 use drcom::{DrCOMCommon, DrCOMResponseCommon, DrCOMValidateError, DrCOMFlag};
 
+const RESPONSE_LENGTH: usize = 16;
+
 #[derive(Debug)]
 pub enum DrCOMHeartbeatError {
     ValidateError(DrCOMValidateError),
     CRCHashError(CRCHashError),
     PacketReadError(ReadBytesError),
     UnexpectedBytes(Vec<u8>),
+    UnrecognizedResponse,
+    // Expect checksum {}, got {}
+    ChecksumMismatch(Vec<u8>, Vec<u8>),
 }
 
 type PacketResult<T> = result::Result<T, DrCOMHeartbeatError>;
@@ -52,6 +58,47 @@ pub enum CRCHashError {
     InputLengthInvalid,
 }
 
+impl fmt::Display for DrCOMHeartbeatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DrCOMHeartbeatError::ValidateError(_) => write!(f, "packet validate failed"),
+            DrCOMHeartbeatError::CRCHashError(_) => write!(f, "crc hash failed"),
+            DrCOMHeartbeatError::PacketReadError(_) => write!(f, "read packet failed"),
+            DrCOMHeartbeatError::UnexpectedBytes(ref bytes) => {
+                write!(f, "unexpected bytes {:?}", bytes)
+            }
+            DrCOMHeartbeatError::UnrecognizedResponse => {
+                write!(f, "unrecognized keep alive response")
+            }
+            DrCOMHeartbeatError::ChecksumMismatch(ref expect, ref got) => {
+                write!(f, "expect checksum {}, got {}", expect.to_hex(), got.to_hex())
+            }
+        }
+    }
+}
+
+impl error::Error for DrCOMHeartbeatError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            DrCOMHeartbeatError::ValidateError(ref e) => Some(e),
+            DrCOMHeartbeatError::CRCHashError(ref e) => Some(e),
+            DrCOMHeartbeatError::PacketReadError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CRCHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CRCHashError::ModeNotExist => write!(f, "crc hash mode not exist"),
+            CRCHashError::InputLengthInvalid => write!(f, "input length is not a multiple of 4"),
+        }
+    }
+}
+
+impl error::Error for CRCHashError {}
+
 struct NoneHasher;
 
 #[derive(Debug)]
@@ -235,6 +282,25 @@ impl ChallengeResponse {
             source_ip: source_ip,
         })
     }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![0u8; RESPONSE_LENGTH];
+        result[0] = 0x07;
+        result[2] = 0x10;
+        NativeEndian::write_u32(&mut result[8..12], self.challenge_seed);
+        result[12..16].copy_from_slice(&self.source_ip.octets());
+        result
+    }
+}
+
+impl HeartbeatFlag {
+    fn from_u32(value: u32) -> Option<&'static Self> {
+        match value {
+            0x2a006200 => Some(&HeartbeatFlag::First),
+            0x2a006300 => Some(&HeartbeatFlag::NotFirst),
+            _ => None,
+        }
+    }
 }
 
 impl DrCOMFlag for HeartbeatFlag {
@@ -246,6 +312,16 @@ impl DrCOMFlag for HeartbeatFlag {
     }
 }
 
+impl KeepAliveRequestFlag {
+    fn from_u32(value: u32) -> Option<&'static Self> {
+        match value {
+            0x122f270f => Some(&KeepAliveRequestFlag::First),
+            0x122f02dc => Some(&KeepAliveRequestFlag::NotFirst),
+            _ => None,
+        }
+    }
+}
+
 impl DrCOMFlag for KeepAliveRequestFlag {
     fn as_u32(&self) -> u32 {
         match *self {
@@ -278,6 +354,61 @@ impl<'a> HeartbeatRequest<'a> {
         }
     }
 
+    /// Parse a heartbeat request and verify its CRC hash.
+    pub fn from_bytes<R>(input: &mut io::BufReader<R>) -> PacketResult<HeartbeatRequest<'static>>
+        where R: io::Read
+    {
+        let bytes = try!(input.read_bytes(Self::packet_length())
+            .map_err(DrCOMHeartbeatError::PacketReadError));
+        try!(validate_request_header(&bytes, Self::code(), Self::packet_length()));
+
+        let flag = match HeartbeatFlag::from_u32(NativeEndian::read_u32(&bytes[16..20])) {
+            Some(flag) => flag,
+            None => return Err(DrCOMHeartbeatError::UnexpectedBytes(bytes[16..20].to_vec())),
+        };
+        let mut mac_address = [0u8; 6];
+        mac_address.copy_from_slice(&bytes[6..12]);
+        let request = HeartbeatRequest {
+            sequence: bytes[1],
+            type_id: bytes[4],
+            uid_length: bytes[5],
+            mac_address: mac_address,
+            source_ip: Ipv4Addr::new(bytes[12], bytes[13], bytes[14], bytes[15]),
+            flag: flag,
+            challenge_seed: NativeEndian::read_u32(&bytes[20..24]),
+        };
+
+        let expect = request.as_bytes()[24..32].to_vec();
+        if expect[..] != bytes[24..32] {
+            return Err(DrCOMHeartbeatError::ChecksumMismatch(expect, bytes[24..32].to_vec()));
+        }
+        Ok(request)
+    }
+
+    pub fn sequence(&self) -> u8 {
+        self.sequence
+    }
+
+    pub fn type_id(&self) -> u8 {
+        self.type_id
+    }
+
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.mac_address
+    }
+
+    pub fn source_ip(&self) -> Ipv4Addr {
+        self.source_ip
+    }
+
+    pub fn flag(&self) -> &DrCOMFlag {
+        self.flag
+    }
+
+    pub fn challenge_seed(&self) -> u32 {
+        self.challenge_seed
+    }
+
     #[inline]
     fn header_length() -> usize {
         1 + // code 
@@ -378,6 +509,54 @@ impl<'a> KeepAliveRequest<'a> {
         }
     }
 
+    /// Parse a keep alive request, type 3 requests must carry a valid CRC
+    /// hash.
+    pub fn from_bytes<R>(input: &mut io::BufReader<R>) -> PacketResult<KeepAliveRequest<'static>>
+        where R: io::Read
+    {
+        let bytes = try!(input.read_bytes(Self::packet_length())
+            .map_err(DrCOMHeartbeatError::PacketReadError));
+        try!(validate_request_header(&bytes, Self::code(), Self::packet_length()));
+
+        let flag = match KeepAliveRequestFlag::from_u32(NativeEndian::read_u32(&bytes[6..10])) {
+            Some(flag) => flag,
+            None => return Err(DrCOMHeartbeatError::UnexpectedBytes(bytes[6..10].to_vec())),
+        };
+        let request = KeepAliveRequest {
+            sequence: bytes[1],
+            type_id: bytes[5],
+            source_ip: Ipv4Addr::new(bytes[28], bytes[29], bytes[30], bytes[31]),
+            flag: flag,
+            keep_alive_seed: NativeEndian::read_u32(&bytes[16..20]),
+        };
+
+        let expect = request.as_bytes()[20..28].to_vec();
+        if expect[..] != bytes[20..28] {
+            return Err(DrCOMHeartbeatError::ChecksumMismatch(expect, bytes[20..28].to_vec()));
+        }
+        Ok(request)
+    }
+
+    pub fn sequence(&self) -> u8 {
+        self.sequence
+    }
+
+    pub fn type_id(&self) -> u8 {
+        self.type_id
+    }
+
+    pub fn source_ip(&self) -> Ipv4Addr {
+        self.source_ip
+    }
+
+    pub fn flag(&self) -> &DrCOMFlag {
+        self.flag
+    }
+
+    pub fn keep_alive_seed(&self) -> u32 {
+        self.keep_alive_seed
+    }
+
     #[inline]
     fn packet_length() -> usize {
         1 + // code
@@ -462,9 +641,30 @@ impl KeepAliveResponse {
 
         Ok(KeepAliveResponse { response_type: response_type })
     }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![0u8; RESPONSE_LENGTH];
+        result[0] = 0x07;
+        result[2] = match self.response_type {
+            KeepAliveResponseType::KeepAliveSucceed => 0x28,
+            KeepAliveResponseType::FileResponse => 0x10,
+            KeepAliveResponseType::UnrecognizedResponse => 0x11,
+        };
+        result
+    }
 }
 
 
+fn validate_request_header(bytes: &[u8], code: u8, length: usize) -> PacketResult<()> {
+    if bytes[0] != code {
+        return Err(DrCOMHeartbeatError::ValidateError(DrCOMValidateError::CodeMismatch(bytes[0])));
+    }
+    if NativeEndian::read_u16(&bytes[2..4]) as usize != length {
+        return Err(DrCOMHeartbeatError::UnexpectedBytes(bytes[2..4].to_vec()));
+    }
+    Ok(())
+}
+
 fn calculate_drcom_crc32(bytes: &[u8], initial: Option<u32>) -> Result<u32, CRCHashError> {
     if bytes.len() % 4 != 0 {
         return Err(CRCHashError::InputLengthInvalid);
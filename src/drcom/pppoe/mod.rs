@@ -1 +1,8 @@
-pub mod heartbeater;
\ No newline at end of file
+pub mod heartbeater;
+pub mod client;
+pub mod session;
+pub mod server;
+#[cfg(feature="async")]
+pub mod async_client;
+#[cfg(feature="async")]
+pub mod async_session;
\ No newline at end of file
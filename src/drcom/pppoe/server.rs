@@ -0,0 +1,88 @@
+use std::io;
+use std::net::Ipv4Addr;
+
+use byteorder::{NativeEndian, ByteOrder};
+use rand::{self, Rng};
+
+use common::server::Responder;
+use drcom::pppoe::heartbeater::{ChallengeResponse, HeartbeatRequest, KeepAliveRequest,
+                                KeepAliveResponse, KeepAliveResponseType};
+
+const CHALLENGE_REQUEST_LENGTH: u16 = 8;
+const HEARTBEAT_REQUEST_LENGTH: u16 = 96;
+const KEEP_ALIVE_REQUEST_LENGTH: u16 = 40;
+
+/// Emulates the DrCOM PPPoE heartbeat server, heartbeats which do not carry
+/// the seed of the last challenge or a valid CRC get an unrecognized response.
+#[derive(Debug)]
+pub struct DrCOMPPPoEServer {
+    source_ip: Ipv4Addr,
+    challenge_seed: Option<u32>,
+}
+
+impl DrCOMPPPoEServer {
+    /// `source_ip` is the address the server reports back to the client.
+    pub fn new(source_ip: Ipv4Addr) -> Self {
+        DrCOMPPPoEServer {
+            source_ip: source_ip,
+            challenge_seed: None,
+        }
+    }
+
+    fn challenge(&mut self, sequence: u8) -> Vec<u8> {
+        let challenge_seed = rand::thread_rng().gen();
+        self.challenge_seed = Some(challenge_seed);
+
+        let response = ChallengeResponse {
+            challenge_seed: challenge_seed,
+            source_ip: self.source_ip,
+        };
+        Self::with_sequence(response.as_bytes(), sequence)
+    }
+
+    fn heartbeat(&mut self, request: &[u8]) -> Vec<u8> {
+        match HeartbeatRequest::from_bytes(&mut io::BufReader::new(request)) {
+            Ok(ref heartbeat) if self.challenge_seed == Some(heartbeat.challenge_seed()) &&
+                                 heartbeat.source_ip() == self.source_ip => {
+                Self::response(request[1], KeepAliveResponseType::KeepAliveSucceed)
+            }
+            _ => Self::response(request[1], KeepAliveResponseType::UnrecognizedResponse),
+        }
+    }
+
+    fn keep_alive(&mut self, request: &[u8]) -> Vec<u8> {
+        match KeepAliveRequest::from_bytes(&mut io::BufReader::new(request)) {
+            Ok(_) => Self::response(request[1], KeepAliveResponseType::KeepAliveSucceed),
+            Err(_) => Self::response(request[1], KeepAliveResponseType::UnrecognizedResponse),
+        }
+    }
+
+    fn response(sequence: u8, response_type: KeepAliveResponseType) -> Vec<u8> {
+        let response = KeepAliveResponse { response_type: response_type };
+        Self::with_sequence(response.as_bytes(), sequence)
+    }
+
+    /// Responses echo the sequence of the request.
+    fn with_sequence(mut response: Vec<u8>, sequence: u8) -> Vec<u8> {
+        response[1] = sequence;
+        response
+    }
+}
+
+impl Responder for DrCOMPPPoEServer {
+    fn respond(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        if request.len() < 4 || request[0] != 0x07 {
+            return None;
+        }
+        let length = NativeEndian::read_u16(&request[2..4]);
+        if request.len() != length as usize {
+            return None;
+        }
+        match length {
+            CHALLENGE_REQUEST_LENGTH => Some(self.challenge(request[1])),
+            HEARTBEAT_REQUEST_LENGTH => Some(self.heartbeat(request)),
+            KEEP_ALIVE_REQUEST_LENGTH => Some(self.keep_alive(request)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use common::daemon::KeepAliveSession;
+use common::transport::Transport;
+use drcom::pppoe::client::DrCOMPPPoEClient;
+use drcom::pppoe::heartbeater::{ChallengeRequest, ChallengeResponse, HeartbeatRequest,
+                                HeartbeatFlag, KeepAliveRequest, KeepAliveRequestFlag,
+                                KeepAliveResponse, KeepAliveResponseType, DrCOMHeartbeatError};
+use error::Result;
+
+pub const KEEP_ALIVE_INTERVAL: u64 = 10;
+
+/// Drives the DrCOM PPPoE heartbeat: every round is a challenge, a heartbeat
+/// and the type 1 and type 3 keep alive packets, with one sequence shared by
+/// all of them.
+#[derive(Debug)]
+pub struct DrCOMPPPoESession<T: Transport> {
+    client: DrCOMPPPoEClient<T>,
+    context: SessionContext,
+}
+
+/// Sequence and challenge of a DrCOM PPPoE heartbeat, shared by the blocking
+/// and the async sessions which only carry the packets.
+#[derive(Debug, Default)]
+pub struct SessionContext {
+    sequence: u8,
+    rounds: usize,
+    challenge: Option<ChallengeResponse>,
+}
+
+impl<T> DrCOMPPPoESession<T>
+    where T: Transport
+{
+    pub fn new(transport: T) -> Self {
+        DrCOMPPPoESession {
+            client: DrCOMPPPoEClient::new(transport),
+            context: SessionContext::default(),
+        }
+    }
+
+    pub fn client(&mut self) -> &mut DrCOMPPPoEClient<T> {
+        &mut self.client
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.context.rounds()
+    }
+
+    /// Restart the heartbeat with a fresh challenge, the next round sends the
+    /// first flags again.
+    pub fn start(&mut self) -> Result<()> {
+        let challenge = try!(self.client.challenge(&self.context.challenge_request()));
+        self.context.started(challenge);
+        Ok(())
+    }
+
+    /// Run one heartbeat round, an `UnrecognizedResponse` from the server
+    /// fails the round.
+    pub fn keep_alive(&mut self) -> Result<()> {
+        let challenge = match self.context.take_challenge() {
+            Some(challenge) => challenge,
+            None => try!(self.client.challenge(&self.context.challenge_request())),
+        };
+
+        let request = self.context.heartbeat_request(&challenge);
+        try!(check_response(try!(self.client.heartbeat(&request))));
+        for &type_id in &[1u8, 3u8] {
+            let request = self.context.keep_alive_request(&challenge, type_id);
+            try!(check_response(try!(self.client.keep_alive(&request))));
+        }
+
+        self.context.kept_alive();
+        Ok(())
+    }
+}
+
+impl SessionContext {
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    pub fn challenge_request(&self) -> ChallengeRequest {
+        ChallengeRequest::new(Some(self.sequence))
+    }
+
+    pub fn started(&mut self, challenge: ChallengeResponse) {
+        self.rounds = 0;
+        self.challenge = Some(challenge);
+    }
+
+    /// The challenge of `start`, a round without one challenges first.
+    pub fn take_challenge(&mut self) -> Option<ChallengeResponse> {
+        self.challenge.take()
+    }
+
+    pub fn heartbeat_request(&mut self,
+                             challenge: &ChallengeResponse)
+                             -> HeartbeatRequest<'static> {
+        let flag = if self.rounds == 0 {
+            &HeartbeatFlag::First
+        } else {
+            &HeartbeatFlag::NotFirst
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        HeartbeatRequest::new(self.sequence,
+                              challenge.source_ip,
+                              flag,
+                              challenge.challenge_seed,
+                              None,
+                              None,
+                              None)
+    }
+
+    pub fn keep_alive_request(&mut self,
+                              challenge: &ChallengeResponse,
+                              type_id: u8)
+                              -> KeepAliveRequest<'static> {
+        let flag = if self.rounds == 0 {
+            &KeepAliveRequestFlag::First
+        } else {
+            &KeepAliveRequestFlag::NotFirst
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        KeepAliveRequest::new(self.sequence,
+                              flag,
+                              Some(type_id),
+                              Some(challenge.source_ip),
+                              None)
+    }
+
+    pub fn kept_alive(&mut self) {
+        self.rounds += 1;
+    }
+}
+
+impl<T> KeepAliveSession for DrCOMPPPoESession<T>
+    where T: Transport
+{
+    fn login(&mut self) -> Result<()> {
+        self.start()
+    }
+
+    fn keep_alive(&mut self) -> Result<()> {
+        DrCOMPPPoESession::keep_alive(self)
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(KEEP_ALIVE_INTERVAL)
+    }
+}
+
+/// Fail on an `UnrecognizedResponse` from the server.
+pub fn check_response(response: KeepAliveResponse) -> Result<KeepAliveResponse> {
+    if response.response_type == KeepAliveResponseType::UnrecognizedResponse {
+        return Err(DrCOMHeartbeatError::UnrecognizedResponse.into());
+    }
+    Ok(response)
+}
@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod pppoe_tests {
+    use rand;
     use std::io::BufReader;
     use std::net::Ipv4Addr;
     use std::str::FromStr;
@@ -111,16 +112,184 @@ mod pppoe_tests {
         assert_eq!(kar3.response_type,
                    KeepAliveResponseType::UnrecognizedResponse);
     }
+
+    #[test]
+    fn test_drcom_pppoe_response_as_bytes() {
+        let mut seeds: Vec<(u32, u32)> = (0..256).map(|_| rand::random()).collect();
+        seeds.extend_from_slice(&[(0, 0), (1, 0x0a000002), (0x04030201, 0xffffffff)]);
+        for (seed, source_ip) in seeds {
+            let response = ChallengeResponse {
+                challenge_seed: seed,
+                source_ip: Ipv4Addr::from(source_ip),
+            };
+            let bytes = response.as_bytes();
+            let parsed = ChallengeResponse::from_bytes(&mut BufReader::new(&bytes as &[u8]))
+                .unwrap();
+            assert_eq!(parsed.challenge_seed, seed);
+            assert_eq!(parsed.source_ip, response.source_ip);
+            assert_eq!(parsed.as_bytes(), bytes);
+        }
+
+        let response_types = || {
+            vec![KeepAliveResponseType::KeepAliveSucceed,
+                 KeepAliveResponseType::FileResponse,
+                 KeepAliveResponseType::UnrecognizedResponse]
+        };
+        for (response_type, expected) in response_types().into_iter().zip(response_types()) {
+            let bytes = KeepAliveResponse { response_type: response_type }.as_bytes();
+            let parsed = KeepAliveResponse::from_bytes(&mut BufReader::new(&bytes as &[u8]))
+                .unwrap();
+            assert_eq!(parsed.response_type, expected);
+            assert_eq!(parsed.as_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_drcom_pppoe_request_from_bytes() {
+        use drcom::DrCOMFlag;
+        use drcom::pppoe::heartbeater::DrCOMHeartbeatError;
+
+        let flag_first = HeartbeatFlag::First;
+        for &seed in &[0x04030201u32, 0x04030200u32] {
+            let hr = HeartbeatRequest::new(1,
+                                           Ipv4Addr::from_str("1.2.3.4").unwrap(),
+                                           &flag_first,
+                                           seed,
+                                           None,
+                                           None,
+                                           None);
+            let mut bytes = hr.as_bytes();
+            let parsed = HeartbeatRequest::from_bytes(&mut BufReader::new(&bytes as &[u8]))
+                .unwrap();
+            assert_eq!(parsed.challenge_seed(), seed);
+            assert_eq!(parsed.source_ip(), Ipv4Addr::from_str("1.2.3.4").unwrap());
+            assert_eq!(parsed.flag().as_u32(), flag_first.as_u32());
+            assert_eq!(parsed.as_bytes(), bytes);
+
+            bytes[25] ^= 0xff;
+            match HeartbeatRequest::from_bytes(&mut BufReader::new(&bytes as &[u8])) {
+                Err(DrCOMHeartbeatError::ChecksumMismatch(..)) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+
+        let flag_not_first = KeepAliveRequestFlag::NotFirst;
+        let ka = KeepAliveRequest::new(2u8,
+                                       &flag_not_first,
+                                       Some(3),
+                                       Some(Ipv4Addr::from_str("1.2.3.4").unwrap()),
+                                       Some(0x22221111u32));
+        let mut bytes = ka.as_bytes();
+        let parsed = KeepAliveRequest::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+        assert_eq!(parsed.sequence(), 2);
+        assert_eq!(parsed.type_id(), 3);
+        assert_eq!(parsed.keep_alive_seed(), 0x22221111);
+        assert_eq!(parsed.as_bytes(), bytes);
+
+        bytes[16] ^= 0xff;
+        match KeepAliveRequest::from_bytes(&mut BufReader::new(&bytes as &[u8])) {
+            Err(DrCOMHeartbeatError::ChecksumMismatch(..)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_drcom_pppoe_session() {
+        use std::time::Duration;
+        use common::daemon::{Backoff, KeepAliveDaemon};
+        use common::transport::LoopbackTransport;
+        use drcom::pppoe::session::DrCOMPPPoESession;
+
+        let challenge_response: Vec<u8> = (0..32).collect();
+        let transport = LoopbackTransport::scripted(vec![challenge_response.clone(),
+                                                         vec![7, 0, 0x28],
+                                                         vec![7, 0, 0x28],
+                                                         vec![7, 0, 0x10],
+                                                         challenge_response,
+                                                         vec![7, 0, 0x11]]);
+        let mut session = DrCOMPPPoESession::new(transport);
+        session.client().inner().retries(0);
+
+        let mut daemon = KeepAliveDaemon::new(session);
+        daemon.backoff(Backoff::new(Duration::from_secs(3), Duration::from_secs(60)));
+        assert_eq!(daemon.step().unwrap(), Duration::from_secs(10));
+        assert_eq!(daemon.session().rounds(), 1);
+        // the unrecognized response fails the round and schedules a new login
+        assert_eq!(daemon.step().unwrap(), Duration::from_secs(3));
+        assert!(!daemon.is_logged_in());
+
+        let mut session = daemon.into_session();
+        let sent = session.client().inner().transport().sent().to_vec();
+        assert_eq!(sent.len(), 6);
+        assert_eq!(sent[0], vec![7, 0, 8, 0, 1, 0, 0, 0]);
+        // heartbeat carries the challenge seed and source ip
+        assert_eq!(&sent[1][..4], &[7, 1, 96, 0]);
+        assert_eq!(&sent[1][12..16], &[12, 13, 14, 15]);
+        assert_eq!(&sent[1][20..24], &[8, 9, 10, 11]);
+        assert_eq!(&sent[2][..6], &[7, 2, 40, 0, 11, 1]);
+        assert_eq!(&sent[3][..6], &[7, 3, 40, 0, 11, 3]);
+        // the next round challenges again, then sends the not first flags
+        assert_eq!(sent[4], vec![7, 3, 8, 0, 1, 0, 0, 0]);
+        assert_eq!(&sent[5][..2], &[7, 4]);
+    }
+
+    #[test]
+    fn test_drcom_pppoe_mock_server() {
+        use std::time::Duration;
+        use common::server::UdpServer;
+        use common::transport::{Transport, UdpTransport};
+        use drcom::pppoe::server::DrCOMPPPoEServer;
+        use drcom::pppoe::session::DrCOMPPPoESession;
+
+        let server = DrCOMPPPoEServer::new(Ipv4Addr::from_str("10.0.0.2").unwrap());
+        let server = UdpServer::spawn_local(server).unwrap();
+        let mut transport = UdpTransport::connect("127.0.0.1:0", server.local_addr()).unwrap();
+        transport.set_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let mut session = DrCOMPPPoESession::new(transport);
+        session.start().unwrap();
+        session.keep_alive().unwrap();
+        session.keep_alive().unwrap();
+        assert_eq!(session.rounds(), 2);
+    }
+
+    #[cfg(feature="async")]
+    #[test]
+    fn test_drcom_pppoe_async_session() {
+        use futures::Future;
+        use tokio::runtime::current_thread::Runtime;
+        use common::async_transport::AsyncUdpTransport;
+        use common::server::UdpServer;
+        use drcom::pppoe::server::DrCOMPPPoEServer;
+        use drcom::pppoe::async_session::AsyncDrCOMPPPoESession;
+
+        let server = DrCOMPPPoEServer::new(Ipv4Addr::from_str("10.0.0.2").unwrap());
+        let server = UdpServer::spawn_local(server).unwrap();
+        let local_addr = "127.0.0.1:0".parse().unwrap();
+        let transport = AsyncUdpTransport::connect(&local_addr, &server.local_addr()).unwrap();
+
+        let session = AsyncDrCOMPPPoESession::new(transport);
+        let rounds = session.start()
+            .and_then(|session| session.keep_alive())
+            .and_then(|session| session.keep_alive());
+        let mut runtime = Runtime::new().unwrap();
+        assert_eq!(runtime.block_on(rounds).unwrap().rounds(), 2);
+    }
 }
 
 #[cfg(test)]
 mod wired_tests {
+    use rand;
     use std::io::BufReader;
     use std::net::Ipv4Addr;
     use std::str::FromStr;
-    use drcom::wired::dialer::{LoginAccount, LoginResponse, ChallengeRequest, ChallengeResponse};
+    use drcom::wired::dialer::{LoginAccount, LoginResponse, ChallengeRequest, ChallengeResponse,
+                               LogoutResponse, LoginError, LoginFailure};
     use drcom::wired::heartbeater::{PhaseOneRequest, PhaseOneResponse, PhaseTwoRequest,
                                     HeartbeatFlag, PhaseTwoResponse};
+    use drcom::wired::client::DrCOMWiredClient;
+    use drcom::wired::session::{DrCOMWiredSession, SessionState};
+    use common::transport::LoopbackTransport;
 
     #[test]
     fn test_drcom_wired_challenge() {
@@ -212,6 +381,38 @@ mod wired_tests {
             assert_eq!(cr.keep_alive_key, [23, 24, 25, 26, 27, 28]);
         }
 
+        {
+            let fake_response: Vec<u8> = vec![5, 0, 0, 0, 1, 10, 30, 22, 17, 0xb8, 0x88, 0xe3,
+                                              0x05, 0x16, 0x80];
+            let mut buffer = BufReader::new(&fake_response as &[u8]);
+            match LoginResponse::from_bytes(&mut buffer) {
+                Err(LoginError::LoginFailed(reason)) => {
+                    assert_eq!(reason,
+                               LoginFailure::AccountInUse {
+                                   ipaddress: Ipv4Addr::new(10, 30, 22, 17),
+                                   mac_address: [0xb8, 0x88, 0xe3, 0x05, 0x16, 0x80],
+                               });
+                    assert_eq!(reason.to_string(),
+                               "account is in use by 10.30.22.17 (b888e3051680)");
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+
+            for &(code, ref expect) in &[(0x03, LoginFailure::WrongPassword),
+                                         (0x04, LoginFailure::InsufficientBalance),
+                                         (0x16, LoginFailure::WrongIPMACBinding),
+                                         (0x17, LoginFailure::DHCPRequired),
+                                         (0x18, LoginFailure::Unknown(0x18)),
+                                         (0x42, LoginFailure::Unknown(0x42))] {
+                let fake_response: Vec<u8> = vec![5, 0, 0, 0, code];
+                let mut buffer = BufReader::new(&fake_response as &[u8]);
+                match LoginResponse::from_bytes(&mut buffer) {
+                    Err(LoginError::LoginFailed(ref reason)) => assert_eq!(reason, expect),
+                    other => panic!("unexpected result {:?}", other),
+                }
+            }
+        }
+
         {
             let mut la = LoginAccount::new("usernameusername", "password", [0x7, 0x8, 0x9, 0x10]);
             la.ipaddresses(&[Ipv4Addr::from_str("1.2.3.4").unwrap()])
@@ -337,4 +538,321 @@ mod wired_tests {
             assert!(PhaseOneResponse::from_bytes(&mut buffer).is_err());
         }
     }
+
+    #[test]
+    fn test_drcom_wired_response_as_bytes() {
+        let mut salts: Vec<[u8; 4]> = (0..256).map(|_| rand::random()).collect();
+        salts.extend_from_slice(&[[0u8; 4], [1, 2, 3, 4], [0xff; 4]]);
+        for salt in salts {
+            let bytes = ChallengeResponse { hash_salt: salt }.as_bytes();
+            let parsed = ChallengeResponse::from_bytes(&mut BufReader::new(&bytes as &[u8]))
+                .unwrap();
+            assert_eq!(parsed.hash_salt, salt);
+            assert_eq!(parsed.as_bytes(), bytes);
+        }
+
+        let mut keys: Vec<[u8; 6]> = (0..256).map(|_| rand::random()).collect();
+        keys.extend_from_slice(&[[0u8; 6], [1, 2, 3, 4, 5, 6], [0xff; 6]]);
+        for key in keys {
+            let bytes = LoginResponse { keep_alive_key: key }.as_bytes();
+            let parsed = LoginResponse::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+            assert_eq!(parsed.keep_alive_key, key);
+            assert_eq!(parsed.as_bytes(), bytes);
+        }
+
+        let failures = vec![LoginFailure::AccountInUse {
+                                ipaddress: Ipv4Addr::from_str("10.0.0.2").unwrap(),
+                                mac_address: [1, 2, 3, 4, 5, 6],
+                            },
+                            LoginFailure::ServerBusy,
+                            LoginFailure::WrongPassword,
+                            LoginFailure::InsufficientBalance,
+                            LoginFailure::AccountFrozen,
+                            LoginFailure::WrongIPAddress,
+                            LoginFailure::WrongMACAddress,
+                            LoginFailure::TooManyIPAddresses,
+                            LoginFailure::WrongClientVersion,
+                            LoginFailure::WrongIPMACBinding,
+                            LoginFailure::DHCPRequired,
+                            LoginFailure::Unknown(0x42)];
+        for failure in failures {
+            let bytes = failure.as_bytes();
+            match LoginResponse::from_bytes(&mut BufReader::new(&bytes as &[u8])) {
+                Err(LoginError::LoginFailed(parsed)) => assert_eq!(parsed, failure),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+
+        // every error code survives a round trip, known or not
+        for error_code in 1..256 {
+            let mut bytes = vec![5, 0, 0, 0, error_code as u8];
+            if error_code == 1 {
+                bytes.extend_from_slice(&[10, 0, 0, 2, 1, 2, 3, 4, 5, 6]);
+            }
+            match LoginResponse::from_bytes(&mut BufReader::new(&bytes as &[u8])) {
+                Err(LoginError::LoginFailed(parsed)) => assert_eq!(parsed.as_bytes(), bytes),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+
+        let bytes = LogoutResponse.as_bytes();
+        assert!(LogoutResponse::from_bytes(&mut BufReader::new(&bytes as &[u8])).is_ok());
+        let bytes = PhaseOneResponse.as_bytes();
+        assert!(PhaseOneResponse::from_bytes(&mut BufReader::new(&bytes as &[u8])).is_ok());
+
+        let edges = [0u8, 1, 0x7f, 0x80, 0xff];
+        for &sequence in &edges {
+            for &type_id in &edges {
+                let response = PhaseTwoResponse::new(sequence, rand::random(), Some(type_id));
+                let bytes = response.as_bytes();
+                assert_eq!(&bytes[4..6], &[0x0b, type_id]);
+                let parsed = PhaseTwoResponse::from_bytes(&mut BufReader::new(&bytes as &[u8]))
+                    .unwrap();
+                assert_eq!(parsed.sequence, sequence);
+                assert_eq!(parsed.uid_length(), 0x0b);
+                assert_eq!(parsed.type_id(), type_id);
+                assert_eq!(parsed.keep_alive_key, response.keep_alive_key);
+                assert_eq!(parsed.as_bytes(), bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_drcom_wired_request_from_bytes() {
+        use drcom::DrCOMFlag;
+        use drcom::wired::dialer::LoginRequest;
+
+        let mut la = LoginAccount::new("usernameusername", "password", [1, 2, 3, 4]);
+        la.ipaddresses(&[Ipv4Addr::from_str("10.30.22.17").unwrap()])
+            .mac_address([0xb8, 0x88, 0xe3, 0x05, 0x16, 0x80])
+            .hostname("drcom".to_string())
+            .client_version(0xa);
+        let mut bytes = la.login_request().unwrap().as_bytes().unwrap();
+
+        let parsed = LoginRequest::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+        assert_eq!(parsed.username(), "usernameusername");
+        assert_eq!(parsed.mac_address(), [0xb8, 0x88, 0xe3, 0x05, 0x16, 0x80]);
+        assert_eq!(parsed.ipaddresses()[0], Ipv4Addr::from_str("10.30.22.17").unwrap());
+        assert_eq!(parsed.hostname(), "drcom");
+        assert_eq!(parsed.client_version(), 0xa);
+        assert_eq!(parsed.as_bytes().unwrap(), bytes);
+
+        bytes[20] ^= 0x01;
+        match LoginRequest::from_bytes(&mut BufReader::new(&bytes as &[u8])) {
+            Err(LoginError::ChecksumMismatch(..)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let phase1 = PhaseOneRequest::new([1, 2, 3, 4], "password", [5, 6, 7, 8], Some(123456789));
+        let bytes = phase1.as_bytes();
+        let parsed = PhaseOneRequest::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+        assert!(parsed.verify_password([1, 2, 3, 4], "password"));
+        assert!(!parsed.verify_password([1, 2, 3, 4], "drowssap"));
+        assert_eq!(parsed.keep_alive_key(), [5, 6, 7, 8]);
+        assert_eq!(parsed.as_bytes(), bytes);
+
+        let flag_not_first = HeartbeatFlag::NotFirst;
+        let phase2 = PhaseTwoRequest::new(1,
+                                          [5, 6, 7, 8],
+                                          &flag_not_first,
+                                          Ipv4Addr::from_str("1.2.3.4").unwrap(),
+                                          Some(3));
+        let bytes = phase2.as_bytes();
+        let parsed = PhaseTwoRequest::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+        assert_eq!(parsed.sequence(), 1);
+        assert_eq!(parsed.type_id(), 3);
+        assert_eq!(parsed.flag().as_u32(), flag_not_first.as_u32());
+        assert_eq!(parsed.host_ip(), Ipv4Addr::from_str("1.2.3.4").unwrap());
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_drcom_wired_client() {
+        let transport = LoopbackTransport::scripted(vec![vec![2, 3, 4, 5, 6, 7, 8, 9, 10],
+                                                         vec![7, 3, 4, 5]]);
+        let mut client = DrCOMWiredClient::new(transport);
+
+        let cr = client.challenge(&ChallengeRequest::new(Some(1))).unwrap();
+        assert_eq!(cr.hash_salt, [6u8, 7u8, 8u8, 9u8]);
+
+        let phase1 = PhaseOneRequest::new(cr.hash_salt, "password", [5, 6, 7, 8], Some(123456789));
+        assert!(client.phase_one(&phase1).is_ok());
+        assert!(client.challenge(&ChallengeRequest::new(Some(1))).is_err());
+        assert_eq!(client.inner().transport().sent()[0],
+                   vec![1, 2, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    fn fake_phase_two_response(sequence: u8, key: [u8; 4]) -> Vec<u8> {
+        let mut response = vec![0u8; 40];
+        response[0] = 7;
+        response[1] = sequence;
+        response[2] = 0x28;
+        response[16..20].copy_from_slice(&key);
+        response
+    }
+
+    #[test]
+    fn test_drcom_wired_session() {
+        let mut login_response = vec![0u8; 32];
+        login_response[0] = 4;
+        login_response[23..29].copy_from_slice(&[9, 8, 7, 6, 5, 4]);
+
+        let transport = LoopbackTransport::scripted(vec![vec![2, 0, 0, 0, 1, 2, 3, 4],
+                                                         login_response,
+                                                         vec![7],
+                                                         fake_phase_two_response(0, [1; 4]),
+                                                         fake_phase_two_response(1, [2; 4]),
+                                                         fake_phase_two_response(2, [3; 4]),
+                                                         vec![7],
+                                                         fake_phase_two_response(3, [4; 4]),
+                                                         fake_phase_two_response(4, [5; 4]),
+                                                         vec![],
+                                                         vec![2, 0, 0, 0, 5, 6, 7, 8],
+                                                         vec![4, 1, 0, 0]]);
+        let mut la = LoginAccount::new("usernameusername", "password", [0; 4]);
+        la.ipaddresses(&[Ipv4Addr::from_str("10.30.22.17").unwrap()]);
+        let mut session =
+            DrCOMWiredSession::new(transport, la, Ipv4Addr::from_str("10.30.22.17").unwrap());
+        session.client().inner().retries(0);
+
+        assert!(session.keep_alive().is_err());
+        session.start().unwrap();
+        assert_eq!(session.state(), SessionState::LoggedIn);
+        session.keep_alive().unwrap();
+        assert_eq!(session.state(), SessionState::KeepingAlive);
+        session.keep_alive().unwrap();
+        assert!(session.keep_alive().is_err());
+        session.logout().unwrap();
+        assert_eq!(session.state(), SessionState::LoggedOut);
+        assert!(session.keep_alive().is_err());
+
+        let sent = session.client().inner().transport().sent().to_vec();
+        assert_eq!(sent.len(), 12);
+        // login packet is salted with the challenge
+        assert_eq!(&sent[1][..20],
+                   &[3, 1, 0, 36, 174, 175, 144, 214, 168, 238, 67, 106, 128, 153, 49, 172, 94,
+                     102, 177, 222]);
+        // phase one carries the login key
+        assert_eq!(sent[2][0], 0xff);
+        assert_eq!(&sent[2][20..24], &[9, 8, 7, 6]);
+        // first phase two uses the first flag, then keys and sequence rotate
+        assert_eq!(&sent[3][..10], &[7, 0, 40, 0, 11, 1, 15, 39, 47, 18]);
+        assert_eq!(&sent[3][16..20], &[0; 4]);
+        assert_eq!(&sent[4][..10], &[7, 1, 40, 0, 11, 1, 220, 2, 47, 18]);
+        assert_eq!(&sent[4][16..20], &[1; 4]);
+        assert_eq!(&sent[5][..6], &[7, 2, 40, 0, 11, 3]);
+        assert_eq!(&sent[5][16..20], &[2; 4]);
+        assert_eq!(sent[6][0], 0xff);
+        assert_eq!(&sent[7][..6], &[7, 3, 40, 0, 11, 1]);
+        assert_eq!(&sent[7][16..20], &[3; 4]);
+        assert_eq!(&sent[8][..6], &[7, 4, 40, 0, 11, 3]);
+        assert_eq!(&sent[8][16..20], &[4; 4]);
+        // logout is salted with a fresh challenge
+        assert_eq!(sent[10][0], 1);
+        assert_eq!(&sent[11][..4], &[6, 1, 0, 36]);
+    }
+
+    #[test]
+    fn test_drcom_wired_logout() {
+        let mut la = LoginAccount::new("usernameusername", "password", [1, 2, 3, 4]);
+        la.mac_address([0xb8, 0x88, 0xe3, 0x05, 0x16, 0x80]);
+        let logout_bytes = la.logout_request().unwrap().as_bytes().unwrap();
+        let real_bytes: Vec<u8> = vec![6, 1, 0, 36, 205, 150, 231, 111, 164, 64, 51, 55, 174, 166,
+                                       215, 161, 33, 174, 163, 175, 117, 115, 101, 114, 110, 97,
+                                       109, 101, 117, 115, 101, 114, 110, 97, 109, 101, 0, 0, 0, 0,
+                                       0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 32, 1, 117,
+                                       30, 4, 106, 178, 192, 2, 12, 240, 28, 106, 83, 0, 0, 184,
+                                       136, 227, 5, 22, 128];
+        assert_eq!(logout_bytes, real_bytes);
+
+        assert!(LogoutResponse::from_bytes(&mut BufReader::new(&[4u8, 1, 0, 0][..])).is_ok());
+        assert!(LogoutResponse::from_bytes(&mut BufReader::new(&[5u8, 1, 0, 0][..])).is_err());
+        assert!(LogoutResponse::from_bytes(&mut BufReader::new(&[4u8, 1, 0, 0, 0][..])).is_err());
+
+        // a login response shares the code but is no logout acknowledgement
+        let login_bytes = LoginResponse { keep_alive_key: [1, 2, 3, 4, 5, 6] }.as_bytes();
+        assert!(LogoutResponse::from_bytes(&mut BufReader::new(&login_bytes as &[u8])).is_err());
+    }
+
+    #[test]
+    fn test_drcom_wired_mock_server() {
+        use std::time::Duration;
+        use common::server::UdpServer;
+        use common::transport::{Transport, UdpTransport};
+        use drcom::wired::server::DrCOMWiredServer;
+        use error::Error;
+
+        let server = DrCOMWiredServer::new("usernameusername", "password");
+        let server = UdpServer::spawn_local(server).unwrap();
+        let connect = || {
+            let mut transport = UdpTransport::connect("127.0.0.1:0", server.local_addr())
+                .unwrap();
+            transport.set_timeout(Some(Duration::from_secs(5))).unwrap();
+            transport
+        };
+        let host_ip = Ipv4Addr::from_str("10.30.22.17").unwrap();
+
+        let mut la = LoginAccount::new("usernameusername", "password", [0; 4]);
+        la.ipaddresses(&[host_ip]);
+        let mut session = DrCOMWiredSession::new(connect(), la, host_ip);
+        session.start().unwrap();
+        session.keep_alive().unwrap();
+        session.keep_alive().unwrap();
+        session.logout().unwrap();
+        assert_eq!(session.state(), SessionState::LoggedOut);
+
+        let la = LoginAccount::new("usernameusername", "wrongpassword", [0; 4]);
+        let mut session = DrCOMWiredSession::new(connect(), la, host_ip);
+        match session.start() {
+            Err(Error::DrCOMLogin(LoginError::LoginFailed(LoginFailure::WrongPassword))) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature="async")]
+    #[test]
+    fn test_drcom_wired_async_session() {
+        use futures::Future;
+        use tokio::runtime::current_thread::Runtime;
+        use common::async_transport::AsyncUdpTransport;
+        use common::server::UdpServer;
+        use drcom::wired::async_session::AsyncDrCOMWiredSession;
+        use drcom::wired::server::DrCOMWiredServer;
+        use error::Error;
+
+        let server = DrCOMWiredServer::new("usernameusername", "password");
+        let server = UdpServer::spawn_local(server).unwrap();
+        let local_addr = "127.0.0.1:0".parse().unwrap();
+        let connect = || AsyncUdpTransport::connect(&local_addr, &server.local_addr()).unwrap();
+        let host_ip = Ipv4Addr::from_str("10.30.22.17").unwrap();
+        let mut runtime = Runtime::new().unwrap();
+
+        let mut la = LoginAccount::new("usernameusername", "password", [0; 4]);
+        la.ipaddresses(&[host_ip]);
+        let session = AsyncDrCOMWiredSession::new(connect(), la, host_ip);
+        match runtime.block_on(session.keep_alive()) {
+            Err(Error::DrCOMWiredSession(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|session| session.state())),
+        }
+
+        let mut la = LoginAccount::new("usernameusername", "password", [0; 4]);
+        la.ipaddresses(&[host_ip]);
+        let session = AsyncDrCOMWiredSession::new(connect(), la, host_ip);
+        let session = runtime.block_on(session.start()
+                .and_then(|session| session.keep_alive())
+                .and_then(|session| {
+                    assert_eq!(session.state(), SessionState::KeepingAlive);
+                    session.keep_alive()
+                })
+                .and_then(|session| session.logout()))
+            .unwrap();
+        assert_eq!(session.state(), SessionState::LoggedOut);
+
+        let la = LoginAccount::new("usernameusername", "wrongpassword", [0; 4]);
+        let session = AsyncDrCOMWiredSession::new(connect(), la, host_ip);
+        match runtime.block_on(session.start()) {
+            Err(Error::DrCOMLogin(LoginError::LoginFailed(LoginFailure::WrongPassword))) => {}
+            other => panic!("unexpected result {:?}", other.map(|session| session.state())),
+        }
+    }
 }
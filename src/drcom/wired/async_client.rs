@@ -0,0 +1,64 @@
+use futures::{future, Future};
+
+use common::async_transport::{AsyncTransport, AsyncTransportClient, BoxFuture};
+use drcom::wired::dialer::{ChallengeRequest, ChallengeResponse, LoginRequest, LoginResponse,
+                           LogoutRequest, LogoutResponse};
+use drcom::wired::heartbeater::{PhaseOneRequest, PhaseOneResponse, PhaseTwoRequest,
+                                PhaseTwoResponse};
+
+/// Non-blocking `DrCOMWiredClient`, every method hands the client back
+/// with the parsed response.
+#[derive(Debug)]
+pub struct AsyncDrCOMWiredClient<T: AsyncTransport> {
+    inner: AsyncTransportClient<T>,
+}
+
+impl<T> AsyncDrCOMWiredClient<T>
+    where T: AsyncTransport + Send + 'static
+{
+    pub fn new(transport: T) -> Self {
+        AsyncDrCOMWiredClient { inner: AsyncTransportClient::new(transport) }
+    }
+
+    pub fn inner(&mut self) -> &mut AsyncTransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn challenge(self, request: &ChallengeRequest) -> BoxFuture<(Self, ChallengeResponse)> {
+        Self::wrap(self.inner.request(request.as_bytes(), |input| {
+            Ok(try!(ChallengeResponse::from_bytes(input)))
+        }))
+    }
+
+    pub fn login(self, request: &LoginRequest) -> BoxFuture<(Self, LoginResponse)> {
+        let inner = self.inner;
+        Self::wrap(Box::new(future::result(request.as_bytes()).from_err().and_then(|request| {
+            inner.request(request, |input| Ok(try!(LoginResponse::from_bytes(input))))
+        })))
+    }
+
+    pub fn logout(self, request: &LogoutRequest) -> BoxFuture<(Self, LogoutResponse)> {
+        let inner = self.inner;
+        Self::wrap(Box::new(future::result(request.as_bytes()).from_err().and_then(|request| {
+            inner.request(request, |input| Ok(try!(LogoutResponse::from_bytes(input))))
+        })))
+    }
+
+    pub fn phase_one(self, request: &PhaseOneRequest) -> BoxFuture<(Self, PhaseOneResponse)> {
+        Self::wrap(self.inner.request(request.as_bytes(), |input| {
+            Ok(try!(PhaseOneResponse::from_bytes(input)))
+        }))
+    }
+
+    pub fn phase_two(self, request: &PhaseTwoRequest) -> BoxFuture<(Self, PhaseTwoResponse)> {
+        Self::wrap(self.inner.request(request.as_bytes(), |input| {
+            Ok(try!(PhaseTwoResponse::from_bytes(input)))
+        }))
+    }
+
+    fn wrap<P>(future: BoxFuture<(AsyncTransportClient<T>, P)>) -> BoxFuture<(Self, P)>
+        where P: Send + 'static
+    {
+        Box::new(future.map(|(inner, response)| (AsyncDrCOMWiredClient { inner: inner }, response)))
+    }
+}
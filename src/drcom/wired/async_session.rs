@@ -0,0 +1,127 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use futures::{future, stream, Future, Stream};
+
+use common::async_transport::{AsyncTransport, BoxFuture};
+use common::clock::Clock;
+use drcom::wired::async_client::AsyncDrCOMWiredClient;
+use drcom::wired::dialer::{ChallengeRequest, LoginAccount};
+use drcom::wired::heartbeater::HeartbeatFlag;
+use drcom::wired::session::{SessionContext, SessionState, KEEP_ALIVE_INTERVAL};
+
+/// Non-blocking `DrCOMWiredSession`, every step takes the session by value
+/// and resolves to it once the server answered.
+#[derive(Debug)]
+pub struct AsyncDrCOMWiredSession<T: AsyncTransport> {
+    client: AsyncDrCOMWiredClient<T>,
+    context: SessionContext,
+}
+
+impl<T> AsyncDrCOMWiredSession<T>
+    where T: AsyncTransport + Send + 'static
+{
+    pub fn new(transport: T, account: LoginAccount, host_ip: Ipv4Addr) -> Self {
+        AsyncDrCOMWiredSession {
+            client: AsyncDrCOMWiredClient::new(transport),
+            context: SessionContext::new(account, host_ip),
+        }
+    }
+
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.context.clock(clock);
+        self
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.context.state()
+    }
+
+    pub fn client(&mut self) -> &mut AsyncDrCOMWiredClient<T> {
+        &mut self.client
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(KEEP_ALIVE_INTERVAL)
+    }
+
+    pub fn challenge(self) -> BoxFuture<Self> {
+        let AsyncDrCOMWiredSession { client, mut context } = self;
+        Box::new(client.challenge(&ChallengeRequest::new(None)).map(move |(client, response)| {
+            context.challenged(&response);
+            Self::from_parts(client, context)
+        }))
+    }
+
+    pub fn login(self) -> BoxFuture<Self> {
+        let AsyncDrCOMWiredSession { client, mut context } = self;
+        let request = match context.login_request() {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        Box::new(client.login(&request).map(move |(client, response)| {
+            context.logged_in(&response);
+            Self::from_parts(client, context)
+        }))
+    }
+
+    pub fn start(self) -> BoxFuture<Self> {
+        Box::new(self.challenge().and_then(|session| session.login()))
+    }
+
+    /// Async counterpart of [`DrCOMWiredSession::keep_alive`].
+    pub fn keep_alive(self) -> BoxFuture<Self> {
+        let AsyncDrCOMWiredSession { client, context } = self;
+        let request = match context.phase_one_request() {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let rounds = context.phase_two_rounds();
+        Box::new(client.phase_one(&request)
+            .and_then(move |(client, _)| {
+                let session = Self::from_parts(client, context);
+                stream::iter_ok(rounds)
+                    .fold(session, |session, (flag, type_id)| session.phase_two(&flag, type_id))
+            })
+            .map(|mut session| {
+                session.context.kept_alive();
+                session
+            }))
+    }
+
+    /// Async counterpart of [`DrCOMWiredSession::logout`].
+    pub fn logout(self) -> BoxFuture<Self> {
+        let AsyncDrCOMWiredSession { client, mut context } = self;
+        if let Err(e) = context.expect_logged_in() {
+            return Box::new(future::err(e));
+        }
+
+        Box::new(client.challenge(&ChallengeRequest::new(None))
+            .and_then(move |(client, response)| {
+                future::result(context.logout_request(&response))
+                    .and_then(move |request| client.logout(&request))
+                    .map(move |(client, _)| {
+                        context.logged_out();
+                        Self::from_parts(client, context)
+                    })
+            }))
+    }
+
+    fn phase_two(self, flag: &HeartbeatFlag, type_id: u8) -> BoxFuture<Self> {
+        let AsyncDrCOMWiredSession { client, mut context } = self;
+        let request = context.phase_two_request(flag, type_id);
+        Box::new(client.phase_two(&request).map(move |(client, response)| {
+            context.phase_two_answered(&response);
+            Self::from_parts(client, context)
+        }))
+    }
+
+    fn from_parts(client: AsyncDrCOMWiredClient<T>, context: SessionContext) -> Self {
+        AsyncDrCOMWiredSession {
+            client: client,
+            context: context,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::io;
+
+use common::transport::{Transport, TransportClient};
+use drcom::wired::dialer::{ChallengeRequest, ChallengeResponse, LoginRequest, LoginResponse,
+                           LogoutRequest, LogoutResponse};
+use drcom::wired::heartbeater::{PhaseOneRequest, PhaseOneResponse, PhaseTwoRequest,
+                                PhaseTwoResponse};
+use error::Result;
+
+#[derive(Debug)]
+pub struct DrCOMWiredClient<T: Transport> {
+    inner: TransportClient<T>,
+}
+
+impl<T> DrCOMWiredClient<T>
+    where T: Transport
+{
+    pub fn new(transport: T) -> Self {
+        DrCOMWiredClient { inner: TransportClient::new(transport) }
+    }
+
+    pub fn inner(&mut self) -> &mut TransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn challenge(&mut self, request: &ChallengeRequest) -> Result<ChallengeResponse> {
+        let response = try!(self.inner.exchange(&request.as_bytes()));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(ChallengeResponse::from_bytes(&mut buffer)))
+    }
+
+    pub fn login(&mut self, request: &LoginRequest) -> Result<LoginResponse> {
+        let response = try!(self.inner.exchange(&try!(request.as_bytes())));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(LoginResponse::from_bytes(&mut buffer)))
+    }
+
+    pub fn logout(&mut self, request: &LogoutRequest) -> Result<LogoutResponse> {
+        let response = try!(self.inner.exchange(&try!(request.as_bytes())));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(LogoutResponse::from_bytes(&mut buffer)))
+    }
+
+    pub fn phase_one(&mut self, request: &PhaseOneRequest) -> Result<PhaseOneResponse> {
+        let response = try!(self.inner.exchange(&request.as_bytes()));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(PhaseOneResponse::from_bytes(&mut buffer)))
+    }
+
+    pub fn phase_two(&mut self, request: &PhaseTwoRequest) -> Result<PhaseTwoResponse> {
+        let response = try!(self.inner.exchange(&request.as_bytes()));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(PhaseTwoResponse::from_bytes(&mut buffer)))
+    }
+}
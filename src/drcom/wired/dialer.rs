@@ -1,4 +1,4 @@
-use std::{io, result};
+use std::{io, result, error, fmt};
 use std::net::Ipv4Addr;
 use std::str::FromStr;
 use std::num::Wrapping;
@@ -6,7 +6,7 @@ use std::num::Wrapping;
 use rand;
 use rand::Rng;
 use rustc_serialize::hex::ToHex;
-use byteorder::{NetworkEndian, ByteOrder};
+use byteorder::{NetworkEndian, NativeEndian, ByteOrder};
 
 use drcom::{DrCOMCommon, DrCOMResponseCommon, DrCOMValidateError, USERNAME_MAX_LEN,
             PASSWORD_MAX_LEN, PACKET_MAGIC_NUMBER};
@@ -15,11 +15,91 @@ use common::reader::{ReadBytesError, ReaderHelper};
 use common::bytes::{BytesAble, BytesAbleNum};
 use crypto::hash::{HasherType, HasherBuilder};
 
+const CHALLENGE_RESPONSE_LENGTH: usize = 8;
+const LOGIN_RESPONSE_LENGTH: usize = 48;
+const LOGOUT_RESPONSE_LENGTH: usize = 4;
+
 #[derive(Debug)]
 pub enum LoginError {
     ValidateError(DrCOMValidateError),
     PacketReadError(ReadBytesError),
     FieldValueOverflow(usize, usize),
+    LoginFailed(LoginFailure),
+    // Expect checksum {}, got {}
+    ChecksumMismatch(Vec<u8>, Vec<u8>),
+    UnexpectedBytes(Vec<u8>),
+}
+
+/// Reason carried by a login response with code 0x05.
+#[derive(Debug, PartialEq)]
+pub enum LoginFailure {
+    // Account is online at {ipaddress} with {mac_address}
+    AccountInUse {
+        ipaddress: Ipv4Addr,
+        mac_address: [u8; 6],
+    },
+    ServerBusy,
+    WrongPassword,
+    InsufficientBalance,
+    AccountFrozen,
+    WrongIPAddress,
+    WrongMACAddress,
+    TooManyIPAddresses,
+    WrongClientVersion,
+    WrongIPMACBinding,
+    DHCPRequired,
+    Unknown(u8),
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoginError::ValidateError(_) => write!(f, "packet validate failed"),
+            LoginError::PacketReadError(_) => write!(f, "read packet failed"),
+            LoginError::FieldValueOverflow(length, max_length) => {
+                write!(f, "field length {} exceeds the limit {}", length, max_length)
+            }
+            LoginError::LoginFailed(ref reason) => write!(f, "server refused: {}", reason),
+            LoginError::ChecksumMismatch(ref expect, ref got) => {
+                write!(f, "expect checksum {}, got {}", expect.to_hex(), got.to_hex())
+            }
+            LoginError::UnexpectedBytes(ref bytes) => write!(f, "unexpected bytes {:?}", bytes),
+        }
+    }
+}
+
+impl fmt::Display for LoginFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoginFailure::AccountInUse { ipaddress, mac_address } => {
+                write!(f,
+                       "account is in use by {} ({})",
+                       ipaddress,
+                       mac_address.to_hex())
+            }
+            LoginFailure::ServerBusy => write!(f, "server is busy"),
+            LoginFailure::WrongPassword => write!(f, "wrong username or password"),
+            LoginFailure::InsufficientBalance => write!(f, "insufficient balance"),
+            LoginFailure::AccountFrozen => write!(f, "account is frozen"),
+            LoginFailure::WrongIPAddress => write!(f, "ip address is not allowed"),
+            LoginFailure::WrongMACAddress => write!(f, "mac address is not allowed"),
+            LoginFailure::TooManyIPAddresses => write!(f, "too many ip addresses"),
+            LoginFailure::WrongClientVersion => write!(f, "client version is not supported"),
+            LoginFailure::WrongIPMACBinding => write!(f, "ip and mac address binding mismatch"),
+            LoginFailure::DHCPRequired => write!(f, "dhcp client is required"),
+            LoginFailure::Unknown(code) => write!(f, "unknown error code {:#04x}", code),
+        }
+    }
+}
+
+impl error::Error for LoginError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            LoginError::ValidateError(ref e) => Some(e),
+            LoginError::PacketReadError(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 type LoginResult<T> = result::Result<T, LoginError>;
@@ -107,6 +187,18 @@ pub struct LoginResponse {
     pub keep_alive_key: [u8; 6],
 }
 
+#[derive(Debug)]
+pub struct LogoutRequest {
+    mac_address: [u8; 6],
+    account_info: TagAccountInfo,
+    control_check_status: u8,
+    adapter_count: u8,
+    auth_extra_option: u16,
+}
+
+#[derive(Debug)]
+pub struct LogoutResponse;
+
 #[derive(Debug)]
 pub struct LoginAccount {
     username: String,
@@ -137,6 +229,7 @@ pub struct LoginAccount {
 }
 
 const SERVICE_PACK_MAX_LEN: usize = 32;
+const LOGOUT_MAGIC_NUMBER: u16 = 0x0106u16;
 const HOSTNAME_MAX_LEN: usize = 32;
 
 
@@ -175,7 +268,7 @@ impl ChallengeRequest {
     pub fn new(sequence: Option<u16>) -> Self {
         ChallengeRequest {
             sequence: sequence.unwrap_or_else(|| {
-                current_timestamp() as u16 + rand::thread_rng().gen_range(0xF, 0xFF)
+                (current_timestamp() as u16).wrapping_add(rand::thread_rng().gen_range(0xF, 0xFF))
             }),
         }
     }
@@ -228,6 +321,14 @@ impl ChallengeResponse {
 
         Ok(ChallengeResponse { hash_salt: hash_salt })
     }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![0u8; CHALLENGE_RESPONSE_LENGTH];
+        result[0] = 0x02;
+        result[1] = 0x02;
+        result[4..8].copy_from_slice(&self.hash_salt);
+        result
+    }
 }
 
 impl TagOSVersionInfo {
@@ -384,8 +485,12 @@ impl LoginAccount {
     }
 
     fn password_md5_hash(&self) -> [u8; 16] {
+        self.salted_password_md5_hash(PACKET_MAGIC_NUMBER)
+    }
+
+    fn salted_password_md5_hash(&self, magic_number: u16) -> [u8; 16] {
         let mut md5 = HasherBuilder::build(HasherType::MD5);
-        md5.update(&PACKET_MAGIC_NUMBER.as_bytes_le());
+        md5.update(&magic_number.as_bytes_le());
         md5.update(&self.hash_salt);
         md5.update(self.password.as_bytes());
 
@@ -480,6 +585,20 @@ impl LoginAccount {
         })
     }
 
+    pub fn logout_request(&self) -> LoginResult<LogoutRequest> {
+        try!(self.validate());
+        Ok(LogoutRequest {
+            mac_address: self.mac_address,
+            account_info: TagAccountInfo {
+                username: self.username.clone(),
+                password_md5_hash: self.salted_password_md5_hash(LOGOUT_MAGIC_NUMBER),
+            },
+            control_check_status: self.control_check_status,
+            adapter_count: self.adapter_count,
+            auth_extra_option: self.auth_extra_option,
+        })
+    }
+
     pub fn ipaddresses(&mut self, value: &[Ipv4Addr]) -> &mut Self {
         let mut fixed_ipaddresses = [Ipv4Addr::from(0x0); 4];
         for (i, ip) in value.into_iter().take(4).enumerate() {
@@ -489,7 +608,16 @@ impl LoginAccount {
         self
     }
 
-    configurable_field!(adapter_count: u8,
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    configurable_field!(hash_salt: [u8; 4],
+                        adapter_count: u8,
                         mac_address: [u8; 6],
                         dog_flag: u8,
                         client_version: u8,
@@ -725,6 +853,301 @@ impl LoginRequest {
     }
 }
 
+impl LoginRequest {
+    /// Parse a login request as sent by a client, verifying the phase two
+    /// hash and the checksum of the extra auth info.
+    pub fn from_bytes<R>(input: &mut io::BufReader<R>) -> LoginResult<Self>
+        where R: io::Read
+    {
+        // every field read so far, the hash and the checksum cover them
+        let mut origin = Vec::new();
+
+        let magic_number_bytes = try!(read_field(input, &mut origin, 2));
+        if magic_number_bytes != PACKET_MAGIC_NUMBER.as_bytes_le() {
+            return Err(LoginError::UnexpectedBytes(magic_number_bytes));
+        }
+
+        let account_info;
+        {
+            let length_bytes = try!(read_field(input, &mut origin, 2));
+            let content_length = NetworkEndian::read_u16(&length_bytes) as usize;
+            if content_length < 20 {
+                return Err(LoginError::UnexpectedBytes(length_bytes));
+            }
+            let mut password_md5_hash = [0u8; 16];
+            password_md5_hash.copy_from_slice(&try!(read_field(input, &mut origin, 16)));
+            let username_bytes = try!(read_field(input, &mut origin, content_length - 20));
+            account_info = TagAccountInfo {
+                username: try!(string_from_bytes(username_bytes)),
+                password_md5_hash: password_md5_hash,
+            };
+        }
+        // padding?
+        try!(read_field(input, &mut origin, 20));
+        let control_check_status = try!(read_field(input, &mut origin, 1))[0];
+
+        let adapter_info;
+        {
+            let counts = try!(read_field(input, &mut origin, 1))[0];
+            let mut hashed_mac_address = [0u8; 6];
+            hashed_mac_address.copy_from_slice(&try!(read_field(input, &mut origin, 6)));
+            let mut password_md5_hash_validator = [0u8; 16];
+            password_md5_hash_validator.copy_from_slice(&try!(read_field(input, &mut origin, 16)));
+            // count of the specified ip addresses
+            try!(read_field(input, &mut origin, 1));
+            let mut ipaddresses = [Ipv4Addr::from(0x0); 4];
+            for ipaddress in &mut ipaddresses {
+                *ipaddress = ipv4_from_bytes(&try!(read_field(input, &mut origin, 4)));
+            }
+            adapter_info = TagAdapterInfo {
+                counts: counts,
+                password_md5_hash: account_info.password_md5_hash,
+                mac_address: TagAdapterInfo::hash_mac_address(&hashed_mac_address,
+                                                              &account_info.password_md5_hash),
+                password_md5_hash_validator: password_md5_hash_validator,
+                ipaddresses: ipaddresses,
+            };
+        }
+
+        {
+            const PHASE_TWO_HASH_SALT: [u8; 4] = [0x14, 0x00, 0x07, 0x0b];
+            let mut md5 = HasherBuilder::build(HasherType::MD5);
+            md5.update(&origin);
+            md5.update(&PHASE_TWO_HASH_SALT);
+            let expect = md5.finish()[..8].to_vec();
+            let got = try!(read_field(input, &mut origin, 8));
+            if expect != got {
+                return Err(LoginError::ChecksumMismatch(expect, got));
+            }
+        }
+
+        let dog_flag = try!(read_field(input, &mut origin, 1))[0];
+        // padding?
+        try!(read_field(input, &mut origin, 4));
+
+        let host_info;
+        {
+            let hostname_bytes = try!(read_field(input, &mut origin, HOSTNAME_MAX_LEN));
+            let dns_server = ipv4_from_bytes(&try!(read_field(input, &mut origin, 4)));
+            let dhcp_server = ipv4_from_bytes(&try!(read_field(input, &mut origin, 4)));
+            let backup_dns_server = ipv4_from_bytes(&try!(read_field(input, &mut origin, 4)));
+            let mut wins_ips = [Ipv4Addr::from(0x0); 2];
+            for ipaddress in &mut wins_ips {
+                *ipaddress = ipv4_from_bytes(&try!(read_field(input, &mut origin, 4)));
+            }
+            host_info = TagHostInfo {
+                hostname: try!(string_from_bytes(hostname_bytes)),
+                dns_server: dns_server,
+                dhcp_server: dhcp_server,
+                backup_dns_server: backup_dns_server,
+                wins_ips: wins_ips,
+            };
+        }
+
+        let os_version_info;
+        {
+            let length_bytes = try!(read_field(input, &mut origin, 4));
+            if NativeEndian::read_u32(&length_bytes) as usize !=
+               TagOSVersionInfo::attribute_length() {
+                return Err(LoginError::UnexpectedBytes(length_bytes));
+            }
+            let mut numbers = [0u32; 4];
+            for number in &mut numbers {
+                *number = NativeEndian::read_u32(&try!(read_field(input, &mut origin, 4)));
+            }
+            let service_pack_bytes = try!(read_field(input, &mut origin, 128));
+            os_version_info = TagOSVersionInfo {
+                major_version: numbers[0],
+                minor_version: numbers[1],
+                build_number: numbers[2],
+                platform_id: numbers[3],
+                service_pack: try!(string_from_bytes(service_pack_bytes)),
+            };
+        }
+
+        let auth_version_bytes = try!(read_field(input, &mut origin, 2));
+        let auth_version_info = TagAuthVersionInfo {
+            client_version: auth_version_bytes[0],
+            dog_version: auth_version_bytes[1],
+        };
+
+        // the ldap auth info starts with 0x00, the extra auth info with 0x02
+        let mut code = try!(input.read_bytes(1).map_err(LoginError::PacketReadError))[0];
+        let mut ldap_auth_info = None;
+        if code == 0 {
+            origin.push(code);
+            let length = try!(read_field(input, &mut origin, 1))[0] as usize;
+            let password_ror_hash = try!(read_field(input, &mut origin, length));
+            ldap_auth_info = Some(TagLDAPAuthInfo { password_ror_hash: password_ror_hash });
+            code = try!(input.read_bytes(1).map_err(LoginError::PacketReadError))[0];
+        }
+        if code != TagAuthExtraInfo::code() {
+            return Err(LoginError::UnexpectedBytes(vec![code]));
+        }
+
+        let mac_address;
+        {
+            let extra_bytes = try!(input.read_bytes(TagAuthExtraInfo::attribute_length() - 1)
+                .map_err(LoginError::PacketReadError));
+            let mut fixed_mac_address = [0u8; 6];
+            fixed_mac_address.copy_from_slice(&extra_bytes[7..13]);
+            mac_address = fixed_mac_address;
+
+            let auth_extra_info = TagAuthExtraInfo {
+                origin_data: &origin,
+                mac_address: mac_address,
+                option: 0,
+            };
+            let expect = auth_extra_info.check_sum().as_bytes_le();
+            let got = extra_bytes[1..5].to_vec();
+            if expect != got {
+                return Err(LoginError::ChecksumMismatch(expect, got));
+            }
+        }
+
+        let tail_bytes = try!(input.read_bytes(4).map_err(LoginError::PacketReadError));
+        Ok(LoginRequest {
+            mac_address: mac_address,
+            account_info: account_info,
+            control_check_status: control_check_status,
+            adapter_info: adapter_info,
+            dog_flag: dog_flag,
+            host_info: host_info,
+            os_version_info: os_version_info,
+            auth_version_info: auth_version_info,
+            auto_logout: tail_bytes[0] != 0,
+            broadcast_mode: tail_bytes[1] != 0,
+            random: NativeEndian::read_u16(&tail_bytes[2..4]),
+            ldap_auth_info: ldap_auth_info,
+            auth_extra_option: 0,
+        })
+    }
+
+    pub fn username(&self) -> &str {
+        &self.account_info.username
+    }
+
+    pub fn password_md5_hash(&self) -> [u8; 16] {
+        self.account_info.password_md5_hash
+    }
+
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.mac_address
+    }
+
+    pub fn ipaddresses(&self) -> &[Ipv4Addr; 4] {
+        &self.adapter_info.ipaddresses
+    }
+
+    pub fn hostname(&self) -> &str {
+        &self.host_info.hostname
+    }
+
+    pub fn client_version(&self) -> u8 {
+        self.auth_version_info.client_version
+    }
+}
+
+impl DrCOMCommon for LogoutRequest {
+    fn code() -> u8 {
+        6u8
+    }
+}
+
+impl LogoutRequest {
+    #[inline]
+    fn username_length() -> usize {
+        36
+    }
+
+    fn packet_length(&self) -> usize {
+        1 + // code
+        1 + // type
+        1 + // padding?
+        1 + // username length + 20
+        16 + // password_md5_hash
+        Self::username_length() +
+        1 + // control_check_status
+        1 + // adapter count
+        6 + // hashed mac address
+        TagAuthExtraInfo::attribute_length()
+    }
+
+    pub fn as_bytes(&self) -> LoginResult<Vec<u8>> {
+        try!(self.account_info.validate());
+
+        let mut result = Vec::with_capacity(self.packet_length());
+        {
+            let mut username_bytes = vec![0u8; Self::username_length()];
+            username_bytes[..self.account_info.username.len()]
+                .copy_from_slice(self.account_info.username.as_bytes());
+
+            result.push(Self::code());
+            result.push(1u8);
+            // padding?
+            result.push(0u8);
+            result.push((self.account_info.username.len() + 20) as u8);
+            result.extend_from_slice(&self.account_info.password_md5_hash);
+            result.extend(username_bytes);
+            result.push(self.control_check_status);
+            result.push(self.adapter_count);
+            result.extend_from_slice(&TagAdapterInfo::hash_mac_address(&self.mac_address,
+                                                     &self.account_info.password_md5_hash));
+        }
+
+        let auth_extra_bytes;
+        {
+            let auth_extra_info = TagAuthExtraInfo {
+                origin_data: &result,
+                mac_address: self.mac_address,
+                option: self.auth_extra_option,
+            };
+            auth_extra_bytes = try!(auth_extra_info.as_bytes());
+        }
+        result.extend(auth_extra_bytes);
+
+        Ok(result)
+    }
+}
+
+impl DrCOMResponseCommon for LogoutResponse {}
+impl DrCOMCommon for LogoutResponse {
+    fn code() -> u8 {
+        4u8
+    }
+}
+
+impl LogoutResponse {
+    pub fn from_bytes<R>(input: &mut io::BufReader<R>) -> LoginResult<Self>
+        where R: io::Read
+    {
+        // validate packet and consume 1 byte
+        try!(Self::validate_stream(input, |c| c == Self::code())
+            .map_err(LoginError::ValidateError));
+
+        // echoes the type of the logout request, which tells it from the
+        // longer login response sharing its code
+        let bytes = try!(input.read_bytes(LOGOUT_RESPONSE_LENGTH - 1)
+            .map_err(LoginError::PacketReadError));
+        if bytes[0] != Self::logout_type() || input.read_bytes(1).is_ok() {
+            return Err(LoginError::UnexpectedBytes(bytes));
+        }
+        Ok(LogoutResponse {})
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![0u8; LOGOUT_RESPONSE_LENGTH];
+        result[0] = Self::code();
+        result[1] = Self::logout_type();
+        result
+    }
+
+    #[inline]
+    fn logout_type() -> u8 {
+        1u8
+    }
+}
+
 impl DrCOMResponseCommon for LoginResponse {}
 impl DrCOMCommon for LoginResponse {
     fn code() -> u8 {
@@ -732,13 +1155,86 @@ impl DrCOMCommon for LoginResponse {
     }
 }
 
+impl LoginFailure {
+    fn code() -> u8 {
+        5u8
+    }
+
+    fn from_bytes<R>(input: &mut io::BufReader<R>) -> LoginResult<Self>
+        where R: io::Read
+    {
+        // drain unknow bytes
+        try!(input.read_bytes(3).map_err(LoginError::PacketReadError));
+
+        let error_code = try!(input.read_bytes(1).map_err(LoginError::PacketReadError))[0];
+        let failure = match error_code {
+            0x01 => {
+                let ip_bytes = try!(input.read_bytes(4).map_err(LoginError::PacketReadError));
+                let mac_bytes = try!(input.read_bytes(6).map_err(LoginError::PacketReadError));
+                let mut mac_address = [0u8; 6];
+                mac_address.clone_from_slice(&mac_bytes);
+                LoginFailure::AccountInUse {
+                    ipaddress: Ipv4Addr::new(ip_bytes[0], ip_bytes[1], ip_bytes[2], ip_bytes[3]),
+                    mac_address: mac_address,
+                }
+            }
+            0x02 => LoginFailure::ServerBusy,
+            0x03 => LoginFailure::WrongPassword,
+            0x04 => LoginFailure::InsufficientBalance,
+            0x05 => LoginFailure::AccountFrozen,
+            0x07 => LoginFailure::WrongIPAddress,
+            0x0b => LoginFailure::WrongMACAddress,
+            0x14 => LoginFailure::TooManyIPAddresses,
+            0x15 => LoginFailure::WrongClientVersion,
+            0x16 => LoginFailure::WrongIPMACBinding,
+            0x17 => LoginFailure::DHCPRequired,
+            _ => LoginFailure::Unknown(error_code),
+        };
+        Ok(failure)
+    }
+
+    fn error_code(&self) -> u8 {
+        match *self {
+            LoginFailure::AccountInUse { .. } => 0x01,
+            LoginFailure::ServerBusy => 0x02,
+            LoginFailure::WrongPassword => 0x03,
+            LoginFailure::InsufficientBalance => 0x04,
+            LoginFailure::AccountFrozen => 0x05,
+            LoginFailure::WrongIPAddress => 0x07,
+            LoginFailure::WrongMACAddress => 0x0b,
+            LoginFailure::TooManyIPAddresses => 0x14,
+            LoginFailure::WrongClientVersion => 0x15,
+            LoginFailure::WrongIPMACBinding => 0x16,
+            LoginFailure::DHCPRequired => 0x17,
+            LoginFailure::Unknown(error_code) => error_code,
+        }
+    }
+
+    /// The failure response a server sends instead of a `LoginResponse`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![Self::code(), 0, 0, 0, self.error_code()];
+        if let LoginFailure::AccountInUse { ipaddress, mac_address } = *self {
+            result.extend_from_slice(&ipaddress.octets());
+            result.extend_from_slice(&mac_address);
+        }
+        result
+    }
+}
+
 impl LoginResponse {
     pub fn from_bytes<R>(input: &mut io::BufReader<R>) -> LoginResult<Self>
         where R: io::Read
     {
         // validate packet and consume 1 byte
-        try!(Self::validate_stream(input, |c| c == Self::code())
+        let mut code = 0u8;
+        try!(Self::validate_stream(input, |c| {
+                code = c;
+                c == Self::code() || c == LoginFailure::code()
+            })
             .map_err(LoginError::ValidateError));
+        if code == LoginFailure::code() {
+            return Err(LoginError::LoginFailed(try!(LoginFailure::from_bytes(input))));
+        }
 
         // drain unknow bytes
         try!(input.read_bytes(22).map_err(LoginError::PacketReadError));
@@ -749,6 +1245,36 @@ impl LoginResponse {
 
         Ok(LoginResponse { keep_alive_key: keep_alive_key })
     }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![0u8; LOGIN_RESPONSE_LENGTH];
+        result[0] = Self::code();
+        result[23..29].copy_from_slice(&self.keep_alive_key);
+        result
+    }
+}
+
+fn read_field<R>(input: &mut io::BufReader<R>,
+                 origin: &mut Vec<u8>,
+                 length: usize)
+                 -> LoginResult<Vec<u8>>
+    where R: io::Read
+{
+    let bytes = try!(input.read_bytes(length).map_err(LoginError::PacketReadError));
+    origin.extend_from_slice(&bytes);
+    Ok(bytes)
+}
+
+fn string_from_bytes(mut bytes: Vec<u8>) -> LoginResult<String> {
+    // strip the zero padding
+    while bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    String::from_utf8(bytes).map_err(|e| LoginError::UnexpectedBytes(e.into_bytes()))
+}
+
+fn ipv4_from_bytes(bytes: &[u8]) -> Ipv4Addr {
+    Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])
 }
 
 #[test]
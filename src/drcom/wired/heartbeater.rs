@@ -1,19 +1,47 @@
-use std::{result, io};
+use std::{result, io, error, fmt};
 use std::net::Ipv4Addr;
 
-use byteorder::{NativeEndian, ByteOrder};
+use byteorder::{NativeEndian, NetworkEndian, ByteOrder};
 
 use drcom::{DrCOMCommon, DrCOMResponseCommon, DrCOMValidateError, PACKET_MAGIC_NUMBER, DrCOMFlag};
-use common::utils::current_timestamp;
+use common::clock::{Clock, FixedClock};
 use common::reader::{ReadBytesError, ReaderHelper};
 use common::bytes::BytesAbleNum;
 use crypto::hash::{HasherType, HasherBuilder};
 
+const PHASE_TWO_RESPONSE_LENGTH: u16 = 0x28;
+
 #[derive(Debug)]
 pub enum HeartbeatError {
     ValidateError(DrCOMValidateError),
     ResponseLengthMismatch(u16, u16),
     PacketReadError(ReadBytesError),
+    UnexpectedBytes(Vec<u8>),
+}
+
+impl fmt::Display for HeartbeatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeartbeatError::ValidateError(_) => write!(f, "packet validate failed"),
+            HeartbeatError::ResponseLengthMismatch(got, expect) => {
+                write!(f, "expect response length {}, got {}", expect, got)
+            }
+            HeartbeatError::PacketReadError(_) => write!(f, "read packet failed"),
+            HeartbeatError::UnexpectedBytes(ref bytes) => {
+                write!(f, "unexpected bytes {:?}", bytes)
+            }
+        }
+    }
+}
+
+impl error::Error for HeartbeatError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            HeartbeatError::ValidateError(ref e) => Some(e),
+            HeartbeatError::PacketReadError(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 type HeartbeatResult<T> = result::Result<T, HeartbeatError>;
@@ -21,8 +49,7 @@ type HeartbeatResult<T> = result::Result<T, HeartbeatError>;
 #[derive(Debug)]
 pub struct PhaseOneRequest {
     timestamp: u32,
-    hash_salt: [u8; 4],
-    password: String,
+    password_hash: [u8; 16],
     keep_alive_key: [u8; 4],
 }
 
@@ -40,6 +67,9 @@ pub struct PhaseOneResponse;
 #[derive(Debug)]
 pub struct PhaseTwoResponse {
     pub sequence: u8,
+    // echoes the uid length (0x0b) and type id of the request
+    uid_length: u8,
+    type_id: u8,
     pub keep_alive_key: [u8; 4],
 }
 
@@ -61,14 +91,60 @@ impl PhaseOneRequest {
                keep_alive_key: [u8; 4],
                timestamp: Option<u32>)
                -> Self {
+        Self::with_clock(hash_salt,
+                         password,
+                         keep_alive_key,
+                         &FixedClock::from_timestamp(timestamp))
+    }
+
+    /// Like `new`, with the timestamp told by `clock`.
+    pub fn with_clock(hash_salt: [u8; 4],
+                      password: &str,
+                      keep_alive_key: [u8; 4],
+                      clock: &Clock)
+                      -> Self {
         PhaseOneRequest {
-            timestamp: timestamp.unwrap_or_else(current_timestamp),
-            hash_salt: hash_salt,
-            password: password.to_string(),
+            timestamp: clock.now(),
+            password_hash: Self::password_hash(hash_salt, password),
             keep_alive_key: keep_alive_key,
         }
     }
 
+    /// Parse a phase one request, only the low 16 bits of the timestamp are
+    /// carried by the packet.
+    pub fn from_bytes<R>(input: &mut io::BufReader<R>) -> HeartbeatResult<Self>
+        where R: io::Read
+    {
+        let bytes = try!(input.read_bytes(Self::packet_length())
+            .map_err(HeartbeatError::PacketReadError));
+        if bytes[0] != Self::code() {
+            return Err(HeartbeatError::ValidateError(DrCOMValidateError::CodeMismatch(bytes[0])));
+        }
+
+        let mut password_hash = [0u8; 16];
+        password_hash.copy_from_slice(&bytes[1..17]);
+        let mut keep_alive_key = [0u8; 4];
+        keep_alive_key.copy_from_slice(&bytes[20..24]);
+        Ok(PhaseOneRequest {
+            timestamp: NetworkEndian::read_u16(&bytes[24..26]) as u32,
+            password_hash: password_hash,
+            keep_alive_key: keep_alive_key,
+        })
+    }
+
+    pub fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    pub fn keep_alive_key(&self) -> [u8; 4] {
+        self.keep_alive_key
+    }
+
+    /// Whether the request was hashed with `hash_salt` and `password`.
+    pub fn verify_password(&self, hash_salt: [u8; 4], password: &str) -> bool {
+        self.password_hash == Self::password_hash(hash_salt, password)
+    }
+
     fn packet_length() -> usize {
         1 + // code
         16 + // password hash
@@ -78,11 +154,11 @@ impl PhaseOneRequest {
         4 // padding?
     }
 
-    fn password_hash(&self) -> [u8; 16] {
+    fn password_hash(hash_salt: [u8; 4], password: &str) -> [u8; 16] {
         let mut md5 = HasherBuilder::build(HasherType::MD5);
         md5.update(&PACKET_MAGIC_NUMBER.as_bytes_le());
-        md5.update(&self.hash_salt);
-        md5.update(self.password.as_bytes());
+        md5.update(&hash_salt);
+        md5.update(password.as_bytes());
 
         let mut md5_digest = [0u8; 16];
         md5_digest.copy_from_slice(&md5.finish());
@@ -92,7 +168,7 @@ impl PhaseOneRequest {
     pub fn as_bytes(&self) -> Vec<u8> {
         let mut result = Vec::with_capacity(Self::packet_length());
         result.push(Self::code());
-        result.extend_from_slice(&self.password_hash());
+        result.extend_from_slice(&self.password_hash);
         // padding?
         result.extend_from_slice(&[0u8; 3]);
         result.extend_from_slice(&self.keep_alive_key);
@@ -118,6 +194,20 @@ impl PhaseOneResponse {
             .map_err(HeartbeatError::ValidateError));
         Ok(PhaseOneResponse {})
     }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        vec![Self::code(), 0, 0x10, 0]
+    }
+}
+
+impl HeartbeatFlag {
+    fn from_u32(value: u32) -> Option<&'static Self> {
+        match value {
+            0x122f270f => Some(&HeartbeatFlag::First),
+            0x122f02dc => Some(&HeartbeatFlag::NotFirst),
+            _ => None,
+        }
+    }
 }
 
 impl DrCOMFlag for HeartbeatFlag {
@@ -153,6 +243,56 @@ impl<'a> PhaseTwoRequest<'a> {
         }
     }
 
+    pub fn from_bytes<R>(input: &mut io::BufReader<R>)
+                         -> HeartbeatResult<PhaseTwoRequest<'static>>
+        where R: io::Read
+    {
+        let bytes = try!(input.read_bytes(Self::packet_length())
+            .map_err(HeartbeatError::PacketReadError));
+        if bytes[0] != Self::code() {
+            return Err(HeartbeatError::ValidateError(DrCOMValidateError::CodeMismatch(bytes[0])));
+        }
+        let length = NativeEndian::read_u16(&bytes[2..4]);
+        if length as usize != Self::packet_length() {
+            return Err(HeartbeatError::UnexpectedBytes(bytes[2..4].to_vec()));
+        }
+
+        let flag = match HeartbeatFlag::from_u32(NativeEndian::read_u32(&bytes[6..10])) {
+            Some(flag) => flag,
+            None => return Err(HeartbeatError::UnexpectedBytes(bytes[6..10].to_vec())),
+        };
+        let mut keep_alive_key = [0u8; 4];
+        keep_alive_key.copy_from_slice(&bytes[16..20]);
+        Ok(PhaseTwoRequest {
+            sequence: bytes[1],
+            keep_alive_key: keep_alive_key,
+            flag: flag,
+            type_id: bytes[5],
+            host_ip: Ipv4Addr::new(bytes[28], bytes[29], bytes[30], bytes[31]),
+        })
+    }
+
+    pub fn sequence(&self) -> u8 {
+        self.sequence
+    }
+
+    pub fn keep_alive_key(&self) -> [u8; 4] {
+        self.keep_alive_key
+    }
+
+    pub fn type_id(&self) -> u8 {
+        self.type_id
+    }
+
+    pub fn flag(&self) -> &DrCOMFlag {
+        self.flag
+    }
+
+    /// Only type 3 packets carry the host ip.
+    pub fn host_ip(&self) -> Ipv4Addr {
+        self.host_ip
+    }
+
     #[inline]
     fn packet_length() -> usize {
         1 + // code
@@ -210,11 +350,18 @@ impl<'a> PhaseTwoRequest<'a> {
 impl DrCOMCommon for PhaseTwoResponse {}
 impl DrCOMResponseCommon for PhaseTwoResponse {}
 impl PhaseTwoResponse {
+    pub fn new(sequence: u8, keep_alive_key: [u8; 4], type_id: Option<u8>) -> Self {
+        PhaseTwoResponse {
+            sequence: sequence,
+            uid_length: 0x0b,
+            type_id: type_id.unwrap_or(1),
+            keep_alive_key: keep_alive_key,
+        }
+    }
+
     pub fn from_bytes<R>(input: &mut io::BufReader<R>) -> HeartbeatResult<Self>
         where R: io::Read
     {
-        const PHASE_TWO_RESPONSE_LENGTH: u16 = 0x28;
-
         // validate packet and consume 1 byte
         try!(Self::validate_stream(input, |c| c == Self::code())
             .map_err(HeartbeatError::ValidateError));
@@ -231,14 +378,37 @@ impl PhaseTwoResponse {
             }
         }
 
+        let uid = try!(input.read_bytes(2).map_err(HeartbeatError::PacketReadError));
+
         // drain unknow bytes
-        try!(input.read_bytes(12).map_err(HeartbeatError::PacketReadError));
+        try!(input.read_bytes(10).map_err(HeartbeatError::PacketReadError));
 
         let mut keep_alive_key = [0u8; 4];
         keep_alive_key.copy_from_slice(&try!(input.read_bytes(4).map_err(HeartbeatError::PacketReadError)));
         Ok(PhaseTwoResponse {
             sequence: sequence,
+            uid_length: uid[0],
+            type_id: uid[1],
             keep_alive_key: keep_alive_key,
         })
     }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![0u8; PHASE_TWO_RESPONSE_LENGTH as usize];
+        result[0] = Self::code();
+        result[1] = self.sequence;
+        NativeEndian::write_u16(&mut result[2..4], PHASE_TWO_RESPONSE_LENGTH);
+        result[4] = self.uid_length;
+        result[5] = self.type_id;
+        result[16..20].copy_from_slice(&self.keep_alive_key);
+        result
+    }
+
+    pub fn uid_length(&self) -> u8 {
+        self.uid_length
+    }
+
+    pub fn type_id(&self) -> u8 {
+        self.type_id
+    }
 }
\ No newline at end of file
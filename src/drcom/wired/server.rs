@@ -0,0 +1,142 @@
+use std::io;
+
+use rand::{self, Rng};
+
+use common::server::Responder;
+use crypto::hash::{HasherBuilder, HasherType};
+use drcom::wired::dialer::{LoginRequest, LoginResponse, LoginFailure, LogoutResponse};
+use drcom::wired::heartbeater::{PhaseOneRequest, PhaseOneResponse, PhaseTwoRequest,
+                                PhaseTwoResponse};
+
+/// Emulates a DrCOM wired authentication server for a single account.
+///
+/// Requests which carry a wrong password hash or keep alive key are dropped,
+/// the way the real server ignores them.
+#[derive(Debug)]
+pub struct DrCOMWiredServer {
+    username: String,
+    password: String,
+    hash_salt: [u8; 4],
+    login_key: [u8; 6],
+    phase_two_key: [u8; 4],
+    logged_in: bool,
+}
+
+impl DrCOMWiredServer {
+    pub fn new(username: &str, password: &str) -> Self {
+        DrCOMWiredServer {
+            username: username.to_string(),
+            password: password.to_string(),
+            hash_salt: [0u8; 4],
+            login_key: [0u8; 6],
+            phase_two_key: [0u8; 4],
+            logged_in: false,
+        }
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.logged_in
+    }
+
+    fn challenge(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        if request.len() < 4 {
+            return None;
+        }
+        self.hash_salt = rand::thread_rng().gen();
+
+        let mut response = vec![0x02, 0x02, request[2], request[3]];
+        response.extend_from_slice(&self.hash_salt);
+        Some(response)
+    }
+
+    fn login(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        let login_request = match LoginRequest::from_bytes(&mut io::BufReader::new(request)) {
+            Ok(login_request) => login_request,
+            Err(_) => return None,
+        };
+        if login_request.username() != self.username ||
+           login_request.password_md5_hash()[..] != self.salted_hash(&request[..2])[..] {
+            return Some(LoginFailure::WrongPassword.as_bytes());
+        }
+        self.login_key = rand::thread_rng().gen();
+        self.phase_two_key = [0u8; 4];
+        self.logged_in = true;
+
+        Some(LoginResponse { keep_alive_key: self.login_key }.as_bytes())
+    }
+
+    fn phase_one(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        if !self.logged_in {
+            return None;
+        }
+        let phase_one = match PhaseOneRequest::from_bytes(&mut io::BufReader::new(request)) {
+            Ok(phase_one) => phase_one,
+            Err(_) => return None,
+        };
+        if !phase_one.verify_password(self.hash_salt, &self.password) ||
+           phase_one.keep_alive_key()[..] != self.login_key[..4] {
+            return None;
+        }
+        Some(PhaseOneResponse.as_bytes())
+    }
+
+    fn phase_two(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        if !self.logged_in {
+            return None;
+        }
+        let phase_two = match PhaseTwoRequest::from_bytes(&mut io::BufReader::new(request)) {
+            Ok(phase_two) => phase_two,
+            Err(_) => return None,
+        };
+        if phase_two.keep_alive_key() != self.phase_two_key {
+            return None;
+        }
+        self.phase_two_key = rand::thread_rng().gen();
+
+        let response = PhaseTwoResponse::new(phase_two.sequence(),
+                                             self.phase_two_key,
+                                             Some(phase_two.type_id()));
+        Some(response.as_bytes())
+    }
+
+    fn logout(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        if !self.logged_in || !self.verify_account(request) {
+            return None;
+        }
+        self.logged_in = false;
+        Some(LogoutResponse.as_bytes())
+    }
+
+    /// Logout requests start with the salted password hash and the username.
+    fn verify_account(&self, request: &[u8]) -> bool {
+        if request.len() < 20 || (request[3] as usize) < 20 {
+            return false;
+        }
+        let username_end = request[3] as usize;
+        if request.len() < username_end || request[4..20] != self.salted_hash(&request[..2])[..] {
+            return false;
+        }
+        &request[20..username_end] == self.username.as_bytes()
+    }
+
+    fn salted_hash(&self, magic_number: &[u8]) -> Vec<u8> {
+        let mut md5 = HasherBuilder::build(HasherType::MD5);
+        md5.update(magic_number);
+        md5.update(&self.hash_salt);
+        md5.update(self.password.as_bytes());
+        md5.finish()
+    }
+}
+
+impl Responder for DrCOMWiredServer {
+    fn respond(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        match request.first() {
+            Some(&0x01) => self.challenge(request),
+            Some(&0x03) => self.login(request),
+            Some(&0xff) => self.phase_one(request),
+            Some(&0x07) => self.phase_two(request),
+            Some(&0x06) => self.logout(request),
+            _ => None,
+        }
+    }
+}
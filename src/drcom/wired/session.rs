@@ -0,0 +1,274 @@
+use std::{error, fmt, thread};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use common::clock::{Clock, SystemClock};
+use common::daemon::KeepAliveSession;
+use common::transport::Transport;
+use drcom::wired::client::DrCOMWiredClient;
+use drcom::wired::dialer::{ChallengeRequest, ChallengeResponse, LoginAccount, LoginRequest,
+                           LoginResponse, LogoutRequest};
+use drcom::wired::heartbeater::{PhaseOneRequest, PhaseTwoRequest, PhaseTwoResponse,
+                                HeartbeatFlag};
+use error::Result;
+
+pub const KEEP_ALIVE_INTERVAL: u64 = 20;
+
+#[derive(Debug)]
+pub enum SessionError {
+    // Operation requires state {}, current is {}
+    InvalidState(SessionState, SessionState),
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SessionState {
+    Initial,
+    Challenged,
+    LoggedIn,
+    KeepingAlive,
+    LoggedOut,
+}
+
+/// Drives a DrCOM wired session: challenge, login, the keep-alive rounds and
+/// logout, carrying the hash salt, keep alive keys and sequence between packets.
+#[derive(Debug)]
+pub struct DrCOMWiredSession<T: Transport> {
+    client: DrCOMWiredClient<T>,
+    context: SessionContext,
+}
+
+/// The state machine of a DrCOM wired session without any I/O: it builds the
+/// next request and takes in the response, so that the blocking and the
+/// async sessions only have to carry the packets.
+#[derive(Debug)]
+pub struct SessionContext {
+    account: LoginAccount,
+    host_ip: Ipv4Addr,
+    state: SessionState,
+    hash_salt: [u8; 4],
+    login_key: [u8; 6],
+    sequence: u8,
+    phase_two_key: [u8; 4],
+    clock: Box<Clock>,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SessionError::InvalidState(expect, current) => {
+                write!(f, "operation requires state {:?}, current is {:?}", expect, current)
+            }
+        }
+    }
+}
+
+impl error::Error for SessionError {}
+
+impl<T> DrCOMWiredSession<T>
+    where T: Transport
+{
+    pub fn new(transport: T, account: LoginAccount, host_ip: Ipv4Addr) -> Self {
+        DrCOMWiredSession {
+            client: DrCOMWiredClient::new(transport),
+            context: SessionContext::new(account, host_ip),
+        }
+    }
+
+    /// Clock of the phase one timestamps.
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.context.clock(clock);
+        self
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.context.state()
+    }
+
+    pub fn client(&mut self) -> &mut DrCOMWiredClient<T> {
+        &mut self.client
+    }
+
+    pub fn challenge(&mut self) -> Result<[u8; 4]> {
+        let response = try!(self.client.challenge(&ChallengeRequest::new(None)));
+        self.context.challenged(&response);
+        Ok(response.hash_salt)
+    }
+
+    pub fn login(&mut self) -> Result<()> {
+        let request = try!(self.context.login_request());
+        let response = try!(self.client.login(&request));
+        self.context.logged_in(&response);
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        try!(self.challenge());
+        self.login()
+    }
+
+    /// Run one keep-alive round, the first round after login also sends the
+    /// `HeartbeatFlag::First` phase two packet.
+    pub fn keep_alive(&mut self) -> Result<()> {
+        let request = try!(self.context.phase_one_request());
+        try!(self.client.phase_one(&request));
+
+        for (flag, type_id) in self.context.phase_two_rounds() {
+            let request = self.context.phase_two_request(&flag, type_id);
+            let response = try!(self.client.phase_two(&request));
+            self.context.phase_two_answered(&response);
+        }
+
+        self.context.kept_alive();
+        Ok(())
+    }
+
+    /// Log out with a fresh challenge salt.
+    pub fn logout(&mut self) -> Result<()> {
+        try!(self.context.expect_logged_in());
+
+        let response = try!(self.client.challenge(&ChallengeRequest::new(None)));
+        let request = try!(self.context.logout_request(&response));
+        try!(self.client.logout(&request));
+        self.context.logged_out();
+        Ok(())
+    }
+
+    /// Log in if needed and keep the session alive every `interval` until
+    /// `keep_running` returns false.
+    pub fn run<F>(&mut self, interval: Option<Duration>, mut keep_running: F) -> Result<()>
+        where F: FnMut(&Self) -> bool
+    {
+        let interval = interval.unwrap_or_else(|| Duration::from_secs(KEEP_ALIVE_INTERVAL));
+        while keep_running(self) {
+            match self.state() {
+                SessionState::LoggedIn |
+                SessionState::KeepingAlive => {}
+                _ => try!(self.start()),
+            }
+            try!(self.keep_alive());
+            thread::sleep(interval);
+        }
+        Ok(())
+    }
+}
+
+impl SessionContext {
+    pub fn new(account: LoginAccount, host_ip: Ipv4Addr) -> Self {
+        SessionContext {
+            account: account,
+            host_ip: host_ip,
+            state: SessionState::Initial,
+            hash_salt: [0u8; 4],
+            login_key: [0u8; 6],
+            sequence: 0,
+            phase_two_key: [0u8; 4],
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    pub fn challenged(&mut self, response: &ChallengeResponse) {
+        self.hash_salt = response.hash_salt;
+        self.state = SessionState::Challenged;
+    }
+
+    pub fn login_request(&mut self) -> Result<LoginRequest> {
+        try!(self.expect_state(&[SessionState::Challenged]));
+        Ok(try!(self.account.hash_salt(self.hash_salt).login_request()))
+    }
+
+    pub fn logged_in(&mut self, response: &LoginResponse) {
+        self.login_key = response.keep_alive_key;
+        self.sequence = 0;
+        self.phase_two_key = [0u8; 4];
+        self.state = SessionState::LoggedIn;
+    }
+
+    pub fn phase_one_request(&self) -> Result<PhaseOneRequest> {
+        try!(self.expect_logged_in());
+        let mut key = [0u8; 4];
+        key.copy_from_slice(&self.login_key[..4]);
+        Ok(PhaseOneRequest::with_clock(self.hash_salt, self.account.password(), key, &*self.clock))
+    }
+
+    /// Flag and type id of the phase two packets of this round.
+    pub fn phase_two_rounds(&self) -> Vec<(HeartbeatFlag, u8)> {
+        let mut rounds = Vec::new();
+        if self.state == SessionState::LoggedIn {
+            rounds.push((HeartbeatFlag::First, 1));
+        }
+        rounds.push((HeartbeatFlag::NotFirst, 1));
+        rounds.push((HeartbeatFlag::NotFirst, 3));
+        rounds
+    }
+
+    pub fn phase_two_request<'a>(&self,
+                                 flag: &'a HeartbeatFlag,
+                                 type_id: u8)
+                                 -> PhaseTwoRequest<'a> {
+        PhaseTwoRequest::new(self.sequence,
+                             self.phase_two_key,
+                             flag,
+                             self.host_ip,
+                             Some(type_id))
+    }
+
+    pub fn phase_two_answered(&mut self, response: &PhaseTwoResponse) {
+        self.phase_two_key = response.keep_alive_key;
+        self.sequence = self.sequence.wrapping_add(1);
+    }
+
+    pub fn kept_alive(&mut self) {
+        self.state = SessionState::KeepingAlive;
+    }
+
+    /// Salt the logout request with the response of a fresh challenge.
+    pub fn logout_request(&mut self, response: &ChallengeResponse) -> Result<LogoutRequest> {
+        try!(self.expect_logged_in());
+        self.hash_salt = response.hash_salt;
+        Ok(try!(self.account.hash_salt(self.hash_salt).logout_request()))
+    }
+
+    pub fn logged_out(&mut self) {
+        self.state = SessionState::LoggedOut;
+    }
+
+    pub fn expect_logged_in(&self) -> Result<()> {
+        self.expect_state(&[SessionState::LoggedIn, SessionState::KeepingAlive])
+    }
+
+    fn expect_state(&self, states: &[SessionState]) -> Result<()> {
+        if states.contains(&self.state) {
+            return Ok(());
+        }
+        Err(SessionError::InvalidState(states[0], self.state).into())
+    }
+}
+
+impl<T> KeepAliveSession for DrCOMWiredSession<T>
+    where T: Transport
+{
+    fn login(&mut self) -> Result<()> {
+        self.start()
+    }
+
+    fn keep_alive(&mut self) -> Result<()> {
+        DrCOMWiredSession::keep_alive(self)
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(KEEP_ALIVE_INTERVAL)
+    }
+}
@@ -0,0 +1,220 @@
+use std::{error, fmt, io, result};
+
+use common::reader::ReadBytesError;
+use common::dialer::DecodeError;
+use common::registry::RegistryError;
+use common::pcap::PcapError;
+use crypto::cipher::CipherError;
+#[cfg(feature="drcom")]
+use drcom::DrCOMValidateError;
+#[cfg(feature="drcom")]
+use drcom::wired::dialer::LoginError;
+#[cfg(feature="drcom")]
+use drcom::wired::heartbeater::HeartbeatError;
+#[cfg(feature="drcom")]
+use drcom::wired::session::SessionError;
+#[cfg(feature="drcom")]
+use drcom::pppoe::heartbeater::{DrCOMHeartbeatError, CRCHashError};
+#[cfg(feature="netkeeper")]
+use netkeeper::heartbeater::NetkeeperHeartbeatError;
+#[cfg(feature="singlenet")]
+use singlenet::heartbeater::SinglenetHeartbeatError;
+#[cfg(feature="singlenet")]
+use singlenet::attributes::ParseAttributesError;
+#[cfg(feature="ghca")]
+use ghca::dialer::GhcaDialerError;
+#[cfg(feature="ipclient")]
+use ipclient::dialer::MACOpenErr;
+
+/// The error type shared by every protocol in this crate.
+///
+/// Each variant wraps the detailed error of the module it comes from, which
+/// stays reachable through `std::error::Error::source`.
+#[derive(Debug)]
+pub enum Error {
+    IO(io::Error),
+    // No response after {} attempts
+    Timeout(usize),
+    ReadBytes(ReadBytesError),
+    Cipher(CipherError),
+    Decode(DecodeError),
+    Registry(RegistryError),
+    Pcap(PcapError),
+    #[cfg(feature="drcom")]
+    DrCOMValidate(DrCOMValidateError),
+    #[cfg(feature="drcom")]
+    DrCOMLogin(LoginError),
+    #[cfg(feature="drcom")]
+    DrCOMWiredHeartbeat(HeartbeatError),
+    #[cfg(feature="drcom")]
+    DrCOMWiredSession(SessionError),
+    #[cfg(feature="drcom")]
+    DrCOMPPPoEHeartbeat(DrCOMHeartbeatError),
+    #[cfg(feature="drcom")]
+    DrCOMCRCHash(CRCHashError),
+    #[cfg(feature="netkeeper")]
+    NetkeeperHeartbeat(NetkeeperHeartbeatError),
+    #[cfg(feature="singlenet")]
+    SinglenetHeartbeat(SinglenetHeartbeatError),
+    #[cfg(feature="singlenet")]
+    SinglenetAttributes(ParseAttributesError),
+    #[cfg(feature="ghca")]
+    GhcaDialer(GhcaDialerError),
+    #[cfg(feature="ipclient")]
+    MACOpen(MACOpenErr),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IO(_) => write!(f, "io error"),
+            Error::Timeout(attempts) => write!(f, "no response after {} attempts", attempts),
+            Error::ReadBytes(_) => write!(f, "read packet failed"),
+            Error::Cipher(_) => write!(f, "cipher failed"),
+            Error::Decode(_) => write!(f, "decode account failed"),
+            Error::Registry(_) => write!(f, "load configurations failed"),
+            Error::Pcap(_) => write!(f, "read capture failed"),
+            #[cfg(feature="drcom")]
+            Error::DrCOMValidate(_) => write!(f, "drcom packet validate failed"),
+            #[cfg(feature="drcom")]
+            Error::DrCOMLogin(_) => write!(f, "drcom login failed"),
+            #[cfg(feature="drcom")]
+            Error::DrCOMWiredHeartbeat(_) => write!(f, "drcom wired heartbeat failed"),
+            #[cfg(feature="drcom")]
+            Error::DrCOMWiredSession(_) => write!(f, "drcom wired session failed"),
+            #[cfg(feature="drcom")]
+            Error::DrCOMPPPoEHeartbeat(_) => write!(f, "drcom pppoe heartbeat failed"),
+            #[cfg(feature="drcom")]
+            Error::DrCOMCRCHash(_) => write!(f, "drcom crc hash failed"),
+            #[cfg(feature="netkeeper")]
+            Error::NetkeeperHeartbeat(_) => write!(f, "netkeeper heartbeat failed"),
+            #[cfg(feature="singlenet")]
+            Error::SinglenetHeartbeat(_) => write!(f, "singlenet heartbeat failed"),
+            #[cfg(feature="singlenet")]
+            Error::SinglenetAttributes(_) => write!(f, "singlenet attributes parse failed"),
+            #[cfg(feature="ghca")]
+            Error::GhcaDialer(_) => write!(f, "ghca dialer failed"),
+            #[cfg(feature="ipclient")]
+            Error::MACOpen(_) => write!(f, "ipclient macopen failed"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            Error::IO(ref e) => Some(e),
+            Error::Timeout(_) => None,
+            Error::ReadBytes(ref e) => Some(e),
+            Error::Cipher(ref e) => Some(e),
+            Error::Decode(ref e) => Some(e),
+            Error::Registry(ref e) => Some(e),
+            Error::Pcap(ref e) => Some(e),
+            #[cfg(feature="drcom")]
+            Error::DrCOMValidate(ref e) => Some(e),
+            #[cfg(feature="drcom")]
+            Error::DrCOMLogin(ref e) => Some(e),
+            #[cfg(feature="drcom")]
+            Error::DrCOMWiredHeartbeat(ref e) => Some(e),
+            #[cfg(feature="drcom")]
+            Error::DrCOMWiredSession(ref e) => Some(e),
+            #[cfg(feature="drcom")]
+            Error::DrCOMPPPoEHeartbeat(ref e) => Some(e),
+            #[cfg(feature="drcom")]
+            Error::DrCOMCRCHash(ref e) => Some(e),
+            #[cfg(feature="netkeeper")]
+            Error::NetkeeperHeartbeat(ref e) => Some(e),
+            #[cfg(feature="singlenet")]
+            Error::SinglenetHeartbeat(ref e) => Some(e),
+            #[cfg(feature="singlenet")]
+            Error::SinglenetAttributes(ref e) => Some(e),
+            #[cfg(feature="ghca")]
+            Error::GhcaDialer(ref e) => Some(e),
+            #[cfg(feature="ipclient")]
+            Error::MACOpen(ref e) => Some(e),
+        }
+    }
+}
+
+/// Join the message of `error` with those of its sources, the `Display` of
+/// each error only tells its own context.
+pub fn describe<E>(error: &E) -> String
+    where E: error::Error + ?Sized
+{
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+    message
+}
+
+macro_rules! impl_from_error {
+    ($( $(#[$attr:meta])* $ty:ty => $variant:ident ),*) => {
+        $(
+            $(#[$attr])*
+            impl From<$ty> for Error {
+                fn from(e: $ty) -> Self {
+                    Error::$variant(e)
+                }
+            }
+        )*
+    }
+}
+
+impl_from_error!(io::Error => IO,
+                 ReadBytesError => ReadBytes,
+                 CipherError => Cipher,
+                 DecodeError => Decode,
+                 RegistryError => Registry,
+                 PcapError => Pcap,
+                 #[cfg(feature="drcom")]
+                 DrCOMValidateError => DrCOMValidate,
+                 #[cfg(feature="drcom")]
+                 LoginError => DrCOMLogin,
+                 #[cfg(feature="drcom")]
+                 HeartbeatError => DrCOMWiredHeartbeat,
+                 #[cfg(feature="drcom")]
+                 SessionError => DrCOMWiredSession,
+                 #[cfg(feature="drcom")]
+                 DrCOMHeartbeatError => DrCOMPPPoEHeartbeat,
+                 #[cfg(feature="drcom")]
+                 CRCHashError => DrCOMCRCHash,
+                 #[cfg(feature="netkeeper")]
+                 NetkeeperHeartbeatError => NetkeeperHeartbeat,
+                 #[cfg(feature="singlenet")]
+                 SinglenetHeartbeatError => SinglenetHeartbeat,
+                 #[cfg(feature="singlenet")]
+                 ParseAttributesError => SinglenetAttributes,
+                 #[cfg(feature="ghca")]
+                 GhcaDialerError => GhcaDialer,
+                 #[cfg(feature="ipclient")]
+                 MACOpenErr => MACOpen);
+
+#[test]
+fn test_error_message_and_source() {
+    use std::error::Error as StdError;
+
+    let e = Error::from(ReadBytesError::LengthMismatch(4, 2));
+    assert_eq!(e.to_string(), "read packet failed");
+    assert_eq!(e.source().unwrap().to_string(), "expect length 4, got 2");
+}
+
+#[cfg(feature="drcom")]
+#[test]
+fn test_error_source_chaining() {
+    use std::error::Error as StdError;
+
+    let e = Error::from(LoginError::ValidateError(DrCOMValidateError::CodeMismatch(5)));
+    assert_eq!(e.to_string(), "drcom login failed");
+    let login_error = e.source().unwrap();
+    assert_eq!(login_error.to_string(), "packet validate failed");
+    let validate_error = login_error.source().unwrap();
+    assert_eq!(validate_error.to_string(), "unexpected packet code 0x05");
+    assert_eq!(describe(&e),
+               "drcom login failed: packet validate failed: unexpected packet code 0x05");
+}
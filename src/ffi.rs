@@ -0,0 +1,791 @@
+//! C ABI of the dialers and DrCOM packets, `include/netkeeper.h` is generated from it.
+//!
+//! Strings and packets are written to caller provided buffers, functions
+//! return the number of bytes written or a negative `NK_ERROR_*` code.
+
+use std::{ptr, result, slice};
+use std::ffi::CStr;
+#[cfg(feature="drcom")]
+use std::io;
+#[cfg(feature="drcom")]
+use std::net::Ipv4Addr;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+use common::dialer::ConfigurableDialer;
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+use common::registry::ConfigurationRegistry;
+#[cfg(feature="netkeeper")]
+use netkeeper::dialer::NetkeeperDialer;
+#[cfg(feature="singlenet")]
+use singlenet::dialer::SingleNetDialer;
+#[cfg(feature="ghca")]
+use ghca::dialer::GhcaDialer;
+#[cfg(feature="srun3k")]
+use srun3k::dialer::{Srun3kDialer, Configuration as Srun3kConfiguration};
+#[cfg(feature="srun3k")]
+use common::dialer::Dialer;
+#[cfg(feature="drcom")]
+use drcom::wired::dialer::{ChallengeRequest, ChallengeResponse, LoginAccount, LoginError,
+                           LoginResponse};
+#[cfg(feature="drcom")]
+use drcom::wired::heartbeater::{PhaseOneRequest, PhaseOneResponse, PhaseTwoRequest,
+                                PhaseTwoResponse, HeartbeatFlag};
+#[cfg(feature="drcom")]
+use drcom::pppoe::heartbeater::{ChallengeRequest as PPPoEChallengeRequest,
+                                ChallengeResponse as PPPoEChallengeResponse, HeartbeatRequest,
+                                KeepAliveRequest, KeepAliveResponse, KeepAliveResponseType,
+                                HeartbeatFlag as PPPoEHeartbeatFlag, KeepAliveRequestFlag};
+
+pub const NK_OK: c_int = 0;
+pub const NK_ERROR_NULL_POINTER: c_int = -1;
+pub const NK_ERROR_INVALID_UTF8: c_int = -2;
+pub const NK_ERROR_UNKNOWN_CONFIGURATION: c_int = -3;
+pub const NK_ERROR_BUFFER_TOO_SMALL: c_int = -4;
+pub const NK_ERROR_INVALID_ARGUMENT: c_int = -5;
+pub const NK_ERROR_INVALID_PACKET: c_int = -6;
+pub const NK_ERROR_LOGIN_FAILED: c_int = -7;
+pub const NK_ERROR_PANIC: c_int = -8;
+
+pub const NK_DRCOM_KEEP_ALIVE_SUCCEED: c_int = 0;
+pub const NK_DRCOM_KEEP_ALIVE_FILE_RESPONSE: c_int = 1;
+pub const NK_DRCOM_KEEP_ALIVE_UNRECOGNIZED: c_int = 2;
+
+type FFIResult<T> = result::Result<T, c_int>;
+
+/// Message of an `NK_ERROR_*` code, the returned string is static.
+#[no_mangle]
+pub extern "C" fn nk_strerror(code: c_int) -> *const c_char {
+    let message: &'static [u8] = match code {
+        NK_OK => b"success\0",
+        NK_ERROR_NULL_POINTER => b"unexpected null pointer\0",
+        NK_ERROR_INVALID_UTF8 => b"string is not valid utf-8\0",
+        NK_ERROR_UNKNOWN_CONFIGURATION => b"unknown configuration\0",
+        NK_ERROR_BUFFER_TOO_SMALL => b"output buffer too small\0",
+        NK_ERROR_INVALID_ARGUMENT => b"invalid argument\0",
+        NK_ERROR_INVALID_PACKET => b"invalid packet\0",
+        NK_ERROR_LOGIN_FAILED => b"login failed\0",
+        NK_ERROR_PANIC => b"internal error\0",
+        _ => b"unknown error\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+/// `configuration` is the name of a built-in configuration such as "zhejiang".
+#[cfg(feature="netkeeper")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_netkeeper_encrypt_account(configuration: *const c_char,
+                                                      username: *const c_char,
+                                                      timestamp: u32,
+                                                      output: *mut c_char,
+                                                      output_length: usize)
+                                                      -> isize {
+    guard_length(|| {
+        let dialer: NetkeeperDialer = try!(load_dialer(configuration));
+        let username = try!(read_str(username));
+        write_string(&dialer.encrypt_account(username, optional_timestamp(timestamp)),
+                     output,
+                     output_length)
+    })
+}
+
+/// `configuration` is the name of a built-in configuration such as "hainan".
+#[cfg(feature="singlenet")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_singlenet_encrypt_account(configuration: *const c_char,
+                                                      username: *const c_char,
+                                                      timestamp: u32,
+                                                      output: *mut c_char,
+                                                      output_length: usize)
+                                                      -> isize {
+    guard_length(|| {
+        let dialer: SingleNetDialer = try!(load_dialer(configuration));
+        let username = try!(read_str(username));
+        write_string(&dialer.encrypt_account(username, optional_timestamp(timestamp)),
+                     output,
+                     output_length)
+    })
+}
+
+/// `configuration` is the name of a built-in configuration such as "sichuan_mac".
+#[cfg(feature="ghca")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_ghca_encrypt_account(configuration: *const c_char,
+                                                 username: *const c_char,
+                                                 password: *const c_char,
+                                                 timestamp: u32,
+                                                 output: *mut c_char,
+                                                 output_length: usize)
+                                                 -> isize {
+    guard_length(|| {
+        let dialer: GhcaDialer = try!(load_dialer(configuration));
+        let username = try!(read_str(username));
+        let password = try!(read_str(password));
+        let timestamp = optional_timestamp(timestamp);
+        let encrypted = try!(dialer.encrypt_account(username, password, timestamp, timestamp)
+            .map_err(|_| NK_ERROR_INVALID_ARGUMENT));
+        write_string(&encrypted, output, output_length)
+    })
+}
+
+/// SRun3k v20 usernames, which carry no timestamp.
+#[cfg(feature="srun3k")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_srun3k_encrypt_account(username: *const c_char,
+                                                   output: *mut c_char,
+                                                   output_length: usize)
+                                                   -> isize {
+    guard_length(|| {
+        let username = try!(read_str(username));
+        let dialer = Srun3kDialer::load_from_config(Srun3kConfiguration::TaLiMu);
+        write_string(&dialer.encrypt_account_v20(username), output, output_length)
+    })
+}
+
+/// DrCOM wired, a sequence of 0 picks a random one.
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_build_challenge_request(sequence: u16,
+                                                                output: *mut u8,
+                                                                output_length: usize)
+                                                                -> isize {
+    guard_length(|| {
+        let sequence = if sequence == 0 {
+            None
+        } else {
+            Some(sequence)
+        };
+        write_bytes(&ChallengeRequest::new(sequence).as_bytes(), output, output_length)
+    })
+}
+
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_parse_challenge_response(packet: *const u8,
+                                                                 packet_length: usize,
+                                                                 hash_salt: *mut u8)
+                                                                 -> c_int {
+    guard_status(|| {
+        let packet = try!(read_bytes(packet, packet_length));
+        let response = try!(ChallengeResponse::from_bytes(&mut io::BufReader::new(packet))
+            .map_err(|_| NK_ERROR_INVALID_PACKET));
+        write_fixed(&response.hash_salt, hash_salt)
+    })
+}
+
+/// `hostname` may be NULL to keep the default one.
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_build_login_request(username: *const c_char,
+                                                            password: *const c_char,
+                                                            hash_salt: *const u8,
+                                                            mac_address: *const u8,
+                                                            ipaddress: *const u8,
+                                                            hostname: *const c_char,
+                                                            output: *mut u8,
+                                                            output_length: usize)
+                                                            -> isize {
+    guard_length(|| {
+        let mut account = try!(login_account(username, password, hash_salt, mac_address));
+        let ipaddress: [u8; 4] = try!(read_fixed(ipaddress));
+        account.ipaddresses(&[Ipv4Addr::from(ipaddress)]);
+        if !hostname.is_null() {
+            account.hostname(try!(read_str(hostname)).to_string());
+        }
+
+        let request = try!(account.login_request().map_err(login_error_code));
+        let bytes = try!(request.as_bytes().map_err(login_error_code));
+        write_bytes(&bytes, output, output_length)
+    })
+}
+
+/// Returns `NK_ERROR_LOGIN_FAILED` when the server rejects the account.
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_parse_login_response(packet: *const u8,
+                                                             packet_length: usize,
+                                                             keep_alive_key: *mut u8)
+                                                             -> c_int {
+    guard_status(|| {
+        let packet = try!(read_bytes(packet, packet_length));
+        let response = try!(LoginResponse::from_bytes(&mut io::BufReader::new(packet))
+            .map_err(login_error_code));
+        write_fixed(&response.keep_alive_key, keep_alive_key)
+    })
+}
+
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_build_logout_request(username: *const c_char,
+                                                             password: *const c_char,
+                                                             hash_salt: *const u8,
+                                                             mac_address: *const u8,
+                                                             output: *mut u8,
+                                                             output_length: usize)
+                                                             -> isize {
+    guard_length(|| {
+        let account = try!(login_account(username, password, hash_salt, mac_address));
+        let request = try!(account.logout_request().map_err(login_error_code));
+        let bytes = try!(request.as_bytes().map_err(login_error_code));
+        write_bytes(&bytes, output, output_length)
+    })
+}
+
+/// `keep_alive_key` is the first 4 bytes of the login response key.
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_build_phase_one_request(hash_salt: *const u8,
+                                                                password: *const c_char,
+                                                                keep_alive_key: *const u8,
+                                                                timestamp: u32,
+                                                                output: *mut u8,
+                                                                output_length: usize)
+                                                                -> isize {
+    guard_length(|| {
+        let request = PhaseOneRequest::new(try!(read_fixed(hash_salt)),
+                                           try!(read_str(password)),
+                                           try!(read_fixed(keep_alive_key)),
+                                           optional_timestamp(timestamp));
+        write_bytes(&request.as_bytes(), output, output_length)
+    })
+}
+
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_parse_phase_one_response(packet: *const u8,
+                                                                 packet_length: usize)
+                                                                 -> c_int {
+    guard_status(|| {
+        let packet = try!(read_bytes(packet, packet_length));
+        try!(PhaseOneResponse::from_bytes(&mut io::BufReader::new(packet))
+            .map_err(|_| NK_ERROR_INVALID_PACKET));
+        Ok(())
+    })
+}
+
+/// `host_ip` is only carried by type 3 requests.
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_build_phase_two_request(sequence: u8,
+                                                                keep_alive_key: *const u8,
+                                                                first: c_int,
+                                                                type_id: u8,
+                                                                host_ip: *const u8,
+                                                                output: *mut u8,
+                                                                output_length: usize)
+                                                                -> isize {
+    guard_length(|| {
+        let flag = if first != 0 {
+            HeartbeatFlag::First
+        } else {
+            HeartbeatFlag::NotFirst
+        };
+        let host_ip: [u8; 4] = try!(read_fixed(host_ip));
+        let request = PhaseTwoRequest::new(sequence,
+                                           try!(read_fixed(keep_alive_key)),
+                                           &flag,
+                                           Ipv4Addr::from(host_ip),
+                                           Some(type_id));
+        write_bytes(&request.as_bytes(), output, output_length)
+    })
+}
+
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_wired_parse_phase_two_response(packet: *const u8,
+                                                                 packet_length: usize,
+                                                                 sequence: *mut u8,
+                                                                 keep_alive_key: *mut u8)
+                                                                 -> c_int {
+    guard_status(|| {
+        let packet = try!(read_bytes(packet, packet_length));
+        let response = try!(PhaseTwoResponse::from_bytes(&mut io::BufReader::new(packet))
+            .map_err(|_| NK_ERROR_INVALID_PACKET));
+        try!(write_fixed(&[response.sequence], sequence));
+        write_fixed(&response.keep_alive_key, keep_alive_key)
+    })
+}
+
+/// DrCOM PPPoE challenge, the sequence is sent as is.
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_pppoe_build_challenge_request(sequence: u8,
+                                                                output: *mut u8,
+                                                                output_length: usize)
+                                                                -> isize {
+    guard_length(|| {
+        write_bytes(&PPPoEChallengeRequest::new(Some(sequence)).as_bytes(),
+                    output,
+                    output_length)
+    })
+}
+
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_pppoe_parse_challenge_response(packet: *const u8,
+                                                                 packet_length: usize,
+                                                                 challenge_seed: *mut u32,
+                                                                 source_ip: *mut u8)
+                                                                 -> c_int {
+    guard_status(|| {
+        let packet = try!(read_bytes(packet, packet_length));
+        let response = try!(PPPoEChallengeResponse::from_bytes(&mut io::BufReader::new(packet))
+            .map_err(|_| NK_ERROR_INVALID_PACKET));
+        if challenge_seed.is_null() {
+            return Err(NK_ERROR_NULL_POINTER);
+        }
+        *challenge_seed = response.challenge_seed;
+        write_fixed(&response.source_ip.octets(), source_ip)
+    })
+}
+
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_pppoe_build_heartbeat_request(sequence: u8,
+                                                                source_ip: *const u8,
+                                                                first: c_int,
+                                                                challenge_seed: u32,
+                                                                output: *mut u8,
+                                                                output_length: usize)
+                                                                -> isize {
+    guard_length(|| {
+        let flag = if first != 0 {
+            PPPoEHeartbeatFlag::First
+        } else {
+            PPPoEHeartbeatFlag::NotFirst
+        };
+        let source_ip: [u8; 4] = try!(read_fixed(source_ip));
+        let request = HeartbeatRequest::new(sequence,
+                                            Ipv4Addr::from(source_ip),
+                                            &flag,
+                                            challenge_seed,
+                                            None,
+                                            None,
+                                            None);
+        write_bytes(&request.as_bytes(), output, output_length)
+    })
+}
+
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_pppoe_build_keep_alive_request(sequence: u8,
+                                                                 first: c_int,
+                                                                 type_id: u8,
+                                                                 source_ip: *const u8,
+                                                                 keep_alive_seed: u32,
+                                                                 output: *mut u8,
+                                                                 output_length: usize)
+                                                                 -> isize {
+    guard_length(|| {
+        let flag = if first != 0 {
+            KeepAliveRequestFlag::First
+        } else {
+            KeepAliveRequestFlag::NotFirst
+        };
+        let source_ip: [u8; 4] = try!(read_fixed(source_ip));
+        let request = KeepAliveRequest::new(sequence,
+                                            &flag,
+                                            Some(type_id),
+                                            Some(Ipv4Addr::from(source_ip)),
+                                            Some(keep_alive_seed));
+        write_bytes(&request.as_bytes(), output, output_length)
+    })
+}
+
+/// `response_type` is set to one of `NK_DRCOM_KEEP_ALIVE_*`.
+#[cfg(feature="drcom")]
+#[no_mangle]
+pub unsafe extern "C" fn nk_drcom_pppoe_parse_keep_alive_response(packet: *const u8,
+                                                                  packet_length: usize,
+                                                                  response_type: *mut c_int)
+                                                                  -> c_int {
+    guard_status(|| {
+        let packet = try!(read_bytes(packet, packet_length));
+        let response = try!(KeepAliveResponse::from_bytes(&mut io::BufReader::new(packet))
+            .map_err(|_| NK_ERROR_INVALID_PACKET));
+        if response_type.is_null() {
+            return Err(NK_ERROR_NULL_POINTER);
+        }
+        *response_type = match response.response_type {
+            KeepAliveResponseType::KeepAliveSucceed => NK_DRCOM_KEEP_ALIVE_SUCCEED,
+            KeepAliveResponseType::FileResponse => NK_DRCOM_KEEP_ALIVE_FILE_RESPONSE,
+            KeepAliveResponseType::UnrecognizedResponse => NK_DRCOM_KEEP_ALIVE_UNRECOGNIZED,
+        };
+        Ok(())
+    })
+}
+
+/// Panics must not unwind into C, they are reported as `NK_ERROR_PANIC`.
+fn guard_length<F>(function: F) -> isize
+    where F: FnOnce() -> FFIResult<usize>
+{
+    match panic::catch_unwind(AssertUnwindSafe(function)) {
+        Ok(Ok(length)) => length as isize,
+        Ok(Err(code)) => code as isize,
+        Err(_) => NK_ERROR_PANIC as isize,
+    }
+}
+
+fn guard_status<F>(function: F) -> c_int
+    where F: FnOnce() -> FFIResult<()>
+{
+    match panic::catch_unwind(AssertUnwindSafe(function)) {
+        Ok(Ok(())) => NK_OK,
+        Ok(Err(code)) => code,
+        Err(_) => NK_ERROR_PANIC,
+    }
+}
+
+fn optional_timestamp(timestamp: u32) -> Option<u32> {
+    if timestamp == 0 {
+        None
+    } else {
+        Some(timestamp)
+    }
+}
+
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+unsafe fn load_dialer<D>(configuration: *const c_char) -> FFIResult<D>
+    where D: ConfigurableDialer
+{
+    let name = try!(read_str(configuration));
+    ConfigurationRegistry::new().load_dialer(name).ok_or(NK_ERROR_UNKNOWN_CONFIGURATION)
+}
+
+#[cfg(feature="drcom")]
+unsafe fn login_account(username: *const c_char,
+                        password: *const c_char,
+                        hash_salt: *const u8,
+                        mac_address: *const u8)
+                        -> FFIResult<LoginAccount> {
+    let mut account = LoginAccount::new(try!(read_str(username)),
+                                        try!(read_str(password)),
+                                        try!(read_fixed(hash_salt)));
+    account.mac_address(try!(read_fixed(mac_address)));
+    Ok(account)
+}
+
+#[cfg(feature="drcom")]
+fn login_error_code(error: LoginError) -> c_int {
+    match error {
+        LoginError::FieldValueOverflow(_, _) => NK_ERROR_INVALID_ARGUMENT,
+        LoginError::LoginFailed(_) => NK_ERROR_LOGIN_FAILED,
+        _ => NK_ERROR_INVALID_PACKET,
+    }
+}
+
+unsafe fn read_str<'a>(value: *const c_char) -> FFIResult<&'a str> {
+    if value.is_null() {
+        return Err(NK_ERROR_NULL_POINTER);
+    }
+    CStr::from_ptr(value).to_str().map_err(|_| NK_ERROR_INVALID_UTF8)
+}
+
+unsafe fn read_bytes<'a>(bytes: *const u8, length: usize) -> FFIResult<&'a [u8]> {
+    if bytes.is_null() {
+        return Err(NK_ERROR_NULL_POINTER);
+    }
+    Ok(slice::from_raw_parts(bytes, length))
+}
+
+/// Copy a fixed size array such as `uint8_t mac_address[6]`.
+unsafe fn read_fixed<T>(bytes: *const u8) -> FFIResult<T>
+    where T: Default + AsMut<[u8]>
+{
+    if bytes.is_null() {
+        return Err(NK_ERROR_NULL_POINTER);
+    }
+    let mut result = T::default();
+    {
+        let target = result.as_mut();
+        ptr::copy_nonoverlapping(bytes, target.as_mut_ptr(), target.len());
+    }
+    Ok(result)
+}
+
+unsafe fn write_fixed(bytes: &[u8], output: *mut u8) -> FFIResult<()> {
+    if output.is_null() {
+        return Err(NK_ERROR_NULL_POINTER);
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), output, bytes.len());
+    Ok(())
+}
+
+unsafe fn write_bytes(bytes: &[u8], output: *mut u8, output_length: usize) -> FFIResult<usize> {
+    if output.is_null() {
+        return Err(NK_ERROR_NULL_POINTER);
+    }
+    if bytes.len() > output_length {
+        return Err(NK_ERROR_BUFFER_TOO_SMALL);
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), output, bytes.len());
+    Ok(bytes.len())
+}
+
+/// Strings are NUL terminated, the terminator is not counted in the result.
+unsafe fn write_string(value: &str,
+                       output: *mut c_char,
+                       output_length: usize)
+                       -> FFIResult<usize> {
+    if output.is_null() {
+        return Err(NK_ERROR_NULL_POINTER);
+    }
+    if value.len() >= output_length {
+        return Err(NK_ERROR_BUFFER_TOO_SMALL);
+    }
+    ptr::copy_nonoverlapping(value.as_ptr(), output as *mut u8, value.len());
+    *output.offset(value.len() as isize) = 0;
+    Ok(value.len())
+}
+
+#[cfg(test)]
+fn c_string(value: &str) -> ::std::ffi::CString {
+    ::std::ffi::CString::new(value).unwrap()
+}
+
+#[cfg(test)]
+fn read_output(output: &[c_char], length: isize) -> String {
+    assert!(length >= 0, "unexpected error {}", length);
+    let bytes: Vec<u8> = output[..length as usize].iter().map(|c| *c as u8).collect();
+    String::from_utf8(bytes).unwrap()
+}
+
+#[cfg(all(feature="netkeeper", feature="ghca", feature="srun3k"))]
+#[test]
+fn test_ffi_encrypt_account() {
+    let username = c_string("05802278989@HYXY.XY");
+    let mut output = [0 as c_char; 64];
+    unsafe {
+        let length = nk_netkeeper_encrypt_account(c_string("zhejiang").as_ptr(),
+                                                  username.as_ptr(),
+                                                  1472483020,
+                                                  output.as_mut_ptr(),
+                                                  output.len());
+        assert_eq!(read_output(&output, length), "\r\n:R#(P 5005802278989@HYXY.XY");
+        assert_eq!(output[length as usize], 0);
+
+        assert_eq!(nk_netkeeper_encrypt_account(c_string("zhejiang").as_ptr(),
+                                                username.as_ptr(),
+                                                1472483020,
+                                                output.as_mut_ptr(),
+                                                length as usize),
+                   NK_ERROR_BUFFER_TOO_SMALL as isize);
+        assert_eq!(nk_netkeeper_encrypt_account(c_string("unknown").as_ptr(),
+                                                username.as_ptr(),
+                                                0,
+                                                output.as_mut_ptr(),
+                                                output.len()),
+                   NK_ERROR_UNKNOWN_CONFIGURATION as isize);
+        assert_eq!(nk_netkeeper_encrypt_account(ptr::null(),
+                                                username.as_ptr(),
+                                                0,
+                                                output.as_mut_ptr(),
+                                                output.len()),
+                   NK_ERROR_NULL_POINTER as isize);
+
+        let password = c_string(&"123456".repeat(12));
+        assert_eq!(nk_ghca_encrypt_account(c_string("sichuan_mac").as_ptr(),
+                                           username.as_ptr(),
+                                           password.as_ptr(),
+                                           0,
+                                           output.as_mut_ptr(),
+                                           output.len()),
+                   NK_ERROR_INVALID_ARGUMENT as isize);
+
+        let length = nk_srun3k_encrypt_account(username.as_ptr(),
+                                               output.as_mut_ptr(),
+                                               output.len());
+        let dialer = Srun3kDialer::load_from_config(Srun3kConfiguration::TaLiMu);
+        assert_eq!(read_output(&output, length),
+                   dialer.encrypt_account_v20("05802278989@HYXY.XY"));
+    }
+}
+
+#[cfg(feature="drcom")]
+#[test]
+fn test_ffi_drcom_wired() {
+    use drcom::DrCOMFlag;
+    use drcom::wired::dialer::{LoginFailure, LoginRequest};
+
+    let username = c_string("usernameusername");
+    let password = c_string("password");
+    let hash_salt = [0x1f, 0x2e, 0x3d, 0x4c];
+    let mac_address = [0xb8, 0x88, 0xe3, 0x05, 0x16, 0x80];
+    let mut output = [0u8; 512];
+    unsafe {
+        let length = nk_drcom_wired_build_challenge_request(0x0102,
+                                                            output.as_mut_ptr(),
+                                                            output.len());
+        assert_eq!(&output[..length as usize],
+                   &ChallengeRequest::new(Some(0x0102)).as_bytes()[..]);
+
+        let mut salt = [0u8; 4];
+        let response = ChallengeResponse { hash_salt: hash_salt }.as_bytes();
+        assert_eq!(nk_drcom_wired_parse_challenge_response(response.as_ptr(),
+                                                           response.len(),
+                                                           salt.as_mut_ptr()),
+                   NK_OK);
+        assert_eq!(salt, hash_salt);
+
+        let length = nk_drcom_wired_build_login_request(username.as_ptr(),
+                                                        password.as_ptr(),
+                                                        hash_salt.as_ptr(),
+                                                        mac_address.as_ptr(),
+                                                        [10, 30, 22, 17].as_ptr(),
+                                                        c_string("drcom").as_ptr(),
+                                                        output.as_mut_ptr(),
+                                                        output.len());
+        assert!(length > 0);
+        let request = LoginRequest::from_bytes(&mut io::BufReader::new(&output[..length as usize]))
+            .unwrap();
+        assert_eq!(request.username(), "usernameusername");
+        assert_eq!(request.mac_address(), mac_address);
+        assert_eq!(request.hostname(), "drcom");
+        assert_eq!(request.ipaddresses()[0], Ipv4Addr::new(10, 30, 22, 17));
+
+        let mut key = [0u8; 6];
+        let response = LoginResponse { keep_alive_key: [1, 2, 3, 4, 5, 6] }.as_bytes();
+        assert_eq!(nk_drcom_wired_parse_login_response(response.as_ptr(),
+                                                       response.len(),
+                                                       key.as_mut_ptr()),
+                   NK_OK);
+        assert_eq!(key, [1, 2, 3, 4, 5, 6]);
+        let failure = LoginFailure::WrongPassword.as_bytes();
+        assert_eq!(nk_drcom_wired_parse_login_response(failure.as_ptr(),
+                                                       failure.len(),
+                                                       key.as_mut_ptr()),
+                   NK_ERROR_LOGIN_FAILED);
+
+        let length = nk_drcom_wired_build_phase_one_request(hash_salt.as_ptr(),
+                                                            password.as_ptr(),
+                                                            key.as_ptr(),
+                                                            1476316800,
+                                                            output.as_mut_ptr(),
+                                                            output.len());
+        let request = PhaseOneRequest::from_bytes(&mut io::BufReader::new(&output[..length as
+                                                                                   usize]))
+            .unwrap();
+        assert!(request.verify_password(hash_salt, "password"));
+        assert_eq!(request.keep_alive_key(), [1, 2, 3, 4]);
+        let response = PhaseOneResponse.as_bytes();
+        assert_eq!(nk_drcom_wired_parse_phase_one_response(response.as_ptr(), response.len()),
+                   NK_OK);
+
+        let length = nk_drcom_wired_build_phase_two_request(7,
+                                                            [9, 8, 7, 6].as_ptr(),
+                                                            1,
+                                                            3,
+                                                            [10, 30, 22, 17].as_ptr(),
+                                                            output.as_mut_ptr(),
+                                                            output.len());
+        let request = PhaseTwoRequest::from_bytes(&mut io::BufReader::new(&output[..length as
+                                                                                   usize]))
+            .unwrap();
+        assert_eq!(request.sequence(), 7);
+        assert_eq!(request.type_id(), 3);
+        assert_eq!(request.flag().as_u32(), HeartbeatFlag::First.as_u32());
+        assert_eq!(request.host_ip(), Ipv4Addr::new(10, 30, 22, 17));
+
+        let mut sequence = 0u8;
+        let mut phase_two_key = [0u8; 4];
+        let response = PhaseTwoResponse::new(7, [4, 3, 2, 1], Some(3)).as_bytes();
+        assert_eq!(nk_drcom_wired_parse_phase_two_response(response.as_ptr(),
+                                                           response.len(),
+                                                           &mut sequence,
+                                                           phase_two_key.as_mut_ptr()),
+                   NK_OK);
+        assert_eq!((sequence, phase_two_key), (7, [4, 3, 2, 1]));
+        assert_eq!(nk_drcom_wired_parse_phase_two_response(response.as_ptr(),
+                                                           4,
+                                                           &mut sequence,
+                                                           phase_two_key.as_mut_ptr()),
+                   NK_ERROR_INVALID_PACKET);
+    }
+}
+
+#[cfg(feature="drcom")]
+#[test]
+fn test_ffi_drcom_pppoe() {
+    let source_ip = [172, 16, 5, 9];
+    let mut output = [0u8; 128];
+    unsafe {
+        let length = nk_drcom_pppoe_build_challenge_request(1,
+                                                            output.as_mut_ptr(),
+                                                            output.len());
+        assert_eq!(&output[..length as usize],
+                   &PPPoEChallengeRequest::new(Some(1)).as_bytes()[..]);
+
+        let mut seed = 0u32;
+        let mut ip = [0u8; 4];
+        let response = PPPoEChallengeResponse {
+                challenge_seed: 0x5a1c9e03,
+                source_ip: Ipv4Addr::from(source_ip),
+            }
+            .as_bytes();
+        assert_eq!(nk_drcom_pppoe_parse_challenge_response(response.as_ptr(),
+                                                           response.len(),
+                                                           &mut seed,
+                                                           ip.as_mut_ptr()),
+                   NK_OK);
+        assert_eq!((seed, ip), (0x5a1c9e03, source_ip));
+
+        let length = nk_drcom_pppoe_build_heartbeat_request(2,
+                                                            source_ip.as_ptr(),
+                                                            1,
+                                                            seed,
+                                                            output.as_mut_ptr(),
+                                                            output.len());
+        let request = HeartbeatRequest::from_bytes(&mut io::BufReader::new(&output[..length as
+                                                                                    usize]))
+            .unwrap();
+        assert_eq!(request.challenge_seed(), 0x5a1c9e03);
+        assert_eq!(request.source_ip(), Ipv4Addr::from(source_ip));
+
+        let length = nk_drcom_pppoe_build_keep_alive_request(3,
+                                                             0,
+                                                             3,
+                                                             source_ip.as_ptr(),
+                                                             0x11223344,
+                                                             output.as_mut_ptr(),
+                                                             output.len());
+        let request = KeepAliveRequest::from_bytes(&mut io::BufReader::new(&output[..length as
+                                                                                    usize]))
+            .unwrap();
+        assert_eq!((request.sequence(), request.type_id()), (3, 3));
+        assert_eq!(request.keep_alive_seed(), 0x11223344);
+        assert_eq!(nk_drcom_pppoe_build_keep_alive_request(3,
+                                                           0,
+                                                           3,
+                                                           source_ip.as_ptr(),
+                                                           0x11223344,
+                                                           output.as_mut_ptr(),
+                                                           8),
+                   NK_ERROR_BUFFER_TOO_SMALL as isize);
+
+        let mut response_type = -1;
+        let response = KeepAliveResponse {
+                response_type: KeepAliveResponseType::FileResponse,
+            }
+            .as_bytes();
+        assert_eq!(nk_drcom_pppoe_parse_keep_alive_response(response.as_ptr(),
+                                                            response.len(),
+                                                            &mut response_type),
+                   NK_OK);
+        assert_eq!(response_type, NK_DRCOM_KEEP_ALIVE_FILE_RESPONSE);
+    }
+}
+
+#[test]
+fn test_ffi_header() {
+    // the checked-in header must be the one cbindgen generates from this file
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/netkeeper.h"));
+    let header = include_str!("../include/netkeeper.h");
+    for (line, (expected, declared)) in generated.lines().zip(header.lines()).enumerate() {
+        assert_eq!(declared, expected, "include/netkeeper.h differs at line {}", line + 1);
+    }
+    assert_eq!(header, generated, "include/netkeeper.h is outdated");
+
+    let message = unsafe { CStr::from_ptr(nk_strerror(NK_ERROR_BUFFER_TOO_SMALL)) };
+    assert_eq!(message.to_str().unwrap(), "output buffer too small");
+}
@@ -1,8 +1,11 @@
+use std::{error, fmt};
+
 use rustc_serialize::hex::ToHex;
 
 use crypto::hash::{HasherBuilder, HasherType};
-use common::utils::current_timestamp;
-use common::dialer::Dialer;
+use common::clock::{Clock, SystemClock};
+use common::dialer::{Dialer, ConfigurableDialer, DialerParameters, DecodedAccount, DecodeError,
+                     DecodeResult};
 use common::bytes::BytesAbleNum;
 
 #[derive(Debug)]
@@ -11,7 +14,20 @@ pub enum GhcaDialerError {
     InvalidPassword(String),
 }
 
-#[derive(Debug)]
+impl fmt::Display for GhcaDialerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GhcaDialerError::InvalidUsername(ref username) => {
+                write!(f, "invalid username {:?}", username)
+            }
+            GhcaDialerError::InvalidPassword(_) => write!(f, "invalid password"),
+        }
+    }
+}
+
+impl error::Error for GhcaDialerError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Configuration {
     SichuanMac,
 }
@@ -21,6 +37,7 @@ pub struct GhcaDialer {
     pub share_key: String,
     pub prefix: String,
     pub version: String,
+    clock: Box<Clock>,
 }
 
 impl GhcaDialer {
@@ -29,15 +46,24 @@ impl GhcaDialer {
             share_key: share_key.to_string(),
             prefix: prefix.to_string(),
             version: version.to_string(),
+            clock: Box::new(SystemClock),
         }
     }
 
+    /// Clock read for the timestamps not passed to `encrypt_account`.
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
     fn validate(username: &str, password: &str) -> Result<(), GhcaDialerError> {
         if username.len() > 60 {
             return Err(GhcaDialerError::InvalidUsername(username.to_string()));
         }
         if password.len() > 60 {
-            return Err(GhcaDialerError::InvalidUsername(password.to_string()));
+            return Err(GhcaDialerError::InvalidPassword(password.to_string()));
         }
         Ok(())
     }
@@ -49,11 +75,10 @@ impl GhcaDialer {
                            sec_timestamp: Option<u32>)
                            -> Result<String, GhcaDialerError> {
         try!(Self::validate(username, password));
-        let name_len = username.len() as u32;
         let pwd_len = password.len() as u32;
 
-        let fst_timestamp = fst_timestamp.unwrap_or_else(current_timestamp);
-        let sec_timestamp = sec_timestamp.unwrap_or_else(current_timestamp);
+        let fst_timestamp = fst_timestamp.unwrap_or_else(|| self.clock.now());
+        let sec_timestamp = sec_timestamp.unwrap_or_else(|| self.clock.now());
 
         let mut cursor = fst_timestamp % pwd_len;
         if cursor < 1 {
@@ -66,6 +91,18 @@ impl GhcaDialer {
         };
 
         let delta = cursor - match_flag;
+        Ok(self.encrypt_with_delta(username, password, delta, sec_timestamp))
+    }
+
+    fn encrypt_with_delta(&self,
+                          username: &str,
+                          password: &str,
+                          delta: u32,
+                          sec_timestamp: u32)
+                          -> String {
+        let name_len = username.len() as u32;
+        let pwd_len = password.len() as u32;
+
         let md5_hash_prefix;
         {
             let mut md5 = HasherBuilder::build(HasherType::MD5);
@@ -90,17 +127,98 @@ impl GhcaDialer {
 
         let pwd_char_sum = password.as_bytes().iter().fold(0, |sum, x| sum + *x as u32);
         let pin = format!("{:04X}", delta ^ pwd_char_sum);
-        Ok(format!("{}{:08X}{}{}{}{}",
-                   self.prefix,
-                   sec_timestamp,
-                   self.version,
-                   md5_hash_prefix,
-                   pin,
-                   username))
+        format!("{}{:08X}{}{}{}{}",
+                self.prefix,
+                sec_timestamp,
+                self.version,
+                md5_hash_prefix,
+                pin,
+                username)
+    }
+
+    /// Recover the username, PIN (hash and checksum) and timestamp without
+    /// verifying the PIN, which needs the password.
+    pub fn decode_account(&self,
+                          encrypted: &str)
+                          -> DecodeResult<DecodedAccount<Configuration>> {
+        if !encrypted.starts_with(&self.prefix) {
+            return Err(DecodeError::UnexpectedPrefix(encrypted.to_string()));
+        }
+        let content = &encrypted[self.prefix.len()..];
+        let version_end = 8 + self.version.len();
+        let (timestamp, version, pin, username) = match (content.get(..8),
+                                                         content.get(8..version_end),
+                                                         content.get(version_end..
+                                                                     version_end + 20),
+                                                         content.get(version_end + 20..)) {
+            (Some(timestamp), Some(version), Some(pin), Some(username)) => {
+                (timestamp, version, pin, username)
+            }
+            _ => return Err(DecodeError::InvalidFormat(encrypted.to_string())),
+        };
+        if version != self.version || username.is_empty() {
+            return Err(DecodeError::InvalidFormat(encrypted.to_string()));
+        }
+        let timestamp = try!(u32::from_str_radix(timestamp, 16)
+            .map_err(|_| DecodeError::InvalidFormat(encrypted.to_string())));
+
+        Ok(DecodedAccount {
+            username: username.to_string(),
+            timestamp: Some(timestamp),
+            pin: Some(pin.to_string()),
+            configuration: None,
+        })
+    }
+
+    /// Decode `encrypted` and find the first of `configurations` producing the
+    /// same PIN for `password`.
+    pub fn verify_account(encrypted: &str,
+                          password: &str,
+                          configurations: &[Configuration])
+                          -> DecodeResult<DecodedAccount<Configuration>> {
+        let pwd_len = password.len() as u32;
+        let pwd_char_sum = password.as_bytes().iter().fold(0, |sum, x| sum + *x as u32);
+
+        for configuration in configurations {
+            let dialer = GhcaDialer::load_from_config(*configuration);
+            let mut decoded = try!(dialer.decode_account(encrypted));
+            if Self::validate(&decoded.username, password).is_err() {
+                return Err(DecodeError::InvalidFormat(encrypted.to_string()));
+            }
+
+            let checksum = decoded.pin.as_ref().and_then(|pin| pin.get(16..));
+            let delta = match checksum.map(|checksum| u32::from_str_radix(checksum, 16)) {
+                Some(Ok(checksum)) => checksum ^ pwd_char_sum,
+                _ => return Err(DecodeError::InvalidFormat(encrypted.to_string())),
+            };
+            // the share key slices of `encrypt_with_delta` must stay in bounds
+            let suffix_len = pwd_len.saturating_sub(delta + 1);
+            if delta >= pwd_len || decoded.username.len() as u32 + suffix_len > 64 {
+                continue;
+            }
+
+            let sec_timestamp = decoded.timestamp.unwrap_or_default();
+            if dialer.encrypt_with_delta(&decoded.username, password, delta, sec_timestamp) ==
+               encrypted {
+                decoded.configuration = Some(*configuration);
+                return Ok(decoded);
+            }
+        }
+        Err(DecodeError::PinMismatch(encrypted.to_string()))
     }
 }
 
 impl Configuration {
+    pub fn all() -> &'static [Configuration] {
+        &[Configuration::SichuanMac]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Configuration::SichuanMac => "sichuan_mac",
+        }
+    }
+
     pub fn share_key(&self) -> &'static str {
         match *self {
             Configuration::SichuanMac => "aI0fC8RslXg6HXaKAUa6kpvcAXszvTcxYP8jmS9sBnVfIqTRdJS1eZNHmBjKN28j",
@@ -127,3 +245,33 @@ impl Dialer for GhcaDialer {
         GhcaDialer::new(config.share_key(), config.prefix(), config.version())
     }
 }
+
+impl ConfigurableDialer for GhcaDialer {
+    fn section() -> &'static str {
+        "ghca"
+    }
+
+    fn builtin_parameters() -> Vec<(&'static str, DialerParameters)> {
+        Configuration::all()
+            .iter()
+            .map(|config| {
+                let mut parameters = DialerParameters::new();
+                parameters.insert("share_key".to_string(), config.share_key().to_string());
+                parameters.insert("prefix".to_string(), config.prefix().to_string());
+                parameters.insert("version".to_string(), config.version().to_string());
+                (config.name(), parameters)
+            })
+            .collect()
+    }
+
+    fn from_parameters(parameters: &DialerParameters) -> Option<Self> {
+        match (parameters.get("share_key"), parameters.get("prefix"), parameters.get("version")) {
+            // the hash takes up to 64 bytes of the share key, sliced as a str
+            (Some(share_key), Some(prefix), Some(version)) if share_key.len() >= 64 &&
+                                                               share_key.is_ascii() => {
+                Some(GhcaDialer::new(share_key, prefix, version))
+            }
+            _ => None,
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use common::dialer::Dialer;
-use ghca::dialer::{GhcaDialer, Configuration};
+use ghca::dialer::{GhcaDialer, GhcaDialerError, Configuration};
 
 #[test]
 fn test_ghca_username_encrypt() {
@@ -24,3 +24,38 @@ fn test_ghca_username_encrypt() {
                "~ghca57F4B7B020234370C48B10C2AF5E003105802278989@HYXY.XY");
     assert!(err_result.is_err());
 }
+
+#[test]
+fn test_ghca_invalid_account() {
+    let dialer = GhcaDialer::load_from_config(Configuration::SichuanMac);
+    let long = "1234567890".repeat(7);
+    match dialer.encrypt_account("05802278989@HYXY.XY", &long, None, None) {
+        Err(GhcaDialerError::InvalidPassword(ref password)) if *password == long => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match dialer.encrypt_account(&long, "123456", None, None) {
+        Err(GhcaDialerError::InvalidUsername(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_ghca_username_decode() {
+    let dialer = GhcaDialer::load_from_config(Configuration::SichuanMac);
+    let encrypted = "~ghca57F487192023484F1BD1D9AB5DC5013405802278989@HYXY.XY";
+    let decoded = dialer.decode_account(encrypted).unwrap();
+    assert_eq!(decoded.username, "05802278989@HYXY.XY");
+    assert_eq!(decoded.pin, Some("484F1BD1D9AB5DC50134".to_string()));
+    assert_eq!(decoded.timestamp, Some(0x57F48719));
+
+    let verified = GhcaDialer::verify_account(encrypted, "123456", &[Configuration::SichuanMac])
+        .unwrap();
+    assert_eq!(verified.configuration, Some(Configuration::SichuanMac));
+    let verified = GhcaDialer::verify_account("~ghca57F4B7B020234370C48B10C2AF5E003105802278989@HYXY.XY",
+                                              "1",
+                                              &[Configuration::SichuanMac])
+        .unwrap();
+    assert_eq!(verified.timestamp, Some(0x57F4B7B0));
+    assert!(GhcaDialer::verify_account(encrypted, "654321", &[Configuration::SichuanMac])
+        .is_err());
+}
@@ -1,15 +1,54 @@
+use std::{error, fmt, io, str};
 use std::net::Ipv4Addr;
 use std::num::Wrapping;
 
+use byteorder::{NetworkEndian, ByteOrder};
+use rustc_serialize::hex::ToHex;
+
 use common::bytes::BytesAbleNum;
+use common::reader::{ReadBytesError, ReaderHelper};
 
 const USERNAME_MAX_LEN: usize = 30;
 const MAC_ADDRESS_LEN: usize = 18;
+const PACKET_LEN: usize = 60;
 
 #[derive(Debug)]
 pub enum MACOpenErr {
     UsernameTooLong(String),
     MACAddressError(String),
+    PacketReadError(ReadBytesError),
+    // Expect checksum {}, got {}
+    ChecksumMismatch(Vec<u8>, Vec<u8>),
+    UnexpectedBytes(Vec<u8>),
+}
+
+impl fmt::Display for MACOpenErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MACOpenErr::UsernameTooLong(ref username) => {
+                write!(f, "username {:?} is too long", username)
+            }
+            MACOpenErr::MACAddressError(ref mac_address) => {
+                write!(f, "invalid mac address {:?}", mac_address)
+            }
+            MACOpenErr::PacketReadError(_) => write!(f, "read packet failed"),
+            MACOpenErr::ChecksumMismatch(ref expect, ref got) => {
+                write!(f, "expect checksum {}, got {}", expect.to_hex(), got.to_hex())
+            }
+            MACOpenErr::UnexpectedBytes(ref bytes) => {
+                write!(f, "unexpected bytes: {}", bytes.to_hex())
+            }
+        }
+    }
+}
+
+impl error::Error for MACOpenErr {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            MACOpenErr::PacketReadError(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -26,13 +65,24 @@ pub enum Configuration {
     GXNU,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ISPCode {
     CChinaUnicom = 1 << 8,
     CChinaTelecom = 2 << 8,
     CChinaMobile = 3 << 8,
 }
 
+impl ISPCode {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            v if v == ISPCode::CChinaUnicom as u32 => Some(ISPCode::CChinaUnicom),
+            v if v == ISPCode::CChinaTelecom as u32 => Some(ISPCode::CChinaTelecom),
+            v if v == ISPCode::CChinaMobile as u32 => Some(ISPCode::CChinaMobile),
+            _ => None,
+        }
+    }
+}
+
 impl Configuration {
     pub fn hash_key(&self) -> u32 {
         match *self {
@@ -74,6 +124,51 @@ impl MACOpenPacket {
         Ok(macopen_packet)
     }
 
+    /// Parse a packet and verify its hash against `hash_key`.
+    pub fn from_bytes<R>(input: &mut io::BufReader<R>, hash_key: u32) -> Result<Self, MACOpenErr>
+        where R: io::Read
+    {
+        let bytes = try!(input.read_bytes(PACKET_LEN).map_err(MACOpenErr::PacketReadError));
+
+        let expect = Self::hash_bytes(&bytes[..PACKET_LEN - 4], hash_key);
+        if expect[..] != bytes[PACKET_LEN - 4..] {
+            return Err(MACOpenErr::ChecksumMismatch(expect.to_vec(),
+                                                    bytes[PACKET_LEN - 4..].to_vec()));
+        }
+
+        let username = try!(string_from_bytes(&bytes[..USERNAME_MAX_LEN]));
+        let ip = &bytes[USERNAME_MAX_LEN..USERNAME_MAX_LEN + 4];
+        let mac_address = try!(string_from_bytes(&bytes[USERNAME_MAX_LEN + 4..PACKET_LEN - 8]));
+        let isp_bytes = &bytes[PACKET_LEN - 8..PACKET_LEN - 4];
+        let isp = match ISPCode::from_u32(NetworkEndian::read_u32(isp_bytes)) {
+            Some(isp) => isp,
+            None => return Err(MACOpenErr::UnexpectedBytes(isp_bytes.to_vec())),
+        };
+
+        let packet = MACOpenPacket::new(&username,
+                                        Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]),
+                                        &mac_address,
+                                        isp);
+        try!(packet.validate());
+        Ok(packet)
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn ipaddress(&self) -> Ipv4Addr {
+        self.ipaddress
+    }
+
+    pub fn mac_address(&self) -> &str {
+        &self.mac_address
+    }
+
+    pub fn isp(&self) -> ISPCode {
+        self.isp
+    }
+
     fn validate(&self) -> Result<(), MACOpenErr> {
         if self.username.len() > USERNAME_MAX_LEN - 1 {
             return Err(MACOpenErr::UsernameTooLong(self.username.clone()));
@@ -97,6 +192,13 @@ impl MACOpenPacket {
     }
 }
 
+/// Decode a NUL padded string field.
+fn string_from_bytes(bytes: &[u8]) -> Result<String, MACOpenErr> {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    str::from_utf8(&bytes[..end])
+        .map(|s| s.to_string())
+        .map_err(|_| MACOpenErr::UnexpectedBytes(bytes.to_vec()))
+}
 
 #[test]
 fn test_mac_opener_hash_bytes() {
@@ -1,5 +1,6 @@
 // copy from https://github.com/xuzhipengnt/ipclient_gxnu
 pub mod dialer;
+pub mod server;
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -0,0 +1,49 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use common::server::Responder;
+use ipclient::dialer::{MACOpenPacket, Configuration};
+
+/// Collects the MACOpen packets sent by clients, the protocol has no response.
+/// Packets with a wrong hash are dropped.
+///
+/// Clones share the received packets, keep one to inspect them while another
+/// one is served.
+#[derive(Debug, Clone)]
+pub struct MACOpenServer {
+    hash_key: u32,
+    received: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl Default for MACOpenServer {
+    fn default() -> Self {
+        MACOpenServer::with_hash_key(Configuration::GUET.hash_key())
+    }
+}
+
+impl MACOpenServer {
+    pub fn new() -> Self {
+        MACOpenServer::default()
+    }
+
+    pub fn with_hash_key(hash_key: u32) -> Self {
+        MACOpenServer {
+            hash_key: hash_key,
+            received: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn received(&self) -> Vec<Vec<u8>> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+impl Responder for MACOpenServer {
+    fn respond(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        let mut input = io::BufReader::new(request);
+        if MACOpenPacket::from_bytes(&mut input, self.hash_key).is_ok() {
+            self.received.lock().unwrap().push(request.to_vec());
+        }
+        None
+    }
+}
@@ -22,3 +22,62 @@ fn test_ipclient_macopener_packet() {
                                         ISPCode::CChinaUnicom);
     assert!(packet_err.as_bytes(Configuration::GUET.hash_key()).is_err());
 }
+
+#[test]
+fn test_ipclient_macopener_packet_from_bytes() {
+    use std::io::BufReader;
+    use ipclient::dialer::MACOpenErr;
+
+    let packet = MACOpenPacket::new("a",
+                                    Ipv4Addr::from_str("172.16.1.1").unwrap(),
+                                    "40:61:86:87:9F:F1",
+                                    ISPCode::CChinaTelecom);
+    let hash_key = Configuration::GUET.hash_key();
+    let mut packet_bytes = packet.as_bytes(hash_key).unwrap();
+
+    let parsed = MACOpenPacket::from_bytes(&mut BufReader::new(&packet_bytes[..]), hash_key)
+        .unwrap();
+    assert_eq!(parsed.username(), "a");
+    assert_eq!(parsed.ipaddress(), Ipv4Addr::new(172, 16, 1, 1));
+    assert_eq!(parsed.mac_address(), "40:61:86:87:9F:F1");
+    assert_eq!(parsed.isp(), ISPCode::CChinaTelecom);
+    assert_eq!(parsed.as_bytes(hash_key).unwrap(), packet_bytes);
+
+    packet_bytes[0] = b'b';
+    match MACOpenPacket::from_bytes(&mut BufReader::new(&packet_bytes[..]), hash_key) {
+        Err(MACOpenErr::ChecksumMismatch(..)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match MACOpenPacket::from_bytes(&mut BufReader::new(&packet_bytes[..20]), hash_key) {
+        Err(MACOpenErr::PacketReadError(..)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_ipclient_mock_server() {
+    use std::thread;
+    use std::time::Duration;
+    use common::server::UdpServer;
+    use common::transport::{Transport, UdpTransport};
+    use ipclient::server::MACOpenServer;
+
+    let server = MACOpenServer::new();
+    let udp_server = UdpServer::spawn_local(server.clone()).unwrap();
+    let mut transport = UdpTransport::connect("127.0.0.1:0", udp_server.local_addr()).unwrap();
+
+    let packet = MACOpenPacket::new("a",
+                                    Ipv4Addr::from_str("172.16.1.1").unwrap(),
+                                    "40:61:86:87:9F:F1",
+                                    ISPCode::CChinaUnicom);
+    let packet_bytes = packet.as_bytes(Configuration::GUET.hash_key()).unwrap();
+    transport.send(&packet_bytes).unwrap();
+
+    for _ in 0..50 {
+        if !server.received().is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(server.received(), vec![packet_bytes]);
+}
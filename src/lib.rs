@@ -10,6 +10,15 @@ extern crate md4;
 extern crate time;
 extern crate byteorder;
 extern crate rand;
+#[cfg(feature="toml")]
+extern crate toml;
+#[cfg(feature="async")]
+extern crate bytes;
+#[cfg(feature="async")]
+#[macro_use]
+extern crate futures;
+#[cfg(feature="async")]
+extern crate tokio;
 
 #[cfg(feature="drcom")]
 pub mod drcom;
@@ -25,7 +34,19 @@ pub mod singlenet;
 pub mod srun3k;
 
 pub mod common;
+pub mod error;
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k"))]
+pub mod detect;
+#[cfg(any(feature="drcom", feature="netkeeper", feature="singlenet"))]
+pub mod dissect;
+#[cfg(feature="ffi")]
+pub mod ffi;
+#[cfg(all(feature="pppd",
+          any(feature="netkeeper", feature="singlenet", feature="ghca", feature="srun3k")))]
+pub mod pppd;
 mod crypto;
 
+pub use error::{Error, Result};
+
 #[cfg(test)]
 mod tests {}
@@ -0,0 +1,68 @@
+use std::io;
+
+use futures::{future, Future};
+
+use common::async_transport::{AsyncTransport, AsyncTransportClient, BoxFuture};
+use crypto::cipher::AES_128_ECB;
+use netkeeper::heartbeater::{Frame, Packet, HeartbeatProfile};
+use netkeeper::frames::ServerFrame;
+use error::Result;
+
+/// Non-blocking `NetkeeperClient`, every method hands the client back with
+/// the response.
+#[derive(Debug)]
+pub struct AsyncNetkeeperClient<T: AsyncTransport> {
+    inner: AsyncTransportClient<T>,
+    encrypter: AES_128_ECB,
+}
+
+impl<T> AsyncNetkeeperClient<T>
+    where T: AsyncTransport + Send + 'static
+{
+    pub fn new(transport: T, aes_key: &[u8]) -> Result<Self> {
+        Ok(AsyncNetkeeperClient {
+            inner: AsyncTransportClient::new(transport),
+            encrypter: try!(AES_128_ECB::new(aes_key)),
+        })
+    }
+
+    pub fn from_profile(transport: T, profile: HeartbeatProfile) -> Result<Self> {
+        Ok(AsyncNetkeeperClient {
+            inner: AsyncTransportClient::new(transport),
+            encrypter: try!(profile.encrypter()),
+        })
+    }
+
+    pub fn inner(&mut self) -> &mut AsyncTransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn request(self, packet: &Packet) -> BoxFuture<(Self, Packet)> {
+        let AsyncNetkeeperClient { inner, encrypter } = self;
+        let request = match packet.as_bytes(&encrypter) {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e.into())),
+        };
+        Box::new(inner.exchange(request).and_then(move |(inner, response)| {
+            let packet = {
+                let mut buffer = io::BufReader::new(&response as &[u8]);
+                try!(Packet::from_bytes(&mut buffer, &encrypter, None))
+            };
+            let client = AsyncNetkeeperClient {
+                inner: inner,
+                encrypter: encrypter,
+            };
+            Ok((client, packet))
+        }))
+    }
+
+    pub fn send_frame(self,
+                      version: u8,
+                      code: u16,
+                      frame: Frame)
+                      -> BoxFuture<(Self, ServerFrame)> {
+        Box::new(self.request(&Packet::new(version, code, frame)).map(|(client, response)| {
+            (client, ServerFrame::from_frame(response.into_frame()))
+        }))
+    }
+}
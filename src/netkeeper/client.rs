@@ -0,0 +1,46 @@
+use std::io;
+
+use common::transport::{Transport, TransportClient};
+use crypto::cipher::AES_128_ECB;
+use netkeeper::heartbeater::{Frame, Packet, HeartbeatProfile};
+use netkeeper::frames::ServerFrame;
+use error::Result;
+
+#[derive(Debug)]
+pub struct NetkeeperClient<T: Transport> {
+    inner: TransportClient<T>,
+    encrypter: AES_128_ECB,
+}
+
+impl<T> NetkeeperClient<T>
+    where T: Transport
+{
+    pub fn new(transport: T, aes_key: &[u8]) -> Result<Self> {
+        Ok(NetkeeperClient {
+            inner: TransportClient::new(transport),
+            encrypter: try!(AES_128_ECB::new(aes_key)),
+        })
+    }
+
+    pub fn from_profile(transport: T, profile: HeartbeatProfile) -> Result<Self> {
+        Ok(NetkeeperClient {
+            inner: TransportClient::new(transport),
+            encrypter: try!(profile.encrypter()),
+        })
+    }
+
+    pub fn inner(&mut self) -> &mut TransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn request(&mut self, packet: &Packet) -> Result<Packet> {
+        let response = try!(self.inner.exchange(&try!(packet.as_bytes(&self.encrypter))));
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(Packet::from_bytes(&mut buffer, &self.encrypter, None)))
+    }
+
+    pub fn send_frame(&mut self, version: u8, code: u16, frame: Frame) -> Result<ServerFrame> {
+        let response = try!(self.request(&Packet::new(version, code, frame)));
+        Ok(ServerFrame::from_frame(response.into_frame()))
+    }
+}
@@ -3,12 +3,14 @@ use std::str;
 use rustc_serialize::hex::ToHex;
 
 use crypto::hash::{HasherBuilder, HasherType};
-use common::utils::current_timestamp;
-use common::dialer::Dialer;
+use common::clock::{Clock, SystemClock};
+use common::dialer::{Dialer, ConfigurableDialer, DialerParameters, DecodedAccount, DecodeError,
+                     DecodeResult};
 use common::bytes::BytesAbleNum;
+use netkeeper::heartbeater::HeartbeatProfile;
 
 // copy from https://github.com/miao1007/Openwrt-NetKeeper
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Configuration {
     Zhejiang,
     SingleNet,
@@ -28,6 +30,7 @@ pub enum Configuration {
 pub struct NetkeeperDialer {
     pub share_key: String,
     pub prefix: String,
+    clock: Box<Clock>,
 }
 
 impl NetkeeperDialer {
@@ -35,12 +38,21 @@ impl NetkeeperDialer {
         NetkeeperDialer {
             share_key: share_key.to_string(),
             prefix: prefix.to_string(),
+            clock: Box::new(SystemClock),
         }
     }
 
+    /// Clock of the timestamps embedded in usernames, `SystemClock` by default.
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
     pub fn encrypt_account(&self, username: &str, timestamp: Option<u32>) -> String {
         let username = username.to_uppercase();
-        let timenow = timestamp.unwrap_or_else(current_timestamp);
+        let timenow = timestamp.unwrap_or_else(|| self.clock.now());
         let time_div_by_five: u32 = timenow / 5;
 
         let mut pin27_byte: [u8; 6] = [0; 6];
@@ -85,9 +97,101 @@ impl NetkeeperDialer {
 
         format!("{}{}{}{}", self.prefix, pin27_str, pin89_str, username)
     }
+
+    /// Recover the username, PIN and timestamp (rounded down to a multiple of
+    /// five seconds) without verifying the PIN.
+    pub fn decode_account(&self,
+                          encrypted: &str)
+                          -> DecodeResult<DecodedAccount<Configuration>> {
+        if !encrypted.starts_with(&self.prefix) {
+            return Err(DecodeError::UnexpectedPrefix(encrypted.to_string()));
+        }
+        let content = &encrypted[self.prefix.len()..];
+        let (pin, username) = match (content.get(..8), content.get(8..)) {
+            (Some(pin), Some(username)) if !username.is_empty() => (pin, username),
+            _ => return Err(DecodeError::InvalidFormat(encrypted.to_string())),
+        };
+
+        let mut pin27_byte: [u8; 6] = [0; 6];
+        for (byte, code) in pin27_byte.iter_mut().zip(pin.bytes()) {
+            let code = if code > 0x41 { code - 1 } else { code };
+            if code < 0x20 || code >= 0x60 {
+                return Err(DecodeError::InvalidFormat(encrypted.to_string()));
+            }
+            *byte = code - 0x20;
+        }
+
+        let mut time_hash: [u8; 4] = [0; 4];
+        time_hash[0] = (pin27_byte[0] << 2) | (pin27_byte[1] >> 4);
+        time_hash[1] = ((pin27_byte[1] & 0x0F) << 4) | (pin27_byte[2] >> 2);
+        time_hash[2] = ((pin27_byte[2] & 0x03) << 6) | pin27_byte[3];
+        time_hash[3] = (pin27_byte[4] << 2) | (pin27_byte[5] >> 4);
+
+        let mut time_div_by_five: u32 = 0;
+        for (i, code) in time_hash.iter().enumerate() {
+            for j in 0..8 {
+                time_div_by_five |= (((*code >> (7 - j)) & 1) as u32) << (i + 4 * j);
+            }
+        }
+
+        Ok(DecodedAccount {
+            username: username.to_string(),
+            timestamp: Some(time_div_by_five.wrapping_mul(5)),
+            pin: Some(pin.to_string()),
+            configuration: None,
+        })
+    }
+
+    /// Decode `encrypted` and find the first of `configurations` whose share
+    /// key produces the same PIN.
+    pub fn verify_account(encrypted: &str,
+                          configurations: &[Configuration])
+                          -> DecodeResult<DecodedAccount<Configuration>> {
+        for configuration in configurations {
+            let dialer = NetkeeperDialer::load_from_config(*configuration);
+            let mut decoded = try!(dialer.decode_account(encrypted));
+            if dialer.encrypt_account(&decoded.username, decoded.timestamp) == encrypted {
+                decoded.configuration = Some(*configuration);
+                return Ok(decoded);
+            }
+        }
+        Err(DecodeError::PinMismatch(encrypted.to_string()))
+    }
 }
 
 impl Configuration {
+    pub fn all() -> &'static [Configuration] {
+        &[Configuration::Zhejiang,
+          Configuration::SingleNet,
+          Configuration::Enterprise,
+          Configuration::Chongqing,
+          Configuration::Chongqing2,
+          Configuration::Wuhan,
+          Configuration::Qinghai,
+          Configuration::Xinjiang,
+          Configuration::Hebei,
+          Configuration::Shandong,
+          Configuration::Shanxi,
+          Configuration::Gansu]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Configuration::Zhejiang => "zhejiang",
+            Configuration::SingleNet => "single_net",
+            Configuration::Enterprise => "enterprise",
+            Configuration::Chongqing => "chongqing",
+            Configuration::Chongqing2 => "chongqing2",
+            Configuration::Wuhan => "wuhan",
+            Configuration::Qinghai => "qinghai",
+            Configuration::Xinjiang => "xinjiang",
+            Configuration::Hebei => "hebei",
+            Configuration::Shandong => "shandong",
+            Configuration::Shanxi => "shanxi",
+            Configuration::Gansu => "gansu",
+        }
+    }
+
     pub fn share_key(&self) -> &'static str {
         match *self {
             Configuration::Zhejiang => "zjxinlisx01",
@@ -110,6 +214,14 @@ impl Configuration {
             _ => "\r\n",
         }
     }
+
+    /// `None` for the provinces whose heartbeat AES key is unknown.
+    pub fn heartbeat_profile(&self) -> Option<HeartbeatProfile> {
+        match *self {
+            Configuration::Zhejiang => Some(HeartbeatProfile::Zhejiang),
+            _ => None,
+        }
+    }
 }
 
 impl Dialer for NetkeeperDialer {
@@ -119,3 +231,28 @@ impl Dialer for NetkeeperDialer {
         NetkeeperDialer::new(config.share_key(), config.prefix())
     }
 }
+
+impl ConfigurableDialer for NetkeeperDialer {
+    fn section() -> &'static str {
+        "netkeeper"
+    }
+
+    fn builtin_parameters() -> Vec<(&'static str, DialerParameters)> {
+        Configuration::all()
+            .iter()
+            .map(|config| {
+                let mut parameters = DialerParameters::new();
+                parameters.insert("share_key".to_string(), config.share_key().to_string());
+                parameters.insert("prefix".to_string(), config.prefix().to_string());
+                (config.name(), parameters)
+            })
+            .collect()
+    }
+
+    fn from_parameters(parameters: &DialerParameters) -> Option<Self> {
+        match (parameters.get("share_key"), parameters.get("prefix")) {
+            (Some(share_key), Some(prefix)) => Some(NetkeeperDialer::new(share_key, prefix)),
+            _ => None,
+        }
+    }
+}
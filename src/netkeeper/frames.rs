@@ -0,0 +1,186 @@
+use std::net::Ipv4Addr;
+
+use common::clock::{Clock, FixedClock};
+use netkeeper::heartbeater::{Frame, PacketUtils};
+
+const DEFAULT_VERSION_NUMBER: &'static str = "1.0.1";
+const DEFAULT_DRIVER: &'static str = "1";
+const RESULT_SUCCESS: &'static str = "0";
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FrameType {
+    Heartbeat,
+    Register,
+    Logout,
+    Notice,
+    Other(String),
+}
+
+/// Account fields shared by the frames a client sends.
+#[derive(Debug)]
+pub struct HeartbeatAccount {
+    username: String,
+    password: String,
+    ipaddress: Ipv4Addr,
+    mac_address: String,
+    version_number: String,
+    driver: String,
+    key: Option<String>,
+}
+
+/// Frames sent by the server. Replies carry the TYPE of the request with
+/// `RESULT` (`0` on success) and `REASON`, notices carry `TITLE` and `CONTENT`.
+#[derive(Debug)]
+pub enum ServerFrame {
+    Accepted(FrameType),
+    // Rejected the frame of type {} because of {}
+    Rejected(FrameType, String),
+    Notice { title: String, content: String },
+    Unknown(Frame),
+}
+
+impl FrameType {
+    pub fn name(&self) -> &str {
+        match *self {
+            FrameType::Heartbeat => "HEARTBEAT",
+            FrameType::Register => "REGISTER",
+            FrameType::Logout => "LOGOUT",
+            FrameType::Notice => "NOTICE",
+            FrameType::Other(ref name) => name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "HEARTBEAT" => FrameType::Heartbeat,
+            "REGISTER" | "LOGIN" => FrameType::Register,
+            "LOGOUT" => FrameType::Logout,
+            "NOTICE" => FrameType::Notice,
+            _ => FrameType::Other(name.to_string()),
+        }
+    }
+}
+
+impl HeartbeatAccount {
+    pub fn new(username: &str, password: &str, ipaddress: Ipv4Addr, mac_address: &str) -> Self {
+        HeartbeatAccount {
+            username: username.to_string(),
+            password: password.to_string(),
+            ipaddress: ipaddress,
+            mac_address: mac_address.to_string(),
+            version_number: DEFAULT_VERSION_NUMBER.to_string(),
+            driver: DEFAULT_DRIVER.to_string(),
+            key: None,
+        }
+    }
+
+    pub fn version_number(&mut self, value: &str) -> &mut Self {
+        self.version_number = value.to_string();
+        self
+    }
+
+    pub fn driver(&mut self, value: &str) -> &mut Self {
+        self.driver = value.to_string();
+        self
+    }
+
+    pub fn key(&mut self, value: Option<&str>) -> &mut Self {
+        self.key = value.map(|key| key.to_string());
+        self
+    }
+
+    pub fn heartbeat_frame(&self, timestamp: Option<u32>) -> Frame {
+        self.heartbeat_frame_with_clock(&FixedClock::from_timestamp(timestamp))
+    }
+
+    pub fn register_frame(&self, timestamp: Option<u32>) -> Frame {
+        self.register_frame_with_clock(&FixedClock::from_timestamp(timestamp))
+    }
+
+    pub fn logout_frame(&self, timestamp: Option<u32>) -> Frame {
+        self.logout_frame_with_clock(&FixedClock::from_timestamp(timestamp))
+    }
+
+    /// The heartbeat frame with the PIN of the time told by `clock`.
+    pub fn heartbeat_frame_with_clock(&self, clock: &Clock) -> Frame {
+        let mut frame = self.account_frame(FrameType::Heartbeat, clock);
+        frame.add("DRIVER", &self.driver);
+        if let Some(ref key) = self.key {
+            frame.add("KEY", key);
+        }
+        frame
+    }
+
+    pub fn register_frame_with_clock(&self, clock: &Clock) -> Frame {
+        let mut frame = self.account_frame(FrameType::Register, clock);
+        frame.add("DRIVER", &self.driver);
+        frame
+    }
+
+    pub fn logout_frame_with_clock(&self, clock: &Clock) -> Frame {
+        let mut frame = Frame::new(FrameType::Logout.name(), None);
+        frame.add("USER_NAME", &self.username);
+        frame.add("IP", &self.ipaddress.to_string());
+        frame.add("MAC", &self.mac_address);
+        frame.add("VERSION_NUMBER", &self.version_number);
+        frame.add("PIN", &PacketUtils::claculate_pin_with_clock(clock));
+        frame
+    }
+
+    fn account_frame(&self, frame_type: FrameType, clock: &Clock) -> Frame {
+        let mut frame = Frame::new(frame_type.name(), None);
+        frame.add("USER_NAME", &self.username);
+        frame.add("PASSWORD", &self.password);
+        frame.add("IP", &self.ipaddress.to_string());
+        frame.add("MAC", &self.mac_address);
+        frame.add("VERSION_NUMBER", &self.version_number);
+        frame.add("PIN", &PacketUtils::claculate_pin_with_clock(clock));
+        frame
+    }
+}
+
+impl ServerFrame {
+    pub fn from_frame(frame: Frame) -> Self {
+        let frame_type = FrameType::from_name(frame.type_name());
+        if frame_type == FrameType::Notice {
+            return ServerFrame::Notice {
+                title: frame.get("TITLE").unwrap_or_default().to_string(),
+                content: frame.get("CONTENT").unwrap_or_default().to_string(),
+            };
+        }
+
+        let result = match frame.get("RESULT") {
+            Some(result) => result.to_string(),
+            None => return ServerFrame::Unknown(frame),
+        };
+        if result == RESULT_SUCCESS {
+            ServerFrame::Accepted(frame_type)
+        } else {
+            let reason = frame.get("REASON").unwrap_or(&result).to_string();
+            ServerFrame::Rejected(frame_type, reason)
+        }
+    }
+
+    pub fn as_frame(&self) -> Frame {
+        match *self {
+            ServerFrame::Accepted(ref frame_type) => {
+                let mut frame = Frame::new(frame_type.name(), None);
+                frame.add("RESULT", RESULT_SUCCESS);
+                frame
+            }
+            ServerFrame::Rejected(ref frame_type, ref reason) => {
+                let mut frame = Frame::new(frame_type.name(), None);
+                frame.add("RESULT", "1");
+                frame.add("REASON", reason);
+                frame
+            }
+            ServerFrame::Notice { ref title, ref content } => {
+                let mut frame = Frame::new(FrameType::Notice.name(), None);
+                frame.add("TITLE", title);
+                frame.add("CONTENT", content);
+                frame
+            }
+            ServerFrame::Unknown(ref frame) => frame.clone(),
+        }
+    }
+}
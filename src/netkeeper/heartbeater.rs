@@ -1,25 +1,65 @@
-use std::{io, str, result};
+use std::{io, str, result, error, fmt};
 use std::str::FromStr;
 
-use crypto::cipher::{SimpleCipher, CipherError};
+use crypto::cipher::{SimpleCipher, CipherError, AES_128_ECB};
 use crypto::hash::{HasherBuilder, HasherType};
 use linked_hash_map::LinkedHashMap;
 use byteorder::{NetworkEndian, ByteOrder};
 
-use common::utils::current_timestamp;
+use common::clock::{Clock, FixedClock};
 use common::reader::{ReadBytesError, ReaderHelper};
 use common::bytes::BytesAbleNum;
+#[cfg(feature="async")]
+use common::async_transport::{read_packet, BoxFuture};
+#[cfg(feature="async")]
+use bytes::BytesMut;
+#[cfg(feature="async")]
+use futures::Future;
+#[cfg(feature="async")]
+use tokio::codec::{Decoder, Encoder};
+#[cfg(feature="async")]
+use tokio::io::AsyncRead;
+#[cfg(feature="async")]
+use error::Error;
+use netkeeper::dialer::Configuration;
+use netkeeper::frames::HeartbeatAccount;
 
 #[derive(Debug)]
 pub enum NetkeeperHeartbeatError {
     PacketCipherError(CipherError),
     PacketReadError(ReadBytesError),
     UnexpectedBytes(Vec<u8>),
+    HeartbeatRejected(String),
+}
+
+impl fmt::Display for NetkeeperHeartbeatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetkeeperHeartbeatError::PacketCipherError(_) => write!(f, "packet cipher failed"),
+            NetkeeperHeartbeatError::PacketReadError(_) => write!(f, "read packet failed"),
+            NetkeeperHeartbeatError::UnexpectedBytes(ref bytes) => {
+                write!(f, "unexpected bytes {:?}", bytes)
+            }
+            NetkeeperHeartbeatError::HeartbeatRejected(ref reason) => {
+                write!(f, "heartbeat rejected because of {}", reason)
+            }
+        }
+    }
+}
+
+impl error::Error for NetkeeperHeartbeatError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            NetkeeperHeartbeatError::PacketCipherError(ref e) => Some(e),
+            NetkeeperHeartbeatError::PacketReadError(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 type PacketResult<T> = result::Result<T, NetkeeperHeartbeatError>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Frame {
     type_name: String,
     content: LinkedHashMap<String, String>,
@@ -35,6 +75,31 @@ pub struct Packet {
 
 pub struct PacketUtils;
 
+/// Frames Netkeeper packets on a stream as their bytes arrive, for
+/// `tokio::codec::Framed`, the contents are ciphered with `encrypter`.
+#[cfg(feature="async")]
+pub struct PacketCodec<E: SimpleCipher> {
+    encrypter: E,
+    split_with: Option<&'static str>,
+}
+
+/// Heartbeat protocol parameters of a province, named after its
+/// `netkeeper::dialer::Configuration`. Provinces get a profile once their
+/// AES key is known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeartbeatProfile {
+    Zhejiang,
+}
+
+const HEARTBEAT_FIELDS_V30: &'static [&'static str] = &["USER_NAME",
+                                                         "PASSWORD",
+                                                         "IP",
+                                                         "MAC",
+                                                         "VERSION_NUMBER",
+                                                         "PIN",
+                                                         "DRIVER",
+                                                         "KEY"];
+
 impl Frame {
     pub fn new(type_name: &str, content: Option<LinkedHashMap<String, String>>) -> Self {
         let content = content.unwrap_or_else(LinkedHashMap::new);
@@ -48,6 +113,18 @@ impl Frame {
         self.content.insert(name.to_string(), value.to_string());
     }
 
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.content.get(name).map(|value| value.as_str())
+    }
+
+    pub fn fields<'a>(&'a self) -> ::linked_hash_map::Iter<'a, String, String> {
+        self.content.iter()
+    }
+
     fn as_bytes(&self, join_with: Option<&str>) -> Vec<u8> {
         let mut linked_content: Vec<String> = Vec::new();
         let join_with = join_with.unwrap_or("&");
@@ -61,10 +138,7 @@ impl Frame {
     fn from_bytes(bytes: &[u8], split_with: Option<&str>) -> Self {
         let split_with = split_with.unwrap_or("&");
 
-        let byte_content;
-        unsafe {
-            byte_content = str::from_utf8_unchecked(bytes);
-        }
+        let byte_content = String::from_utf8_lossy(bytes);
 
         let mut type_name = String::from("");
         let mut frame_content: LinkedHashMap<String, String> = LinkedHashMap::new();
@@ -107,6 +181,22 @@ impl Packet {
         }
     }
 
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub fn into_frame(self) -> Frame {
+        self.frame
+    }
+
     pub fn as_bytes<E>(&self, encrypter: &E) -> PacketResult<Vec<u8>>
         where E: SimpleCipher
     {
@@ -145,8 +235,10 @@ impl Packet {
         {
             let version_bytes = try!(input.read_bytes(2)
                 .map_err(NetkeeperHeartbeatError::PacketReadError));
-            let version_str = String::from_utf8(version_bytes).unwrap();
-            version = version_str.parse::<u8>().unwrap();
+            version = match str::from_utf8(&version_bytes).ok().and_then(|v| v.parse().ok()) {
+                Some(version) => version,
+                None => return Err(NetkeeperHeartbeatError::UnexpectedBytes(version_bytes)),
+            };
         }
 
         let code;
@@ -173,9 +265,144 @@ impl Packet {
     }
 }
 
+#[cfg(feature="async")]
+impl Packet {
+    /// Async counterpart of [`Packet::from_bytes`].
+    pub fn from_async_read<R, E>(reader: R,
+                                 encrypter: E,
+                                 split_with: Option<&'static str>)
+                                 -> BoxFuture<(R, Self)>
+        where R: AsyncRead + Send + 'static,
+              E: SimpleCipher + Send + 'static
+    {
+        Box::new(read_packet(reader, Self::header_length(), Self::frame_length)
+            .and_then(move |(reader, packet)| {
+                let mut buffer = io::BufReader::new(&packet as &[u8]);
+                let packet = try!(Self::from_bytes(&mut buffer, &encrypter, split_with));
+                Ok((reader, packet))
+            }))
+    }
+
+    fn header_length() -> usize {
+        // len(magic_number) + len(version) + len(code) + len(content_length)
+        10
+    }
+
+    fn frame_length(header: &[u8]) -> usize {
+        Self::header_length() + NetworkEndian::read_u32(&header[6..10]) as usize
+    }
+}
+
+#[cfg(feature="async")]
+impl<E> PacketCodec<E>
+    where E: SimpleCipher
+{
+    pub fn new(encrypter: E, split_with: Option<&'static str>) -> Self {
+        PacketCodec {
+            encrypter: encrypter,
+            split_with: split_with,
+        }
+    }
+}
+
+#[cfg(feature="async")]
+impl<E> Decoder for PacketCodec<E>
+    where E: SimpleCipher
+{
+    type Item = Packet;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Packet>, Error> {
+        if src.len() < Packet::header_length() {
+            return Ok(None);
+        }
+        let length = Packet::frame_length(src);
+        if src.len() < length {
+            src.reserve(length - src.len());
+            return Ok(None);
+        }
+        let packet = src.split_to(length);
+        let mut buffer = io::BufReader::new(&packet as &[u8]);
+        Ok(Some(try!(Packet::from_bytes(&mut buffer, &self.encrypter, self.split_with))))
+    }
+}
+
+#[cfg(feature="async")]
+impl<E> Encoder for PacketCodec<E>
+    where E: SimpleCipher
+{
+    type Item = Packet;
+    type Error = Error;
+
+    fn encode(&mut self, packet: Packet, dst: &mut BytesMut) -> Result<(), Error> {
+        dst.extend_from_slice(&try!(packet.as_bytes(&self.encrypter)));
+        Ok(())
+    }
+}
+
+impl HeartbeatProfile {
+    pub fn aes_key(&self) -> &'static [u8] {
+        match *self {
+            HeartbeatProfile::Zhejiang => b"xlzjhrprotocol3x",
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        match *self {
+            HeartbeatProfile::Zhejiang => 30,
+        }
+    }
+
+    pub fn code(&self) -> u16 {
+        match *self {
+            HeartbeatProfile::Zhejiang => 0x0205,
+        }
+    }
+
+    pub fn heartbeat_fields(&self) -> &'static [&'static str] {
+        match *self {
+            HeartbeatProfile::Zhejiang => HEARTBEAT_FIELDS_V30,
+        }
+    }
+
+    pub fn configuration(&self) -> Configuration {
+        match *self {
+            HeartbeatProfile::Zhejiang => Configuration::Zhejiang,
+        }
+    }
+
+    pub fn encrypter(&self) -> PacketResult<AES_128_ECB> {
+        AES_128_ECB::new(self.aes_key()).map_err(NetkeeperHeartbeatError::PacketCipherError)
+    }
+
+    /// Build the heartbeat frame of `account` with the fields of this profile.
+    pub fn heartbeat_frame(&self, account: &HeartbeatAccount, timestamp: Option<u32>) -> Frame {
+        self.heartbeat_frame_with_clock(account, &FixedClock::from_timestamp(timestamp))
+    }
+
+    pub fn heartbeat_frame_with_clock(&self, account: &HeartbeatAccount, clock: &Clock) -> Frame {
+        let full_frame = account.heartbeat_frame_with_clock(clock);
+        let mut frame = Frame::new(full_frame.type_name(), None);
+        for name in self.heartbeat_fields() {
+            if let Some(value) = full_frame.get(name) {
+                frame.add(name, value);
+            }
+        }
+        frame
+    }
+
+    pub fn packet(&self, frame: Frame) -> Packet {
+        Packet::new(self.version(), self.code(), frame)
+    }
+}
+
 impl PacketUtils {
     pub fn claculate_pin(timestamp: Option<u32>) -> String {
-        let timestamp = timestamp.unwrap_or_else(current_timestamp);
+        Self::claculate_pin_with_clock(&FixedClock::from_timestamp(timestamp))
+    }
+
+    pub fn claculate_pin_with_clock(clock: &Clock) -> String {
+        let timestamp = clock.now();
         let salts = ["wanglei", "zhangni", "wangtianyou"];
         let mut hashed_bytes = [0; 16];
         let timestamp_hex = format!("{:08x}", timestamp);
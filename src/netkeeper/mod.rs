@@ -1,5 +1,11 @@
 pub mod dialer;
 pub mod heartbeater;
+pub mod frames;
+pub mod client;
+pub mod server;
+pub mod session;
+#[cfg(feature="async")]
+pub mod async_client;
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -0,0 +1,63 @@
+use std::io;
+use std::collections::BTreeMap;
+
+use common::server::Responder;
+use crypto::cipher::AES_128_ECB;
+use netkeeper::frames::{FrameType, ServerFrame};
+use netkeeper::heartbeater::{Frame, Packet, HeartbeatProfile};
+use error::Result;
+
+/// Emulates a Netkeeper heartbeat server. Frames of known accounts are
+/// accepted, when no account is registered every frame is accepted.
+#[derive(Debug)]
+pub struct NetkeeperServer {
+    encrypter: AES_128_ECB,
+    accounts: BTreeMap<String, String>,
+}
+
+impl NetkeeperServer {
+    pub fn new(profile: HeartbeatProfile) -> Result<Self> {
+        Ok(NetkeeperServer {
+            encrypter: try!(profile.encrypter()),
+            accounts: BTreeMap::new(),
+        })
+    }
+
+    pub fn account(&mut self, username: &str, password: &str) -> &mut Self {
+        self.accounts.insert(username.to_string(), password.to_string());
+        self
+    }
+
+    fn reply(&self, frame: &Frame) -> ServerFrame {
+        let frame_type = FrameType::from_name(frame.type_name());
+        if self.accounts.is_empty() {
+            return ServerFrame::Accepted(frame_type);
+        }
+
+        let password = match frame.get("USER_NAME").and_then(|name| self.accounts.get(name)) {
+            Some(password) => password,
+            None => return ServerFrame::Rejected(frame_type, "unknown user".to_string()),
+        };
+        match frame.get("PASSWORD") {
+            Some(value) if value != password => {
+                ServerFrame::Rejected(frame_type, "wrong password".to_string())
+            }
+            _ => ServerFrame::Accepted(frame_type),
+        }
+    }
+}
+
+impl Responder for NetkeeperServer {
+    fn respond(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        let packet = match Packet::from_bytes(&mut io::BufReader::new(request),
+                                              &self.encrypter,
+                                              None) {
+            Ok(packet) => packet,
+            Err(_) => return None,
+        };
+        let reply = self.reply(packet.frame());
+        Packet::new(packet.version(), packet.code(), reply.as_frame())
+            .as_bytes(&self.encrypter)
+            .ok()
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use common::daemon::KeepAliveSession;
+use common::transport::Transport;
+use netkeeper::client::NetkeeperClient;
+use netkeeper::frames::{HeartbeatAccount, ServerFrame};
+use netkeeper::heartbeater::{HeartbeatProfile, NetkeeperHeartbeatError};
+use error::Result;
+
+pub const KEEP_ALIVE_INTERVAL: u64 = 60;
+
+/// Sends the heartbeat frame of one account per round. The protocol has no
+/// login step, a rejected heartbeat fails the round.
+#[derive(Debug)]
+pub struct NetkeeperSession<T: Transport> {
+    client: NetkeeperClient<T>,
+    profile: HeartbeatProfile,
+    account: HeartbeatAccount,
+    // (title, content) of the notices not taken yet
+    notices: Vec<(String, String)>,
+}
+
+impl<T> NetkeeperSession<T>
+    where T: Transport
+{
+    pub fn new(transport: T, profile: HeartbeatProfile, account: HeartbeatAccount) -> Result<Self> {
+        Ok(NetkeeperSession {
+            client: try!(NetkeeperClient::from_profile(transport, profile)),
+            profile: profile,
+            account: account,
+            notices: Vec::new(),
+        })
+    }
+
+    pub fn client(&mut self) -> &mut NetkeeperClient<T> {
+        &mut self.client
+    }
+
+    /// Notices pushed by the server since the last call.
+    pub fn take_notices(&mut self) -> Vec<(String, String)> {
+        self.notices.drain(..).collect()
+    }
+
+    pub fn keep_alive(&mut self) -> Result<()> {
+        let frame = self.profile.heartbeat_frame(&self.account, None);
+        let reply = try!(self.client
+            .send_frame(self.profile.version(), self.profile.code(), frame));
+        match reply {
+            ServerFrame::Rejected(_, reason) => {
+                Err(NetkeeperHeartbeatError::HeartbeatRejected(reason).into())
+            }
+            ServerFrame::Notice { title, content } => {
+                self.notices.push((title, content));
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T> KeepAliveSession for NetkeeperSession<T>
+    where T: Transport
+{
+    fn login(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn keep_alive(&mut self) -> Result<()> {
+        NetkeeperSession::keep_alive(self)
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(KEEP_ALIVE_INTERVAL)
+    }
+}
@@ -1,8 +1,13 @@
-use common::dialer::Dialer;
+use common::dialer::{Dialer, DecodeError};
 use netkeeper::dialer::{NetkeeperDialer, Configuration};
 use std::io::BufReader;
 use crypto::cipher::AES_128_ECB;
-use netkeeper::heartbeater::{Frame, Packet};
+use netkeeper::heartbeater::{Frame, Packet, HeartbeatProfile};
+use netkeeper::client::NetkeeperClient;
+use netkeeper::frames::{HeartbeatAccount, FrameType, ServerFrame};
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use common::transport::LoopbackTransport;
 
 #[test]
 fn test_netkeeper_username_encrypt() {
@@ -11,6 +16,39 @@ fn test_netkeeper_username_encrypt() {
     assert_eq!(encrypted, "\r\n:R#(P 5005802278989@HYXY.XY");
 }
 
+#[test]
+fn test_netkeeper_dialer_clock() {
+    use common::clock::{FixedClock, OffsetClock};
+
+    let mut dialer = NetkeeperDialer::load_from_config(Configuration::Zhejiang);
+    dialer.clock(OffsetClock::new(FixedClock::new(1472483000), 20));
+    assert_eq!(dialer.encrypt_account("05802278989@HYXY.XY", None),
+               "\r\n:R#(P 5005802278989@HYXY.XY");
+    assert!(dialer.encrypt_account("05802278989@HYXY.XY", Some(1472483040)) !=
+            "\r\n:R#(P 5005802278989@HYXY.XY");
+}
+
+#[test]
+fn test_netkeeper_frame_clock() {
+    use common::clock::FixedClock;
+    use netkeeper::heartbeater::PacketUtils;
+
+    let profile = HeartbeatProfile::Zhejiang;
+    let account = HeartbeatAccount::new("05802278989@HYXY.XY",
+                                        "123456",
+                                        Ipv4Addr::from_str("124.77.234.214").unwrap(),
+                                        "08:00:27:00:24:FD");
+    let clock = FixedClock::new(1472483020);
+    let pin = PacketUtils::claculate_pin(Some(1472483020));
+    assert_eq!(PacketUtils::claculate_pin_with_clock(&clock), pin);
+    for frame in &[account.heartbeat_frame_with_clock(&clock),
+                    account.register_frame_with_clock(&clock),
+                    account.logout_frame_with_clock(&clock),
+                    profile.heartbeat_frame_with_clock(&account, &clock)] {
+        assert_eq!(frame.get("PIN"), Some(pin.as_str()));
+    }
+}
+
 #[test]
 fn test_netkeeper_heartbeat() {
     let mut frame = Frame::new("HEARTBEAT", None);
@@ -59,3 +97,337 @@ fn test_netkeeper_heartbeat_parse() {
     let packet_bytes = packet.as_bytes(&encrypter).unwrap();
     assert_eq!(packet_bytes, origin_bytes);
 }
+
+#[test]
+fn test_netkeeper_client() {
+    let transport = LoopbackTransport::new(|bytes| Some(bytes.to_vec()));
+    let mut client = NetkeeperClient::new(transport, b"xlzjhrprotocol3x").unwrap();
+
+    let mut frame = Frame::new("HEARTBEAT", None);
+    frame.add("USER_NAME", "05802278989@HYXY.XY");
+    let packet = Packet::new(30 as u8, 0x0205, frame);
+
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    let response = client.request(&packet).unwrap();
+    assert_eq!(response.as_bytes(&encrypter).unwrap(),
+               packet.as_bytes(&encrypter).unwrap());
+    assert!(NetkeeperClient::new(LoopbackTransport::scripted(vec![]), b"short").is_err());
+}
+
+#[test]
+fn test_netkeeper_typed_frames() {
+    let mut account = HeartbeatAccount::new("05802278989@HYXY.XY",
+                                            "123456",
+                                            Ipv4Addr::from_str("124.77.234.214").unwrap(),
+                                            "08:00:27:00:24:FD");
+    account.key(Some("123456"));
+
+    let mut frame = Frame::new("HEARTBEAT", None);
+    frame.add("USER_NAME", "05802278989@HYXY.XY");
+    frame.add("PASSWORD", "123456");
+    frame.add("IP", "124.77.234.214");
+    frame.add("MAC", "08:00:27:00:24:FD");
+    frame.add("VERSION_NUMBER", "1.0.1");
+    frame.add("PIN", "57c41bc45b493cfb5f5016074e987ef9cca96334");
+    frame.add("DRIVER", "1");
+    frame.add("KEY", "123456");
+
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    let typed = Packet::new(30, 0x0205, account.heartbeat_frame(Some(1472483020)));
+    let manual = Packet::new(30, 0x0205, frame);
+    assert_eq!(typed.as_bytes(&encrypter).unwrap(),
+               manual.as_bytes(&encrypter).unwrap());
+
+    let register = account.register_frame(Some(1472483020));
+    assert_eq!(register.type_name(), "REGISTER");
+    assert_eq!(register.get("KEY"), None);
+    let logout = account.logout_frame(Some(1472483020));
+    assert_eq!(logout.type_name(), "LOGOUT");
+    assert_eq!(logout.get("PASSWORD"), None);
+    assert_eq!(logout.get("PIN"),
+               Some("57c41bc45b493cfb5f5016074e987ef9cca96334"));
+}
+
+#[test]
+fn test_netkeeper_server_frames() {
+    let notice = ServerFrame::Notice {
+        title: "maintenance".to_string(),
+        content: "offline at 2:00".to_string(),
+    };
+    match ServerFrame::from_frame(notice.as_frame()) {
+        ServerFrame::Notice { title, content } => {
+            assert_eq!(title, "maintenance");
+            assert_eq!(content, "offline at 2:00");
+        }
+        other => panic!("unexpected frame {:?}", other),
+    }
+
+    let rejected = ServerFrame::Rejected(FrameType::Register, "wrong pin".to_string());
+    match ServerFrame::from_frame(rejected.as_frame()) {
+        ServerFrame::Rejected(FrameType::Register, ref reason) if reason == "wrong pin" => {}
+        other => panic!("unexpected frame {:?}", other),
+    }
+
+    match ServerFrame::from_frame(Frame::new("HEARTBEAT", None)) {
+        ServerFrame::Unknown(ref frame) if frame.type_name() == "HEARTBEAT" => {}
+        other => panic!("unexpected frame {:?}", other),
+    }
+
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    let transport = LoopbackTransport::new(move |_| {
+        let reply = Packet::new(30, 0x0205, ServerFrame::Accepted(FrameType::Heartbeat).as_frame());
+        Some(reply.as_bytes(&encrypter).unwrap())
+    });
+    let mut client = NetkeeperClient::new(transport, b"xlzjhrprotocol3x").unwrap();
+    let account = HeartbeatAccount::new("05802278989@HYXY.XY",
+                                        "123456",
+                                        Ipv4Addr::from_str("124.77.234.214").unwrap(),
+                                        "08:00:27:00:24:FD");
+    match client.send_frame(30, 0x0205, account.heartbeat_frame(None)).unwrap() {
+        ServerFrame::Accepted(FrameType::Heartbeat) => {}
+        other => panic!("unexpected frame {:?}", other),
+    }
+}
+
+#[test]
+fn test_netkeeper_heartbeat_profile() {
+    let profile = Configuration::Zhejiang.heartbeat_profile().unwrap();
+    assert_eq!(profile, HeartbeatProfile::Zhejiang);
+    assert_eq!(profile.configuration(), Configuration::Zhejiang);
+
+    let mut account = HeartbeatAccount::new("05802278989@HYXY.XY",
+                                            "123456",
+                                            Ipv4Addr::from_str("124.77.234.214").unwrap(),
+                                            "08:00:27:00:24:FD");
+    account.key(Some("123456"));
+    let packet = profile.packet(profile.heartbeat_frame(&account, Some(1472483020)));
+    let expected = Packet::new(30, 0x0205, account.heartbeat_frame(Some(1472483020)));
+
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    assert_eq!(packet.as_bytes(&profile.encrypter().unwrap()).unwrap(),
+               expected.as_bytes(&encrypter).unwrap());
+
+    assert_eq!(Configuration::Chongqing.heartbeat_profile(), None);
+}
+
+#[test]
+fn test_netkeeper_username_decode() {
+    let dialer = NetkeeperDialer::load_from_config(Configuration::Zhejiang);
+    let decoded = dialer.decode_account("\r\n:R#(P 5005802278989@HYXY.XY").unwrap();
+    assert_eq!(decoded.username, "05802278989@HYXY.XY");
+    assert_eq!(decoded.pin, Some(":R#(P 50".to_string()));
+    assert_eq!(decoded.timestamp, Some(1472483020));
+    assert_eq!(decoded.configuration, None);
+
+    let verified = NetkeeperDialer::verify_account("\r\n:R#(P 5005802278989@HYXY.XY",
+                                                   &[Configuration::Chongqing,
+                                                     Configuration::Zhejiang])
+        .unwrap();
+    assert_eq!(verified.configuration, Some(Configuration::Zhejiang));
+
+    match NetkeeperDialer::verify_account("\r\n:R#(P 5005802278989@HYXY.XY",
+                                          &[Configuration::Chongqing]) {
+        Err(DecodeError::PinMismatch(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(dialer.decode_account("05802278989@HYXY.XY").is_err());
+    assert!(dialer.decode_account("\r\n:R#(P").is_err());
+}
+
+#[test]
+fn test_netkeeper_mock_server() {
+    use std::time::Duration;
+    use common::server::UdpServer;
+    use common::transport::{Transport, UdpTransport};
+    use netkeeper::server::NetkeeperServer;
+
+    let profile = HeartbeatProfile::Zhejiang;
+    let mut server = NetkeeperServer::new(profile).unwrap();
+    server.account("05802278989@HYXY.XY", "123456");
+    let server = UdpServer::spawn_local(server).unwrap();
+    let mut transport = UdpTransport::connect("127.0.0.1:0", server.local_addr()).unwrap();
+    transport.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut client = NetkeeperClient::from_profile(transport, profile).unwrap();
+
+    let ipaddress = Ipv4Addr::from_str("10.0.0.1").unwrap();
+    let account =
+        HeartbeatAccount::new("05802278989@HYXY.XY", "123456", ipaddress, "00:11:22:33:44:55");
+    let frame = profile.heartbeat_frame(&account, None);
+    match client.send_frame(profile.version(), profile.code(), frame).unwrap() {
+        ServerFrame::Accepted(FrameType::Heartbeat) => {}
+        other => panic!("unexpected reply {:?}", other),
+    }
+
+    let account =
+        HeartbeatAccount::new("05802278989@HYXY.XY", "654321", ipaddress, "00:11:22:33:44:55");
+    let frame = profile.heartbeat_frame(&account, None);
+    match client.send_frame(profile.version(), profile.code(), frame).unwrap() {
+        ServerFrame::Rejected(FrameType::Heartbeat, ref reason) => {
+            assert_eq!(reason, "wrong password")
+        }
+        other => panic!("unexpected reply {:?}", other),
+    }
+}
+
+#[test]
+fn test_netkeeper_session() {
+    use common::daemon::{KeepAliveDaemon, KeepAliveSession};
+    use common::server::Responder;
+    use error::Error;
+    use netkeeper::heartbeater::NetkeeperHeartbeatError;
+    use netkeeper::server::NetkeeperServer;
+    use netkeeper::session::NetkeeperSession;
+
+    let profile = HeartbeatProfile::Zhejiang;
+    let mut server = NetkeeperServer::new(profile).unwrap();
+    server.account("05802278989@HYXY.XY", "123456");
+    let transport = LoopbackTransport::new(move |bytes| server.respond(bytes));
+    let ipaddress = Ipv4Addr::from_str("10.0.0.1").unwrap();
+    let account =
+        HeartbeatAccount::new("05802278989@HYXY.XY", "123456", ipaddress, "00:11:22:33:44:55");
+    let mut daemon = KeepAliveDaemon::new(NetkeeperSession::new(transport, profile, account)
+        .unwrap());
+    assert_eq!(daemon.step().unwrap(), daemon.session().interval());
+    assert!(daemon.is_logged_in());
+    assert!(daemon.session_mut().take_notices().is_empty());
+
+    let transport = LoopbackTransport::new(move |bytes| {
+        let encrypter = profile.encrypter().unwrap();
+        let request = Packet::from_bytes(&mut BufReader::new(bytes), &encrypter, None).unwrap();
+        let reply = if request.frame().get("PASSWORD") == Some("123456") {
+            ServerFrame::Notice {
+                title: "maintenance".to_string(),
+                content: "tonight".to_string(),
+            }
+        } else {
+            ServerFrame::Rejected(FrameType::Heartbeat, "wrong password".to_string())
+        };
+        Packet::new(request.version(), request.code(), reply.as_frame())
+            .as_bytes(&encrypter)
+            .ok()
+    });
+    let account =
+        HeartbeatAccount::new("05802278989@HYXY.XY", "123456", ipaddress, "00:11:22:33:44:55");
+    let mut session = NetkeeperSession::new(transport, profile, account).unwrap();
+    session.keep_alive().unwrap();
+    assert_eq!(session.take_notices(),
+               vec![("maintenance".to_string(), "tonight".to_string())]);
+    assert!(session.take_notices().is_empty());
+
+    let mut server = NetkeeperServer::new(profile).unwrap();
+    server.account("05802278989@HYXY.XY", "123456");
+    let transport = LoopbackTransport::new(move |bytes| server.respond(bytes));
+    let account =
+        HeartbeatAccount::new("05802278989@HYXY.XY", "654321", ipaddress, "00:11:22:33:44:55");
+    let mut session = NetkeeperSession::new(transport, profile, account).unwrap();
+    match session.keep_alive() {
+        Err(Error::NetkeeperHeartbeat(NetkeeperHeartbeatError::HeartbeatRejected(ref reason))) => {
+            assert_eq!(reason, "wrong password")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[cfg(feature="async")]
+#[test]
+fn test_netkeeper_async_client() {
+    use std::time::Duration;
+    use futures::Future;
+    use tokio::runtime::current_thread::Runtime;
+    use common::async_transport::AsyncUdpTransport;
+    use common::server::UdpServer;
+    use netkeeper::async_client::AsyncNetkeeperClient;
+    use netkeeper::server::NetkeeperServer;
+
+    let profile = HeartbeatProfile::Zhejiang;
+    let mut server = NetkeeperServer::new(profile).unwrap();
+    server.account("05802278989@HYXY.XY", "123456");
+    let server = UdpServer::spawn_local(server).unwrap();
+    let local_addr = "127.0.0.1:0".parse().unwrap();
+    let transport = AsyncUdpTransport::connect(&local_addr, &server.local_addr()).unwrap();
+    let mut client = AsyncNetkeeperClient::from_profile(transport, profile).unwrap();
+    client.inner().timeout(Duration::from_secs(5));
+
+    let ipaddress = Ipv4Addr::from_str("10.0.0.1").unwrap();
+    let accepted =
+        HeartbeatAccount::new("05802278989@HYXY.XY", "123456", ipaddress, "00:11:22:33:44:55");
+    let rejected =
+        HeartbeatAccount::new("05802278989@HYXY.XY", "654321", ipaddress, "00:11:22:33:44:55");
+    let heartbeats = client.send_frame(profile.version(),
+                    profile.code(),
+                    profile.heartbeat_frame(&accepted, None))
+        .and_then(move |(client, accepted)| {
+            client.send_frame(profile.version(),
+                            profile.code(),
+                            profile.heartbeat_frame(&rejected, None))
+                .map(|(_, rejected)| (accepted, rejected))
+        });
+
+    let mut runtime = Runtime::new().unwrap();
+    match runtime.block_on(heartbeats).unwrap() {
+        (ServerFrame::Accepted(FrameType::Heartbeat),
+         ServerFrame::Rejected(FrameType::Heartbeat, ref reason)) => {
+            assert_eq!(reason, "wrong password")
+        }
+        other => panic!("unexpected replies {:?}", other),
+    }
+}
+
+#[cfg(feature="async")]
+#[test]
+fn test_netkeeper_packet_from_async_read() {
+    use std::io::Cursor;
+    use futures::Future;
+
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    let mut frame = Frame::new("HEARTBEAT", None);
+    frame.add("USER_NAME", "05802278989@HYXY.XY");
+    let packet = Packet::new(30 as u8, 0x0205, frame);
+    let mut stream = packet.as_bytes(&encrypter).unwrap();
+    stream.extend(packet.as_bytes(&encrypter).unwrap());
+
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    let length = stream.len();
+    let (rest, parsed) = Packet::from_async_read(Cursor::new(stream), encrypter, None)
+        .wait()
+        .unwrap();
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    assert_eq!(parsed.as_bytes(&encrypter).unwrap(),
+               packet.as_bytes(&encrypter).unwrap());
+    assert_eq!(rest.position() as usize, length / 2);
+}
+
+#[cfg(feature="async")]
+#[test]
+fn test_netkeeper_packet_codec() {
+    use std::io::Cursor;
+    use bytes::BytesMut;
+    use futures::{Future, Stream};
+    use tokio::codec::{Decoder, Encoder, FramedRead};
+    use netkeeper::heartbeater::PacketCodec;
+
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    let mut frame = Frame::new("HEARTBEAT", None);
+    frame.add("USER_NAME", "05802278989@HYXY.XY");
+    let bytes = Packet::new(30u8, 0x0205, frame).as_bytes(&encrypter).unwrap();
+
+    let mut codec = PacketCodec::new(encrypter, None);
+    let mut buffer = BytesMut::from(&bytes[..5]);
+    assert!(codec.decode(&mut buffer).unwrap().is_none());
+    buffer.extend_from_slice(&bytes[5..bytes.len() - 1]);
+    assert!(codec.decode(&mut buffer).unwrap().is_none());
+    buffer.extend_from_slice(&bytes[bytes.len() - 1..]);
+    let parsed = codec.decode(&mut buffer).unwrap().unwrap();
+    assert!(buffer.is_empty());
+    codec.encode(parsed, &mut buffer).unwrap();
+    assert_eq!(&buffer[..], &bytes[..]);
+
+    let mut stream = bytes.clone();
+    stream.extend(&bytes);
+    let encrypter = AES_128_ECB::new(b"xlzjhrprotocol3x").unwrap();
+    let packets = FramedRead::new(Cursor::new(stream), PacketCodec::new(encrypter, None))
+        .collect()
+        .wait()
+        .unwrap();
+    assert_eq!(packets.len(), 2);
+}
@@ -0,0 +1,441 @@
+//! pppd plugin which rewrites the PPPoE username with a dialer right before
+//! PAP or CHAP authentication. Build the shared library with
+//! `cargo rustc --release --features pppd --crate-type cdylib` and load it
+//! from the pppd options:
+//!
+//! ```text
+//! plugin /usr/lib/pppd/2.4.7/libnetkeeper.so
+//! netkeeper-algorithm netkeeper
+//! netkeeper-province zhejiang
+//! user 05802278989@HYXY.XY
+//! netkeeper-password 123456
+//! ```
+//!
+//! pppd only asks the plugin for the secret while its own `password` option
+//! is empty, so the password goes to `netkeeper-password` instead. The
+//! username is encrypted again for every authentication attempt, so the time
+//! based PIN stays fresh when pppd redials.
+//!
+//! The plugin is built for pppd 2.4.7, set `PPPD_VERSION` at build time to
+//! load it into another version.
+
+use std::{error, fmt, ptr, result};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_short, c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicPtr, Ordering};
+#[cfg(test)]
+use std::sync::Mutex;
+
+use error::describe;
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+use common::registry::ConfigurationRegistry;
+#[cfg(feature="srun3k")]
+use common::dialer::Dialer;
+#[cfg(feature="netkeeper")]
+use netkeeper::dialer::NetkeeperDialer;
+#[cfg(feature="singlenet")]
+use singlenet::dialer::SingleNetDialer;
+#[cfg(feature="ghca")]
+use ghca::dialer::{GhcaDialer, GhcaDialerError};
+#[cfg(feature="srun3k")]
+use srun3k::dialer::{Srun3kDialer, Configuration as Srun3kConfiguration};
+
+// MAXNAMELEN and MAXSECRETLEN of pppd.h
+const MAX_NAME_LENGTH: usize = 256;
+const MAX_SECRET_LENGTH: usize = 256;
+// o_string of enum opt_type
+const OPTION_TYPE_STRING: c_int = 5;
+
+// `pppd_version`, which must equal the version of the pppd loading the
+// plugin or pppd refuses to load it
+include!(concat!(env!("OUT_DIR"), "/pppd_version.rs"));
+
+#[derive(Debug)]
+pub enum PppdPluginError {
+    UnknownAlgorithm(String),
+    UnknownConfiguration(String),
+    // Encrypted username of {} bytes exceeds {}
+    UsernameTooLong(usize, usize),
+    PasswordMissing,
+    #[cfg(feature="ghca")]
+    Ghca(GhcaDialerError),
+}
+
+type PluginResult<T> = result::Result<T, PppdPluginError>;
+
+enum AccountDialer {
+    #[cfg(feature="netkeeper")]
+    Netkeeper(NetkeeperDialer),
+    #[cfg(feature="singlenet")]
+    SingleNet(SingleNetDialer),
+    #[cfg(feature="ghca")]
+    Ghca(GhcaDialer),
+    #[cfg(feature="srun3k")]
+    Srun3k(Srun3kDialer),
+}
+
+/// Encrypts the account of every authentication attempt.
+///
+/// pppd hands the rewritten username back on retries, so the account seen
+/// first is kept and encrypted again each time.
+pub struct AccountRewriter {
+    dialer: AccountDialer,
+    account: Option<(String, String)>,
+}
+
+/// `option_t` of pppd.h
+#[repr(C)]
+struct PppdOption {
+    name: *const c_char,
+    option_type: c_int,
+    addr: *mut c_void,
+    description: *const c_char,
+    flags: c_uint,
+    addr2: *mut c_void,
+    upper_limit: c_int,
+    lower_limit: c_int,
+    source: *const c_char,
+    priority: c_short,
+    winner: c_short,
+}
+
+/// State of the loaded plugin, leaked since pppd never unloads plugins.
+struct Plugin {
+    // set by the pppd option parser
+    algorithm: *mut c_char,
+    province: *mut c_char,
+    password: *mut c_char,
+    rewriter: Option<AccountRewriter>,
+    options: Vec<PppdOption>,
+}
+
+type PasswordHook = unsafe extern "C" fn(*mut c_char, *mut c_char) -> c_int;
+
+#[cfg(not(test))]
+extern "C" {
+    static mut pap_passwd_hook: Option<PasswordHook>;
+    static mut chap_passwd_hook: Option<PasswordHook>;
+
+    fn add_options(options: *mut PppdOption);
+    fn error(format: *const c_char, ...);
+}
+
+static PLUGIN: AtomicPtr<Plugin> = AtomicPtr::new(0 as *mut Plugin);
+
+impl fmt::Display for PppdPluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PppdPluginError::UnknownAlgorithm(ref name) => {
+                write!(f, "unknown algorithm {:?}", name)
+            }
+            PppdPluginError::UnknownConfiguration(ref name) => {
+                write!(f, "unknown configuration {:?}", name)
+            }
+            PppdPluginError::UsernameTooLong(length, maximum) => {
+                write!(f, "encrypted username of {} bytes exceeds {}", length, maximum)
+            }
+            PppdPluginError::PasswordMissing => write!(f, "netkeeper-password is not set"),
+            #[cfg(feature="ghca")]
+            PppdPluginError::Ghca(_) => write!(f, "encrypt account failed"),
+        }
+    }
+}
+
+impl error::Error for PppdPluginError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            #[cfg(feature="ghca")]
+            PppdPluginError::Ghca(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl AccountRewriter {
+    /// `algorithm` is one of `netkeeper`, `singlenet`, `ghca` or `srun3k`,
+    /// `configuration` defaults to the one the command line tool uses.
+    pub fn new(algorithm: &str, configuration: Option<&str>) -> PluginResult<Self> {
+        let dialer = match algorithm {
+            #[cfg(feature="netkeeper")]
+            "netkeeper" => {
+                AccountDialer::Netkeeper(try!(load_dialer(configuration.unwrap_or("zhejiang"))))
+            }
+            #[cfg(feature="singlenet")]
+            "singlenet" => {
+                AccountDialer::SingleNet(try!(load_dialer(configuration.unwrap_or("hainan"))))
+            }
+            #[cfg(feature="ghca")]
+            "ghca" => {
+                AccountDialer::Ghca(try!(load_dialer(configuration.unwrap_or("sichuan_mac"))))
+            }
+            #[cfg(feature="srun3k")]
+            "srun3k" => {
+                match configuration.unwrap_or("talimu") {
+                    "talimu" => {
+                        let dialer = Srun3kDialer::load_from_config(Srun3kConfiguration::TaLiMu);
+                        AccountDialer::Srun3k(dialer)
+                    }
+                    name => return Err(PppdPluginError::UnknownConfiguration(name.to_string())),
+                }
+            }
+            _ => return Err(PppdPluginError::UnknownAlgorithm(algorithm.to_string())),
+        };
+        Ok(AccountRewriter {
+            dialer: dialer,
+            account: None,
+        })
+    }
+
+    /// The username and password to authenticate with at `timestamp`, which
+    /// defaults to the current time.
+    pub fn rewrite(&mut self,
+                   username: &str,
+                   password: &str,
+                   timestamp: Option<u32>)
+                   -> PluginResult<(String, String)> {
+        if self.account.is_none() {
+            self.account = Some((username.to_string(), password.to_string()));
+        }
+        let (ref username, ref password) = *self.account.as_ref().unwrap();
+
+        let encrypted = match self.dialer {
+            #[cfg(feature="netkeeper")]
+            AccountDialer::Netkeeper(ref dialer) => {
+                dialer.encrypt_account(username, timestamp)
+            }
+            #[cfg(feature="singlenet")]
+            AccountDialer::SingleNet(ref dialer) => {
+                dialer.encrypt_account(username, timestamp)
+            }
+            #[cfg(feature="ghca")]
+            AccountDialer::Ghca(ref dialer) => {
+                try!(dialer.encrypt_account(username, password, timestamp, timestamp)
+                    .map_err(PppdPluginError::Ghca))
+            }
+            #[cfg(feature="srun3k")]
+            AccountDialer::Srun3k(ref dialer) => {
+                // v20 usernames carry no timestamp
+                let _ = timestamp;
+                dialer.encrypt_account_v20(username)
+            }
+        };
+        if encrypted.len() >= MAX_NAME_LENGTH {
+            return Err(PppdPluginError::UsernameTooLong(encrypted.len(), MAX_NAME_LENGTH - 1));
+        }
+        Ok((encrypted, password.clone()))
+    }
+}
+
+impl Plugin {
+    fn options(&mut self) -> Vec<PppdOption> {
+        vec![string_option(b"netkeeper-algorithm\0",
+                           &mut self.algorithm,
+                           b"Dialer of the username: netkeeper, singlenet, ghca or srun3k\0"),
+             string_option(b"netkeeper-province\0",
+                           &mut self.province,
+                           b"Dialer configuration, such as zhejiang\0"),
+             string_option(b"netkeeper-password\0",
+                           &mut self.password,
+                           b"Password to authenticate with\0"),
+             // pppd stops at the option without a name
+             PppdOption { name: ptr::null(), ..string_option(b"\0", ptr::null_mut(), b"\0") }]
+    }
+
+    unsafe fn rewriter(&mut self) -> PluginResult<&mut AccountRewriter> {
+        if self.rewriter.is_none() {
+            let algorithm = option_value(self.algorithm).unwrap_or("netkeeper");
+            self.rewriter = Some(try!(AccountRewriter::new(algorithm,
+                                                           option_value(self.province))));
+        }
+        Ok(self.rewriter.as_mut().unwrap())
+    }
+
+    /// Write the encrypted username to `user` and the password to `password`.
+    unsafe fn rewrite(&mut self, user: *mut c_char, password: *mut c_char) -> PluginResult<()> {
+        let username = CStr::from_ptr(user).to_string_lossy().into_owned();
+        let secret = try!(option_value(self.password).ok_or(PppdPluginError::PasswordMissing));
+        let (username, secret) = try!(try!(self.rewriter()).rewrite(&username, secret, None));
+
+        write_c_string(&username, user, MAX_NAME_LENGTH);
+        write_c_string(&secret, password, MAX_SECRET_LENGTH);
+        Ok(())
+    }
+}
+
+/// Entry point called by pppd after loading the plugin.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_init() {
+    let plugin = Box::into_raw(Box::new(Plugin {
+        algorithm: ptr::null_mut(),
+        province: ptr::null_mut(),
+        password: ptr::null_mut(),
+        rewriter: None,
+        options: Vec::new(),
+    }));
+    (*plugin).options = (*plugin).options();
+    PLUGIN.store(plugin, Ordering::SeqCst);
+
+    add_options((*plugin).options.as_mut_ptr());
+    pap_passwd_hook = Some(password_hook);
+    chap_passwd_hook = Some(password_hook);
+}
+
+/// `pap_passwd_hook` and `chap_passwd_hook`, pppd probes with a null
+/// `password` whether the hook can supply a secret.
+unsafe extern "C" fn password_hook(user: *mut c_char, password: *mut c_char) -> c_int {
+    if password.is_null() {
+        return 1;
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        match PLUGIN.load(Ordering::SeqCst).as_mut() {
+            Some(plugin) => plugin.rewrite(user, password).map_err(|e| describe(&e)),
+            None => Err("plugin is not initialized".to_string()),
+        }
+    }));
+    let message = match result {
+        Ok(Ok(())) => return 1,
+        Ok(Err(message)) => message,
+        Err(_) => "rewrite username panicked".to_string(),
+    };
+    // fall back to the secrets files of pppd
+    log_error(&message);
+    -1
+}
+
+fn string_option(name: &'static [u8],
+                 value: *mut *mut c_char,
+                 description: &'static [u8])
+                 -> PppdOption {
+    PppdOption {
+        name: name.as_ptr() as *const c_char,
+        option_type: OPTION_TYPE_STRING,
+        addr: value as *mut c_void,
+        description: description.as_ptr() as *const c_char,
+        flags: 0,
+        addr2: ptr::null_mut(),
+        upper_limit: 0,
+        lower_limit: 0,
+        source: ptr::null(),
+        priority: 0,
+        winner: 0,
+    }
+}
+
+unsafe fn option_value<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+    CStr::from_ptr(value).to_str().ok()
+}
+
+/// `value` must be shorter than `capacity`, the regions may overlap.
+unsafe fn write_c_string(value: &str, output: *mut c_char, capacity: usize) {
+    let length = value.len().min(capacity - 1);
+    ptr::copy(value.as_ptr() as *const c_char, output, length);
+    *output.offset(length as isize) = 0;
+}
+
+#[cfg(any(feature="netkeeper", feature="singlenet", feature="ghca"))]
+fn load_dialer<D>(name: &str) -> PluginResult<D>
+    where D: ::common::dialer::ConfigurableDialer
+{
+    ConfigurationRegistry::new()
+        .load_dialer(name)
+        .ok_or_else(|| PppdPluginError::UnknownConfiguration(name.to_string()))
+}
+
+#[cfg(not(test))]
+fn log_error(message: &str) {
+    use std::ffi::CString;
+
+    let message = CString::new(format!("netkeeper: {}", message).replace('\0', ""))
+        .unwrap_or_default();
+    unsafe {
+        error(b"%s\0".as_ptr() as *const c_char, message.as_ptr());
+    }
+}
+
+// the symbols pppd provides to its plugins
+#[cfg(test)]
+#[allow(non_upper_case_globals)]
+static mut pap_passwd_hook: Option<PasswordHook> = None;
+#[cfg(test)]
+#[allow(non_upper_case_globals)]
+static mut chap_passwd_hook: Option<PasswordHook> = None;
+#[cfg(test)]
+static ADDED_OPTIONS: AtomicPtr<PppdOption> = AtomicPtr::new(0 as *mut PppdOption);
+
+#[cfg(test)]
+unsafe fn add_options(options: *mut PppdOption) {
+    ADDED_OPTIONS.store(options, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+static LOGGED_ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[cfg(test)]
+fn log_error(message: &str) {
+    LOGGED_ERRORS.lock().unwrap().push(format!("netkeeper: {}", message));
+}
+
+#[cfg(feature="netkeeper")]
+#[test]
+fn test_account_rewriter() {
+    let mut rewriter = AccountRewriter::new("netkeeper", None).unwrap();
+    let (username, password) = rewriter.rewrite("05802278989@HYXY.XY", "123456", Some(1472483020))
+        .unwrap();
+    assert_eq!(username, "\r\n:R#(P 5005802278989@HYXY.XY");
+    assert_eq!(password, "123456");
+
+    // pppd passes the rewritten username on retries
+    let (retried, _) = rewriter.rewrite(&username, "", Some(1472483020 + 60)).unwrap();
+    assert!(retried.ends_with("05802278989@HYXY.XY"));
+    assert!(retried != username);
+
+    match AccountRewriter::new("netkeeper", Some("atlantis")) {
+        Err(PppdPluginError::UnknownConfiguration(ref name)) => assert_eq!(name, "atlantis"),
+        _ => panic!("expect an unknown configuration"),
+    }
+    assert!(AccountRewriter::new("telnet", None).is_err());
+}
+
+#[cfg(feature="singlenet")]
+#[test]
+fn test_plugin_hooks() {
+    use std::ffi::CString;
+
+    unsafe {
+        plugin_init();
+        let options = ADDED_OPTIONS.load(Ordering::SeqCst);
+        assert_eq!(CStr::from_ptr((*options).name).to_str().unwrap(), "netkeeper-algorithm");
+        assert!((*options.offset(3)).name.is_null());
+
+        // what the pppd option parser does
+        *((*options).addr as *mut *mut c_char) = CString::new("singlenet").unwrap().into_raw();
+
+        let hook = pap_passwd_hook.unwrap();
+        assert_eq!(hook(ptr::null_mut(), ptr::null_mut()), 1);
+        let mut user = [0 as c_char; MAX_NAME_LENGTH];
+        let mut password = [0 as c_char; MAX_SECRET_LENGTH];
+        write_c_string("05802278989@HYXY.XY", user.as_mut_ptr(), MAX_NAME_LENGTH);
+
+        // pppd calls the hook with an empty passwd, without netkeeper-password
+        // it falls back to the secrets files
+        assert_eq!(hook(user.as_mut_ptr(), password.as_mut_ptr()), -1);
+        assert_eq!(CStr::from_ptr(user.as_ptr()).to_str().unwrap(), "05802278989@HYXY.XY");
+        assert_eq!(password[0], 0);
+        assert_eq!(*LOGGED_ERRORS.lock().unwrap(),
+                   vec!["netkeeper: netkeeper-password is not set".to_string()]);
+
+        let option = options.offset(2);
+        assert_eq!(CStr::from_ptr((*option).name).to_str().unwrap(), "netkeeper-password");
+        *((*option).addr as *mut *mut c_char) = CString::new("123456").unwrap().into_raw();
+        assert_eq!(hook(user.as_mut_ptr(), password.as_mut_ptr()), 1);
+
+        let username = CStr::from_ptr(user.as_ptr()).to_str().unwrap();
+        assert!(username.starts_with('~'));
+        assert!(username.ends_with("_05802278989@HYXY.XY"));
+        assert_eq!(CStr::from_ptr(password.as_ptr()).to_str().unwrap(), "123456");
+    }
+}
@@ -0,0 +1,48 @@
+use std::io;
+
+use futures::Future;
+
+use common::async_transport::{AsyncTransport, AsyncTransportClient, BoxFuture};
+use singlenet::heartbeater::{Packet, PacketAuthenticator, SinglenetHeartbeatError};
+use error::Error;
+
+/// Non-blocking `SingleNetClient`, `request` hands the client back with the
+/// response packet.
+pub struct AsyncSingleNetClient<T: AsyncTransport> {
+    inner: AsyncTransportClient<T>,
+    authenticator: PacketAuthenticator,
+}
+
+impl<T> AsyncSingleNetClient<T>
+    where T: AsyncTransport + Send + 'static
+{
+    pub fn new(transport: T, authenticator: PacketAuthenticator) -> Self {
+        AsyncSingleNetClient {
+            inner: AsyncTransportClient::new(transport),
+            authenticator: authenticator,
+        }
+    }
+
+    pub fn inner(&mut self) -> &mut AsyncTransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn request(self, packet: &Packet) -> BoxFuture<(Self, Packet)> {
+        let AsyncSingleNetClient { inner, authenticator } = self;
+        let request = packet.as_bytes(Some(&authenticator));
+        Box::new(inner.exchange(request)
+            .and_then(move |(inner, response)| {
+                if !authenticator.verify(&response) {
+                    let error = SinglenetHeartbeatError::AuthorizationMismatch;
+                    return Err(Error::SinglenetHeartbeat(error));
+                }
+                let mut buffer = io::BufReader::new(&response as &[u8]);
+                let response = try!(Packet::from_bytes(&mut buffer));
+                let client = AsyncSingleNetClient {
+                    inner: inner,
+                    authenticator: authenticator,
+                };
+                Ok((client, response))
+            }))
+    }
+}
@@ -0,0 +1,86 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use futures::Future;
+
+use common::async_transport::{AsyncTransport, BoxFuture};
+use common::clock::Clock;
+use singlenet::async_client::AsyncSingleNetClient;
+use singlenet::heartbeater::{ClientFingerprint, PacketAuthenticator};
+use singlenet::models::Update;
+use singlenet::session::SessionContext;
+
+/// Async counterpart of [`SingleNetSession`].
+pub struct AsyncSingleNetSession<T: AsyncTransport> {
+    client: AsyncSingleNetClient<T>,
+    context: SessionContext,
+}
+
+impl<T> AsyncSingleNetSession<T>
+    where T: AsyncTransport + Send + 'static
+{
+    pub fn new(transport: T,
+               authenticator: PacketAuthenticator,
+               username: &str,
+               ipaddress: Ipv4Addr,
+               fingerprint: ClientFingerprint)
+               -> Self {
+        AsyncSingleNetSession {
+            client: AsyncSingleNetClient::new(transport, authenticator),
+            context: SessionContext::new(username, ipaddress, fingerprint),
+        }
+    }
+
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.context.clock(clock);
+        self
+    }
+
+    pub fn client(&mut self) -> &mut AsyncSingleNetClient<T> {
+        &mut self.client
+    }
+
+    pub fn last_keepalive_data(&self) -> Option<&str> {
+        self.context.last_keepalive_data()
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.context.interval()
+    }
+
+    pub fn update(&self) -> Option<&Update> {
+        self.context.update()
+    }
+
+    /// See [`SessionContext::reset`].
+    pub fn reset(&mut self) {
+        self.context.reset();
+    }
+
+    pub fn register(self) -> BoxFuture<Self> {
+        let AsyncSingleNetSession { client, mut context } = self;
+        let packet = context.register_request();
+        Box::new(client.request(&packet).and_then(move |(client, response)| {
+            try!(context.registered(&response));
+            Ok(Self::from_parts(client, context))
+        }))
+    }
+
+    pub fn keep_alive(self, timestamp: Option<u32>) -> BoxFuture<Self> {
+        let AsyncSingleNetSession { client, mut context } = self;
+        let (packet, keepalive_data) = context.keepalive_request(timestamp);
+        Box::new(client.request(&packet).and_then(move |(client, response)| {
+            try!(context.kept_alive(&response, keepalive_data));
+            Ok(Self::from_parts(client, context))
+        }))
+    }
+
+    fn from_parts(client: AsyncSingleNetClient<T>, context: SessionContext) -> Self {
+        AsyncSingleNetSession {
+            client: client,
+            context: context,
+        }
+    }
+}
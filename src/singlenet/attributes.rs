@@ -1,23 +1,39 @@
 #![allow(match_same_arms)]
-use std::{str, result};
+use std::{str, result, error, fmt};
 use std::net::Ipv4Addr;
 
 use rustc_serialize::hex::ToHex;
 use byteorder::{NetworkEndian, ByteOrder};
 
 use crypto::hash::{HasherBuilder, HasherType};
-use common::utils::current_timestamp;
+use common::clock::{Clock, FixedClock};
 use common::bytes::{BytesAble, BytesAbleNum};
 
 #[derive(Debug)]
 pub enum ParseAttributesError {
     // Expect length {}, got {}
     UnexpectDataLength(usize, usize),
+    UnknownValueType(u8),
 }
 
+impl fmt::Display for ParseAttributesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseAttributesError::UnexpectDataLength(expect, got) => {
+                write!(f, "expect length {}, got {}", expect, got)
+            }
+            ParseAttributesError::UnknownValueType(value_type_id) => {
+                write!(f, "unknown value type {:#x}", value_type_id)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseAttributesError {}
+
 type AttributeResult<T> = result::Result<T, ParseAttributesError>;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum AttributeValueType {
     TInteger = 0x0,
     TIPAddress = 0x1, // or Integer array?
@@ -25,7 +41,7 @@ pub enum AttributeValueType {
     TGroup = 0x3, // Attributes group
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum AttributeType {
     TAttribute,
     TUserName,
@@ -76,7 +92,7 @@ pub enum AttributeType {
     TWifiRedirectURL,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Attribute {
     name: String,
     parent_id: u8,
@@ -85,6 +101,15 @@ pub struct Attribute {
     data: Vec<u8>,
 }
 
+/// Decoded data of an attribute, groups hold their child attributes.
+#[derive(Debug, PartialEq)]
+pub enum AttributeContent {
+    Integer(u32),
+    IPAddress(Ipv4Addr),
+    String(String),
+    Group(Vec<Attribute>),
+}
+
 pub struct KeepaliveDataCalculator;
 
 pub trait AttributeValue: BytesAble {}
@@ -94,8 +119,59 @@ pub trait AttributeVec {
     fn length(&self) -> u16;
 
     fn from_bytes(bytes: &[u8]) -> AttributeResult<Vec<Attribute>>;
+
+    fn find(&self, attribute_type: AttributeType) -> Option<&Attribute>;
+    fn find_all(&self, attribute_type: AttributeType) -> Vec<&Attribute>;
 }
 
+const ATTRIBUTE_TYPES: [AttributeType; 46] =
+    [AttributeType::TUserName,
+     AttributeType::TClientIPAddress,
+     AttributeType::TClientVersion,
+     AttributeType::TClientType,
+     AttributeType::TOSVersion,
+     AttributeType::TOSLang,
+     AttributeType::TAdapterInfo,
+     AttributeType::TCPUInfo,
+     AttributeType::TMACAddress,
+     AttributeType::TMemorySize,
+     AttributeType::TDefaultExplorer,
+     AttributeType::TBubble,
+     AttributeType::TBubbleId,
+     AttributeType::TBubbleTitle,
+     AttributeType::TBubbleContext,
+     AttributeType::TBubbleContextURL,
+     AttributeType::TBubbleKeepTime,
+     AttributeType::TBubbleDelayTime,
+     AttributeType::TBubbleType,
+     AttributeType::TChannel,
+     AttributeType::TChannelNote,
+     AttributeType::TChannelContextURL,
+     AttributeType::TChannelContextURL2,
+     AttributeType::TChannelOrder,
+     AttributeType::TPlugin,
+     AttributeType::TPluginName,
+     AttributeType::TPluginConfigureData,
+     AttributeType::TUpdateVersion,
+     AttributeType::TUpdateDownloadURL,
+     AttributeType::TUpdateDescription,
+     AttributeType::TKeepAliveTime,
+     AttributeType::TKeepAliveInterval,
+     AttributeType::TKeepAliveData,
+     AttributeType::TProcessCheckInterval,
+     AttributeType::TProcessCheckList,
+     AttributeType::TRealTimeBubbleServer,
+     AttributeType::TRealTimeBubbleInterval,
+     AttributeType::TWifiTransmitIPList,
+     AttributeType::TWifiShareNumber,
+     AttributeType::TWifiShareCode,
+     AttributeType::TWifiShareErrorString,
+     AttributeType::TWifiShareBindRequired,
+     AttributeType::TPlugin2,
+     AttributeType::TDeviceSN,
+     AttributeType::TDeviceType,
+     AttributeType::TWifiRedirectURL];
+
 impl Attribute {
     pub fn new(name: &str,
                parent_id: u8,
@@ -122,10 +198,81 @@ impl Attribute {
                   value.as_bytes().to_vec())
     }
 
+    pub fn group(attribute_type: AttributeType, attributes: Vec<Attribute>) -> Self {
+        Self::new(attribute_type.name(),
+                  attribute_type.parent().id(),
+                  attribute_type.id(),
+                  AttributeValueType::TGroup as u8,
+                  attributes.as_bytes())
+    }
+
     fn header_length() -> u16 {
         3u16
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent_id(&self) -> u8 {
+        self.parent_id
+    }
+
+    pub fn attribute_id(&self) -> u8 {
+        self.attribute_id
+    }
+
+    pub fn value_type_id(&self) -> u8 {
+        self.value_type_id
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn attribute_type(&self) -> Option<AttributeType> {
+        AttributeType::from_id(self.parent_id, self.attribute_id)
+    }
+
+    pub fn is(&self, attribute_type: AttributeType) -> bool {
+        self.parent_id == attribute_type.parent().id() && self.attribute_id == attribute_type.id()
+    }
+
+    /// Decode `data` as told by `value_type_id`. Strings sent by the servers
+    /// are not always UTF-8, invalid sequences are replaced.
+    pub fn value(&self) -> AttributeResult<AttributeContent> {
+        let value_type = match AttributeValueType::from_u8(self.value_type_id) {
+            Some(value_type) => value_type,
+            None => return Err(ParseAttributesError::UnknownValueType(self.value_type_id)),
+        };
+        match value_type {
+            AttributeValueType::TInteger => {
+                try!(self.expect_data_length(4));
+                Ok(AttributeContent::Integer(NetworkEndian::read_u32(&self.data)))
+            }
+            AttributeValueType::TIPAddress => {
+                try!(self.expect_data_length(4));
+                Ok(AttributeContent::IPAddress(Ipv4Addr::new(self.data[0],
+                                                           self.data[1],
+                                                           self.data[2],
+                                                           self.data[3])))
+            }
+            AttributeValueType::TString => {
+                Ok(AttributeContent::String(String::from_utf8_lossy(&self.data).into_owned()))
+            }
+            AttributeValueType::TGroup => {
+                Ok(AttributeContent::Group(try!(parse_attributes(&self.data, self.attribute_id))))
+            }
+        }
+    }
+
+    fn expect_data_length(&self, length: usize) -> AttributeResult<()> {
+        if self.data.len() != length {
+            return Err(ParseAttributesError::UnexpectDataLength(length, self.data.len()));
+        }
+        Ok(())
+    }
+
     pub fn length(&self) -> u16 {
         self.data_length() + Self::header_length()
     }
@@ -148,7 +295,11 @@ impl Attribute {
 
 impl KeepaliveDataCalculator {
     pub fn calculate(timestamp: Option<u32>, last_data: Option<&str>) -> String {
-        let timenow = timestamp.unwrap_or_else(current_timestamp);
+        Self::calculate_with_clock(&FixedClock::from_timestamp(timestamp), last_data)
+    }
+
+    pub fn calculate_with_clock(clock: &Clock, last_data: Option<&str>) -> String {
+        let timenow = clock.now();
         let salt = last_data.unwrap_or("llwl");
 
         let keepalive_data;
@@ -164,7 +315,59 @@ impl KeepaliveDataCalculator {
     }
 }
 
+impl AttributeContent {
+    pub fn as_integer(&self) -> Option<u32> {
+        match *self {
+            AttributeContent::Integer(integer) => Some(integer),
+            _ => None,
+        }
+    }
+
+    pub fn as_ipaddress(&self) -> Option<Ipv4Addr> {
+        match *self {
+            AttributeContent::IPAddress(ipaddress) => Some(ipaddress),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            AttributeContent::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_group(&self) -> Option<&Vec<Attribute>> {
+        match *self {
+            AttributeContent::Group(ref attributes) => Some(attributes),
+            _ => None,
+        }
+    }
+}
+
+impl AttributeValueType {
+    pub fn from_u8(value_type_id: u8) -> Option<Self> {
+        match value_type_id {
+            0x0 => Some(AttributeValueType::TInteger),
+            0x1 => Some(AttributeValueType::TIPAddress),
+            0x2 => Some(AttributeValueType::TString),
+            0x3 => Some(AttributeValueType::TGroup),
+            _ => None,
+        }
+    }
+}
+
 impl AttributeType {
+    /// The attribute type identified by `attribute_id` inside the group
+    /// `parent_id`, top level attributes have a `parent_id` of 0.
+    pub fn from_id(parent_id: u8, attribute_id: u8) -> Option<Self> {
+        ATTRIBUTE_TYPES.iter()
+            .find(|attribute_type| {
+                attribute_type.parent().id() == parent_id && attribute_type.id() == attribute_id
+            })
+            .cloned()
+    }
+
     pub fn name(&self) -> &'static str {
         match *self {
             AttributeType::TUserName => "User-Name",
@@ -336,34 +539,62 @@ impl AttributeVec for Vec<Attribute> {
         self.iter().fold(0, |sum, attr| sum + attr.length()) as u16
     }
 
-    /// Now only support parse from `AttributeType::TAttribute`'s attributes,
-    /// `parent_id` and `value_type_id` will be missed.
     fn from_bytes(bytes: &[u8]) -> AttributeResult<Vec<Attribute>> {
-        let mut index = 0;
-        let mut attributes: Vec<Attribute> = Vec::new();
-        let header_length = Attribute::header_length() as usize;
-        loop {
-            let cursor = &bytes[index..];
-            let bytes_length = cursor.len() as usize;
-            if bytes_length == 0 {
-                return Ok(attributes);
-            }
-            if bytes_length < header_length {
-                return Err(ParseAttributesError::UnexpectDataLength(header_length, bytes_length));
-            }
-            let attribute_id = cursor[0];
+        parse_attributes(bytes, AttributeType::TAttribute.id())
+    }
 
-            let data_length = NetworkEndian::read_u16(&cursor[1..header_length]) as usize;
-            index += data_length;
+    fn find(&self, attribute_type: AttributeType) -> Option<&Attribute> {
+        self.iter().find(|attr| attr.is(attribute_type))
+    }
 
-            if data_length > bytes_length {
-                return Err(ParseAttributesError::UnexpectDataLength(data_length, bytes_length));
-            }
+    fn find_all(&self, attribute_type: AttributeType) -> Vec<&Attribute> {
+        self.iter().filter(|attr| attr.is(attribute_type)).collect()
+    }
+}
 
-            let mut data: Vec<u8> = Vec::new();
-            data.extend_from_slice(&cursor[header_length..data_length]);
-            attributes.push(Attribute::new("", 0u8, attribute_id, 0u8, data))
+/// Parse the attributes of the group `parent_id`, names and value types are
+/// looked up from the known attribute types, unknown ones are kept as strings.
+fn parse_attributes(bytes: &[u8], parent_id: u8) -> AttributeResult<Vec<Attribute>> {
+    let mut index = 0;
+    let mut attributes: Vec<Attribute> = Vec::new();
+    let header_length = Attribute::header_length() as usize;
+    loop {
+        let cursor = &bytes[index..];
+        let bytes_length = cursor.len() as usize;
+        if bytes_length == 0 {
+            return Ok(attributes);
         }
+        if bytes_length < header_length {
+            return Err(ParseAttributesError::UnexpectDataLength(header_length, bytes_length));
+        }
+        let attribute_id = cursor[0];
+
+        let data_length = NetworkEndian::read_u16(&cursor[1..header_length]) as usize;
+        index += data_length;
+
+        if data_length > bytes_length || data_length < header_length {
+            return Err(ParseAttributesError::UnexpectDataLength(data_length, bytes_length));
+        }
+
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(&cursor[header_length..data_length]);
+        let attribute = match AttributeType::from_id(parent_id, attribute_id) {
+            Some(attribute_type) => {
+                Attribute::new(attribute_type.name(),
+                               parent_id,
+                               attribute_id,
+                               attribute_type.value_type() as u8,
+                               data)
+            }
+            None => {
+                Attribute::new("",
+                               parent_id,
+                               attribute_id,
+                               AttributeValueType::TString as u8,
+                               data)
+            }
+        };
+        attributes.push(attribute)
     }
 }
 
@@ -390,6 +621,44 @@ fn test_attributes_parse_bytes() {
     assert_eq!(attributes.as_bytes(), assert_data);
 }
 
+#[test]
+fn test_attributes_decode_values() {
+    let bubble = Attribute::group(AttributeType::TBubble,
+                                  vec![Attribute::from_type(AttributeType::TBubbleId, &7u32),
+                                       Attribute::from_type(AttributeType::TBubbleTitle,
+                                                            &"title".to_string())]);
+    let attributes = vec![Attribute::from_type(AttributeType::TClientIPAddress,
+                                               &Ipv4Addr::new(10, 0, 0, 1)),
+                          Attribute::from_type(AttributeType::TKeepAliveInterval, &60u32),
+                          bubble];
+    let parsed = Vec::<Attribute>::from_bytes(&attributes.as_bytes()).unwrap();
+    assert_eq!(parsed, attributes);
+
+    let ipaddress = parsed.find(AttributeType::TClientIPAddress).unwrap().value().unwrap();
+    assert_eq!(ipaddress.as_ipaddress(), Some(Ipv4Addr::new(10, 0, 0, 1)));
+    let interval = parsed.find(AttributeType::TKeepAliveInterval).unwrap().value().unwrap();
+    assert_eq!(interval, AttributeContent::Integer(60));
+    assert!(parsed.find(AttributeType::TBubbleTitle).is_none());
+
+    let bubbles = parsed.find_all(AttributeType::TBubble);
+    assert_eq!(bubbles.len(), 1);
+    let bubble = bubbles[0].value().unwrap();
+    let children = bubble.as_group().unwrap();
+    assert_eq!(children[1].name(), "Bubble-Title");
+    assert_eq!(children[1].parent_id(), AttributeType::TBubble.id());
+    assert_eq!(children.find(AttributeType::TBubbleId).unwrap().value().unwrap().as_integer(),
+               Some(7));
+    assert_eq!(children.find(AttributeType::TBubbleTitle).unwrap().value().unwrap().as_str(),
+               Some("title"));
+
+    let unknown = Vec::<Attribute>::from_bytes(&[0xfe, 0, 5, 104, 105]).unwrap();
+    assert_eq!(unknown[0].attribute_type(), None);
+    assert_eq!(unknown[0].value().unwrap().as_str(), Some("hi"));
+    let truncated = Attribute::new("", 0, 0x13, AttributeValueType::TInteger as u8, vec![0, 60]);
+    assert!(truncated.value().is_err());
+    assert!(Vec::<Attribute>::from_bytes(&[0x13, 0, 1]).is_err());
+}
+
 #[test]
 fn test_keepalive_data() {
     let kp_data1 = KeepaliveDataCalculator::calculate(Some(1472483020), None);
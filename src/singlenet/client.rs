@@ -0,0 +1,35 @@
+use std::io;
+
+use common::transport::{Transport, TransportClient};
+use singlenet::heartbeater::{Packet, PacketAuthenticator, SinglenetHeartbeatError};
+use error::{Error, Result};
+
+/// Signs the requests and rejects responses signed with another secret.
+pub struct SingleNetClient<T: Transport> {
+    inner: TransportClient<T>,
+    authenticator: PacketAuthenticator,
+}
+
+impl<T> SingleNetClient<T>
+    where T: Transport
+{
+    pub fn new(transport: T, authenticator: PacketAuthenticator) -> Self {
+        SingleNetClient {
+            inner: TransportClient::new(transport),
+            authenticator: authenticator,
+        }
+    }
+
+    pub fn inner(&mut self) -> &mut TransportClient<T> {
+        &mut self.inner
+    }
+
+    pub fn request(&mut self, packet: &Packet) -> Result<Packet> {
+        let response = try!(self.inner.exchange(&packet.as_bytes(Some(&self.authenticator))));
+        if !self.authenticator.verify(&response) {
+            return Err(Error::SinglenetHeartbeat(SinglenetHeartbeatError::AuthorizationMismatch));
+        }
+        let mut buffer = io::BufReader::new(&response as &[u8]);
+        Ok(try!(Packet::from_bytes(&mut buffer)))
+    }
+}
@@ -3,10 +3,11 @@ use std::slice;
 
 use byteorder::{NetworkEndian, NativeEndian, ByteOrder};
 
-use common::dialer::Dialer;
-use common::utils::current_timestamp;
+use common::dialer::{Dialer, ConfigurableDialer, DialerParameters, DecodedAccount, DecodeError,
+                     DecodeResult};
+use common::clock::{Clock, SystemClock};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Configuration {
     Hainan,
 }
@@ -16,6 +17,7 @@ pub struct SingleNetDialer {
     share_key: String,
     secret_key: String,
     key_table: String,
+    clock: Box<Clock>,
 }
 
 impl SingleNetDialer {
@@ -24,12 +26,21 @@ impl SingleNetDialer {
             share_key: share_key.to_string(),
             secret_key: secret_key.to_string(),
             key_table: key_table.to_string(),
+            clock: Box::new(SystemClock),
         }
     }
 
+    /// Replace the `SystemClock` telling the time of encrypted usernames.
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
     pub fn encrypt_account(&self, username: &str, timestamp: Option<u32>) -> String {
         let username = username.to_uppercase();
-        let timenow = timestamp.unwrap_or_else(current_timestamp);
+        let timenow = timestamp.unwrap_or_else(|| self.clock.now());
 
         let first_hash: u16;
         {
@@ -90,6 +101,63 @@ impl SingleNetDialer {
         format!("~LL_{}_{}", pin_str, username)
     }
 
+    /// Recover the username, PIN and timestamp without verifying the PIN.
+    pub fn decode_account(&self,
+                          encrypted: &str)
+                          -> DecodeResult<DecodedAccount<Configuration>> {
+        if !encrypted.starts_with("~LL_") {
+            return Err(DecodeError::UnexpectedPrefix(encrypted.to_string()));
+        }
+        let (pin, username) = match (encrypted.get(4..16), encrypted.get(16..)) {
+            (Some(pin), Some(username)) if username.len() > 1 && username.starts_with('_') => {
+                (pin, &username[1..])
+            }
+            _ => return Err(DecodeError::InvalidFormat(encrypted.to_string())),
+        };
+
+        let key_table_bytes = self.key_table_bytes();
+        let mut vectors: [u8; 12] = [0; 12];
+        for (vector, code) in vectors.iter_mut().zip(pin.bytes()) {
+            match key_table_bytes.iter().position(|c| *c == code) {
+                Some(index) => *vector = index as u8,
+                None => return Err(DecodeError::InvalidFormat(encrypted.to_string())),
+            }
+        }
+
+        let mut scheduled_table: [u8; 8] = [0; 8];
+        for i in 0..4 {
+            let j = 2 * i + 1;
+            let k = 3 * i + 1;
+            scheduled_table[j - 1] = (vectors[k - 1] << 0x3) | (vectors[k] >> 0x2);
+            scheduled_table[j] = ((vectors[k] & 0x3) << 0x6) | (vectors[k + 1] & 0x3F);
+        }
+        let timenow = ((NetworkEndian::read_u16(&scheduled_table[0..2]) as u32) << 16) |
+                      NetworkEndian::read_u16(&scheduled_table[4..6]) as u32;
+
+        Ok(DecodedAccount {
+            username: username.to_string(),
+            timestamp: Some(timenow),
+            pin: Some(pin.to_string()),
+            configuration: None,
+        })
+    }
+
+    /// Decode `encrypted` and find the first of `configurations` whose keys
+    /// produce the same PIN.
+    pub fn verify_account(encrypted: &str,
+                          configurations: &[Configuration])
+                          -> DecodeResult<DecodedAccount<Configuration>> {
+        for configuration in configurations {
+            let dialer = SingleNetDialer::load_from_config(*configuration);
+            let mut decoded = try!(dialer.decode_account(encrypted));
+            if dialer.encrypt_account(&decoded.username, decoded.timestamp) == encrypted {
+                decoded.configuration = Some(*configuration);
+                return Ok(decoded);
+            }
+        }
+        Err(DecodeError::PinMismatch(encrypted.to_string()))
+    }
+
     fn calc_hash(data: &[u8]) -> u16 {
         let length = data.len();
         let mut summary: u32 = 0;
@@ -120,6 +188,16 @@ impl SingleNetDialer {
 }
 
 impl Configuration {
+    pub fn all() -> &'static [Configuration] {
+        &[Configuration::Hainan]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Configuration::Hainan => "hainan",
+        }
+    }
+
     pub fn share_key(&self) -> &'static str {
         match *self {
             Configuration::Hainan => "hngx01",
@@ -149,6 +227,39 @@ impl Dialer for SingleNetDialer {
     }
 }
 
+impl ConfigurableDialer for SingleNetDialer {
+    fn section() -> &'static str {
+        "singlenet"
+    }
+
+    fn builtin_parameters() -> Vec<(&'static str, DialerParameters)> {
+        Configuration::all()
+            .iter()
+            .map(|config| {
+                let mut parameters = DialerParameters::new();
+                parameters.insert("share_key".to_string(), config.share_key().to_string());
+                parameters.insert("secret_key".to_string(), config.secret_key().to_string());
+                parameters.insert("key_table".to_string(), config.key_table().to_string());
+                (config.name(), parameters)
+            })
+            .collect()
+    }
+
+    fn from_parameters(parameters: &DialerParameters) -> Option<Self> {
+        match (parameters.get("share_key"),
+               parameters.get("secret_key"),
+               parameters.get("key_table")) {
+            // every 6 bits of the pin index the key table, which must be ascii to build the
+            // username
+            (Some(share_key), Some(secret_key), Some(key_table)) if key_table.len() == 64 &&
+                                                                     key_table.is_ascii() => {
+                Some(SingleNetDialer::new(share_key, secret_key, key_table))
+            }
+            _ => None,
+        }
+    }
+}
+
 #[test]
 fn test_hash_key() {
     let str1 = "123456".to_string();
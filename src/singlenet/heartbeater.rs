@@ -1,4 +1,4 @@
-use std::{io, result};
+use std::{io, result, error, fmt};
 use std::net::Ipv4Addr;
 
 use crypto::hash::{HasherBuilder, HasherType};
@@ -7,19 +7,62 @@ use byteorder::{NetworkEndian, ByteOrder};
 use singlenet::attributes::{Attribute, AttributeVec, AttributeType, KeepaliveDataCalculator,
                             ParseAttributesError};
 use common::reader::{ReadBytesError, ReaderHelper};
+use common::clock::{Clock, FixedClock};
 use common::utils::current_timestamp;
 use common::bytes::BytesAbleNum;
+#[cfg(feature="async")]
+use common::async_transport::{read_packet, BoxFuture};
+#[cfg(feature="async")]
+use bytes::BytesMut;
+#[cfg(feature="async")]
+use futures::Future;
+#[cfg(feature="async")]
+use tokio::codec::{Decoder, Encoder};
+#[cfg(feature="async")]
+use tokio::io::AsyncRead;
+#[cfg(feature="async")]
+use error::Error;
 
 #[derive(Debug)]
 pub enum SinglenetHeartbeatError {
     PacketReadError(ReadBytesError),
     ParseAttributesError(ParseAttributesError),
     UnexpectedBytes(Vec<u8>),
+    UnexpectedPacketCode(PacketCode),
+    AuthorizationMismatch,
+}
+
+impl fmt::Display for SinglenetHeartbeatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SinglenetHeartbeatError::PacketReadError(_) => write!(f, "read packet failed"),
+            SinglenetHeartbeatError::ParseAttributesError(_) => write!(f, "parse attributes failed"),
+            SinglenetHeartbeatError::UnexpectedBytes(ref bytes) => {
+                write!(f, "unexpected bytes {:?}", bytes)
+            }
+            SinglenetHeartbeatError::UnexpectedPacketCode(code) => {
+                write!(f, "unexpected packet code {:?}", code)
+            }
+            SinglenetHeartbeatError::AuthorizationMismatch => {
+                write!(f, "packet authorization mismatch")
+            }
+        }
+    }
+}
+
+impl error::Error for SinglenetHeartbeatError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            SinglenetHeartbeatError::PacketReadError(ref e) => Some(e),
+            SinglenetHeartbeatError::ParseAttributesError(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 type PacketResult<T> = result::Result<T, SinglenetHeartbeatError>;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum PacketCode {
     CRegisterRequest = 0x1,
     CRegisterResponse = 0x2,
@@ -52,6 +95,26 @@ pub struct PacketAuthenticator {
 pub struct PacketFactoryMac;
 pub struct PacketFactoryWin;
 
+/// Frames SingleNet packets on a stream as their bytes arrive, for
+/// `tokio::codec::Framed`. Written packets are signed with `authenticator`.
+#[cfg(feature="async")]
+pub struct PacketCodec {
+    authenticator: Option<PacketAuthenticator>,
+}
+
+/// Machine details sent by register requests, the client type and MAC
+/// address are also sent by the other client requests.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientFingerprint {
+    pub client_type: String,
+    pub cpu_info: String,
+    pub memory_size: u32,
+    pub os_version: String,
+    pub os_language: String,
+    pub mac_address: String,
+    pub explorer: String,
+}
+
 impl PacketAuthenticator {
     pub fn new(salt: &str) -> Self {
         PacketAuthenticator { salt: salt.to_string() }
@@ -67,6 +130,19 @@ impl PacketAuthenticator {
         authorization.clone_from_slice(&md5.finish());
         authorization
     }
+
+    /// Check the authorization of a signed packet, which covers the packet
+    /// with a zeroed authorization field.
+    pub fn verify(&self, packet_bytes: &[u8]) -> bool {
+        if packet_bytes.len() < Packet::header_length() as usize {
+            return false;
+        }
+        let mut unsigned = packet_bytes.to_vec();
+        for byte in &mut unsigned[6..22] {
+            *byte = 0;
+        }
+        self.authenticate(&unsigned)[..] == packet_bytes[6..22]
+    }
 }
 
 impl Packet {
@@ -99,6 +175,27 @@ impl Packet {
         packet
     }
 
+    pub fn code(&self) -> PacketCode {
+        self.code
+    }
+
+    pub fn seq(&self) -> u8 {
+        self.seq
+    }
+
+    pub fn authorization(&self) -> [u8; 16] {
+        self.authorization
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    /// First top level attribute of `attribute_type`.
+    pub fn attribute(&self, attribute_type: AttributeType) -> Option<&Attribute> {
+        self.attributes.find(attribute_type)
+    }
+
     pub fn calc_length(packet: &Self) -> u16 {
         Self::header_length() + packet.attributes.length()
     }
@@ -178,6 +275,67 @@ impl Packet {
     }
 }
 
+#[cfg(feature="async")]
+impl Packet {
+    /// Like [`Packet::from_bytes`], but waits for the whole packet on `reader`.
+    pub fn from_async_read<R>(reader: R) -> BoxFuture<(R, Self)>
+        where R: AsyncRead + Send + 'static
+    {
+        let header_length = Self::header_length() as usize;
+        Box::new(read_packet(reader, header_length, Self::frame_length)
+            .and_then(|(reader, packet)| {
+                let packet = try!(Self::from_bytes(&mut io::BufReader::new(&packet as &[u8])));
+                Ok((reader, packet))
+            }))
+    }
+
+    fn frame_length(header: &[u8]) -> usize {
+        NetworkEndian::read_u16(&header[2..4]) as usize
+    }
+}
+
+#[cfg(feature="async")]
+impl PacketCodec {
+    pub fn new(authenticator: Option<PacketAuthenticator>) -> Self {
+        PacketCodec { authenticator: authenticator }
+    }
+}
+
+#[cfg(feature="async")]
+impl Decoder for PacketCodec {
+    type Item = Packet;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Packet>, Error> {
+        let header_length = Packet::header_length() as usize;
+        if src.len() < header_length {
+            return Ok(None);
+        }
+        let length = Packet::frame_length(src);
+        if length < header_length {
+            let error = SinglenetHeartbeatError::UnexpectedBytes(src[..header_length].to_vec());
+            return Err(error.into());
+        }
+        if src.len() < length {
+            src.reserve(length - src.len());
+            return Ok(None);
+        }
+        let packet = src.split_to(length);
+        Ok(Some(try!(Packet::from_bytes(&mut io::BufReader::new(&packet as &[u8])))))
+    }
+}
+
+#[cfg(feature="async")]
+impl Encoder for PacketCodec {
+    type Item = Packet;
+    type Error = Error;
+
+    fn encode(&mut self, packet: Packet, dst: &mut BytesMut) -> Result<(), Error> {
+        dst.extend_from_slice(&packet.as_bytes(self.authenticator.as_ref()));
+        Ok(())
+    }
+}
+
 
 impl PacketFactoryWin {
     fn calc_seq(timestamp: Option<u32>) -> u8 {
@@ -194,9 +352,22 @@ impl PacketFactoryWin {
                              last_keepalive_data: Option<&str>,
                              version: Option<&str>)
                              -> Packet {
+        Self::keepalive_request_with_clock(username,
+                                           ipaddress,
+                                           &FixedClock::from_timestamp(timestamp),
+                                           last_keepalive_data,
+                                           version)
+    }
+
+    pub fn keepalive_request_with_clock(username: &str,
+                                        ipaddress: Ipv4Addr,
+                                        clock: &Clock,
+                                        last_keepalive_data: Option<&str>,
+                                        version: Option<&str>)
+                                        -> Packet {
         // FIXME: this protocol needs update
-        let version = version.unwrap_or("1.2.22.36");
-        let timestamp = timestamp.unwrap_or_else(current_timestamp);
+        let version = version.unwrap_or(Self::default_version());
+        let timestamp = clock.now();
         let keepalive_data = KeepaliveDataCalculator::calculate(Some(timestamp),
                                                                 last_keepalive_data);
 
@@ -212,6 +383,82 @@ impl PacketFactoryWin {
                     None,
                     attributes)
     }
+
+    /// The Windows client has no captured defaults, so the machine is always
+    /// described by `fingerprint`.
+    pub fn register_request(username: &str,
+                            ipaddress: Ipv4Addr,
+                            timestamp: Option<u32>,
+                            version: Option<&str>,
+                            fingerprint: &ClientFingerprint)
+                            -> Packet {
+        register_request(Self::calc_seq(timestamp),
+                         username,
+                         ipaddress,
+                         version.unwrap_or(Self::default_version()),
+                         fingerprint)
+    }
+
+    pub fn bubble_request(username: &str,
+                          ipaddress: Ipv4Addr,
+                          timestamp: Option<u32>,
+                          version: Option<&str>,
+                          fingerprint: &ClientFingerprint)
+                          -> Packet {
+        Self::client_request(PacketCode::CBubbleRequest,
+                             username,
+                             ipaddress,
+                             timestamp,
+                             version,
+                             fingerprint)
+    }
+
+    pub fn channel_request(username: &str,
+                           ipaddress: Ipv4Addr,
+                           timestamp: Option<u32>,
+                           version: Option<&str>,
+                           fingerprint: &ClientFingerprint)
+                           -> Packet {
+        Self::client_request(PacketCode::CChannelRequest,
+                             username,
+                             ipaddress,
+                             timestamp,
+                             version,
+                             fingerprint)
+    }
+
+    pub fn plugin_request(username: &str,
+                          ipaddress: Ipv4Addr,
+                          timestamp: Option<u32>,
+                          version: Option<&str>,
+                          fingerprint: &ClientFingerprint)
+                          -> Packet {
+        Self::client_request(PacketCode::CPluginRequest,
+                             username,
+                             ipaddress,
+                             timestamp,
+                             version,
+                             fingerprint)
+    }
+
+    fn default_version() -> &'static str {
+        "1.2.22.36"
+    }
+
+    fn client_request(code: PacketCode,
+                      username: &str,
+                      ipaddress: Ipv4Addr,
+                      timestamp: Option<u32>,
+                      version: Option<&str>,
+                      fingerprint: &ClientFingerprint)
+                      -> Packet {
+        client_request(code,
+                       Self::calc_seq(timestamp),
+                       username,
+                       ipaddress,
+                       version.unwrap_or(Self::default_version()),
+                       fingerprint)
+    }
 }
 
 impl PacketFactoryMac {
@@ -219,8 +466,8 @@ impl PacketFactoryMac {
         0x1u8
     }
 
-    fn client_type() -> String {
-        "Mac-SingletNet".to_string()
+    fn default_version() -> &'static str {
+        "1.1.0"
     }
 
     pub fn register_request(username: &str,
@@ -229,31 +476,23 @@ impl PacketFactoryMac {
                             mac_address: Option<&str>,
                             explorer: Option<&str>)
                             -> Packet {
-        let version = version.unwrap_or("1.1.0");
-        let mac_address = mac_address.unwrap_or("10:dd:b1:d5:95:ca");
-        let explorer = explorer.unwrap_or_default();
-        let client_type = &Self::client_type();
-        let cpu_info = "Intel(R) Core(TM) i5-5287U CPU @ 2.90GHz";
-        let memory_size = 0x2000;
-        let os_version = "Mac OS X Version 10.12 (Build 16A323)";
-        let os_language = "zh_CN";
+        let mut fingerprint = Self::fingerprint(mac_address);
+        fingerprint.explorer = explorer.unwrap_or_default().to_string();
+        Self::register_request_with_fingerprint(username, ipaddress, version, &fingerprint)
+    }
 
-        let attributes =
-            vec![Attribute::from_type(AttributeType::TUserName, &username.to_string()),
-                 Attribute::from_type(AttributeType::TClientVersion, &version.to_string()),
-                 Attribute::from_type(AttributeType::TClientType, &client_type.to_string()),
-                 Attribute::from_type(AttributeType::TClientIPAddress, &ipaddress),
-                 Attribute::from_type(AttributeType::TMACAddress, &mac_address.to_string()),
-                 Attribute::from_type(AttributeType::TDefaultExplorer, &explorer.to_string()),
-                 Attribute::from_type(AttributeType::TCPUInfo, &cpu_info.to_string()),
-                 Attribute::from_type(AttributeType::TMemorySize, &memory_size),
-                 Attribute::from_type(AttributeType::TOSVersion, &os_version.to_string()),
-                 Attribute::from_type(AttributeType::TOSLang, &os_language.to_string())];
-
-        Packet::new(PacketCode::CRegisterRequest,
-                    Self::calc_seq(),
-                    None,
-                    attributes)
+    /// Register as another machine, `ClientFingerprint::mac` describes the
+    /// captured one.
+    pub fn register_request_with_fingerprint(username: &str,
+                                             ipaddress: Ipv4Addr,
+                                             version: Option<&str>,
+                                             fingerprint: &ClientFingerprint)
+                                             -> Packet {
+        register_request(Self::calc_seq(),
+                         username,
+                         ipaddress,
+                         version.unwrap_or(Self::default_version()),
+                         fingerprint)
     }
 
     pub fn bubble_request(username: &str,
@@ -261,21 +500,11 @@ impl PacketFactoryMac {
                           version: Option<&str>,
                           mac_address: Option<&str>)
                           -> Packet {
-        let version = version.unwrap_or("1.1.0");
-        let mac_address = mac_address.unwrap_or("10:dd:b1:d5:95:ca");
-        let client_type = &Self::client_type();
-
-        let attributes =
-            vec![Attribute::from_type(AttributeType::TUserName, &username.to_string()),
-                 Attribute::from_type(AttributeType::TClientVersion, &version.to_string()),
-                 Attribute::from_type(AttributeType::TClientType, &client_type.to_string()),
-                 Attribute::from_type(AttributeType::TClientIPAddress, &ipaddress),
-                 Attribute::from_type(AttributeType::TMACAddress, &mac_address.to_string())];
-
-        Packet::new(PacketCode::CBubbleRequest,
-                    Self::calc_seq(),
-                    None,
-                    attributes)
+        Self::client_request(PacketCode::CBubbleRequest,
+                             username,
+                             ipaddress,
+                             version,
+                             mac_address)
     }
 
     pub fn real_time_bubble_request(username: &str,
@@ -283,22 +512,114 @@ impl PacketFactoryMac {
                                     version: Option<&str>,
                                     mac_address: Option<&str>)
                                     -> Packet {
-        let version = version.unwrap_or("1.1.0");
-        let mac_address = mac_address.unwrap_or("10:dd:b1:d5:95:ca");
-        let client_type = &Self::client_type();
+        Self::client_request(PacketCode::CRealTimeBubbleRequest,
+                             username,
+                             ipaddress,
+                             version,
+                             mac_address)
+    }
 
-        let attributes =
-            vec![Attribute::from_type(AttributeType::TUserName, &username.to_string()),
-                 Attribute::from_type(AttributeType::TClientVersion, &version.to_string()),
-                 Attribute::from_type(AttributeType::TClientType, &client_type.to_string()),
-                 Attribute::from_type(AttributeType::TClientIPAddress, &ipaddress),
-                 Attribute::from_type(AttributeType::TMACAddress, &mac_address.to_string())];
+    pub fn channel_request(username: &str,
+                           ipaddress: Ipv4Addr,
+                           version: Option<&str>,
+                           mac_address: Option<&str>)
+                           -> Packet {
+        Self::client_request(PacketCode::CChannelRequest,
+                             username,
+                             ipaddress,
+                             version,
+                             mac_address)
+    }
 
-        Packet::new(PacketCode::CRealTimeBubbleRequest,
-                    Self::calc_seq(),
-                    None,
-                    attributes)
+    pub fn plugin_request(username: &str,
+                          ipaddress: Ipv4Addr,
+                          version: Option<&str>,
+                          mac_address: Option<&str>)
+                          -> Packet {
+        Self::client_request(PacketCode::CPluginRequest,
+                             username,
+                             ipaddress,
+                             version,
+                             mac_address)
     }
+
+    fn fingerprint(mac_address: Option<&str>) -> ClientFingerprint {
+        ClientFingerprint::mac(mac_address.unwrap_or("10:dd:b1:d5:95:ca"))
+    }
+
+    fn client_request(code: PacketCode,
+                      username: &str,
+                      ipaddress: Ipv4Addr,
+                      version: Option<&str>,
+                      mac_address: Option<&str>)
+                      -> Packet {
+        client_request(code,
+                       Self::calc_seq(),
+                       username,
+                       ipaddress,
+                       version.unwrap_or(Self::default_version()),
+                       &Self::fingerprint(mac_address))
+    }
+}
+
+impl ClientFingerprint {
+    /// The machine of the captured Mac client, reporting `mac_address`.
+    pub fn mac(mac_address: &str) -> Self {
+        ClientFingerprint {
+            client_type: "Mac-SingletNet".to_string(),
+            cpu_info: "Intel(R) Core(TM) i5-5287U CPU @ 2.90GHz".to_string(),
+            memory_size: 0x2000,
+            os_version: "Mac OS X Version 10.12 (Build 16A323)".to_string(),
+            os_language: "zh_CN".to_string(),
+            mac_address: mac_address.to_string(),
+            explorer: "".to_string(),
+        }
+    }
+}
+
+fn register_request(seq: u8,
+                    username: &str,
+                    ipaddress: Ipv4Addr,
+                    version: &str,
+                    fingerprint: &ClientFingerprint)
+                    -> Packet {
+    let mut attributes = client_attributes(username, ipaddress, version, fingerprint);
+    attributes.extend(vec![Attribute::from_type(AttributeType::TDefaultExplorer,
+                                                &fingerprint.explorer),
+                           Attribute::from_type(AttributeType::TCPUInfo, &fingerprint.cpu_info),
+                           Attribute::from_type(AttributeType::TMemorySize,
+                                                &fingerprint.memory_size),
+                           Attribute::from_type(AttributeType::TOSVersion,
+                                                &fingerprint.os_version),
+                           Attribute::from_type(AttributeType::TOSLang,
+                                                &fingerprint.os_language)]);
+
+    Packet::new(PacketCode::CRegisterRequest, seq, None, attributes)
+}
+
+/// Requests of the clients which only identify themselves, such as the
+/// bubble, channel and plugin requests.
+fn client_request(code: PacketCode,
+                  seq: u8,
+                  username: &str,
+                  ipaddress: Ipv4Addr,
+                  version: &str,
+                  fingerprint: &ClientFingerprint)
+                  -> Packet {
+    let attributes = client_attributes(username, ipaddress, version, fingerprint);
+    Packet::new(code, seq, None, attributes)
+}
+
+fn client_attributes(username: &str,
+                     ipaddress: Ipv4Addr,
+                     version: &str,
+                     fingerprint: &ClientFingerprint)
+                     -> Vec<Attribute> {
+    vec![Attribute::from_type(AttributeType::TUserName, &username.to_string()),
+         Attribute::from_type(AttributeType::TClientVersion, &version.to_string()),
+         Attribute::from_type(AttributeType::TClientType, &fingerprint.client_type),
+         Attribute::from_type(AttributeType::TClientIPAddress, &ipaddress),
+         Attribute::from_type(AttributeType::TMACAddress, &fingerprint.mac_address)]
 }
 
 impl PacketCode {
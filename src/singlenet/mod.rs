@@ -1,6 +1,14 @@
 pub mod dialer;
 pub mod attributes;
 pub mod heartbeater;
+pub mod client;
+pub mod session;
+pub mod server;
+pub mod models;
+#[cfg(feature="async")]
+pub mod async_client;
+#[cfg(feature="async")]
+pub mod async_session;
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -0,0 +1,213 @@
+use std::result;
+
+use singlenet::attributes::{Attribute, AttributeType, AttributeContent, AttributeVec};
+use singlenet::heartbeater::{Packet, PacketCode, SinglenetHeartbeatError};
+
+type ModelResult<T> = result::Result<T, SinglenetHeartbeatError>;
+
+/// Notice pushed by the ISP, the official clients pop it up as a balloon
+/// linking to `context_url`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bubble {
+    pub id: u32,
+    pub title: String,
+    pub context: String,
+    pub context_url: String,
+    pub keep_time: u32,
+    pub delay_time: u32,
+    pub bubble_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Channel {
+    pub note: String,
+    pub context_url: String,
+    pub context_url2: String,
+    pub order: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plugin {
+    pub name: String,
+    pub configure_data: String,
+}
+
+/// New client release announced by the server.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Update {
+    pub version: String,
+    pub download_url: String,
+    pub description: String,
+}
+
+/// Children of a group attribute, missing ones read as zero or empty.
+struct Group {
+    attributes: Vec<Attribute>,
+}
+
+impl Group {
+    fn from_attribute(attribute: &Attribute) -> ModelResult<Self> {
+        let value = try!(attribute.value().map_err(SinglenetHeartbeatError::ParseAttributesError));
+        match value {
+            AttributeContent::Group(attributes) => Ok(Group { attributes: attributes }),
+            _ => Err(SinglenetHeartbeatError::UnexpectedBytes(attribute.as_bytes())),
+        }
+    }
+
+    fn from_packet(packet: &Packet,
+                   codes: &[PacketCode],
+                   group_type: AttributeType)
+                   -> ModelResult<Vec<Self>> {
+        if !codes.contains(&packet.code()) {
+            return Err(SinglenetHeartbeatError::UnexpectedPacketCode(packet.code()));
+        }
+        packet.attributes()
+            .iter()
+            .filter(|attribute| attribute.is(group_type))
+            .map(Self::from_attribute)
+            .collect()
+    }
+
+    fn integer(&self, attribute_type: AttributeType) -> ModelResult<u32> {
+        integer_value(self.attributes.find(attribute_type))
+    }
+
+    fn string(&self, attribute_type: AttributeType) -> ModelResult<String> {
+        string_value(self.attributes.find(attribute_type))
+    }
+}
+
+fn value(attribute: Option<&Attribute>) -> ModelResult<Option<AttributeContent>> {
+    match attribute {
+        Some(attribute) => {
+            attribute.value()
+                .map(Some)
+                .map_err(SinglenetHeartbeatError::ParseAttributesError)
+        }
+        None => Ok(None),
+    }
+}
+
+fn integer_value(attribute: Option<&Attribute>) -> ModelResult<u32> {
+    let value = try!(value(attribute));
+    Ok(value.and_then(|value| value.as_integer()).unwrap_or_default())
+}
+
+fn string_value(attribute: Option<&Attribute>) -> ModelResult<String> {
+    let value = try!(value(attribute));
+    Ok(value.and_then(|value| value.as_str().map(|string| string.to_string()))
+        .unwrap_or_default())
+}
+
+impl Bubble {
+    fn from_group(group: &Group) -> ModelResult<Self> {
+        Ok(Bubble {
+            id: try!(group.integer(AttributeType::TBubbleId)),
+            title: try!(group.string(AttributeType::TBubbleTitle)),
+            context: try!(group.string(AttributeType::TBubbleContext)),
+            context_url: try!(group.string(AttributeType::TBubbleContextURL)),
+            keep_time: try!(group.integer(AttributeType::TBubbleKeepTime)),
+            delay_time: try!(group.integer(AttributeType::TBubbleDelayTime)),
+            bubble_type: try!(group.integer(AttributeType::TBubbleType)),
+        })
+    }
+
+    /// Bubbles of a bubble or a real time bubble response.
+    pub fn from_packet(packet: &Packet) -> ModelResult<Vec<Self>> {
+        let codes = [PacketCode::CBubbleResponse, PacketCode::CRealTimeBubbleResponse];
+        let groups = try!(Group::from_packet(packet, &codes, AttributeType::TBubble));
+        groups.iter().map(Self::from_group).collect()
+    }
+
+    pub fn as_attribute(&self) -> Attribute {
+        Attribute::group(AttributeType::TBubble,
+                         vec![Attribute::from_type(AttributeType::TBubbleId, &self.id),
+                              Attribute::from_type(AttributeType::TBubbleTitle, &self.title),
+                              Attribute::from_type(AttributeType::TBubbleContext, &self.context),
+                              Attribute::from_type(AttributeType::TBubbleContextURL,
+                                                   &self.context_url),
+                              Attribute::from_type(AttributeType::TBubbleKeepTime,
+                                                   &self.keep_time),
+                              Attribute::from_type(AttributeType::TBubbleDelayTime,
+                                                   &self.delay_time),
+                              Attribute::from_type(AttributeType::TBubbleType,
+                                                   &self.bubble_type)])
+    }
+}
+
+impl Channel {
+    fn from_group(group: &Group) -> ModelResult<Self> {
+        Ok(Channel {
+            note: try!(group.string(AttributeType::TChannelNote)),
+            context_url: try!(group.string(AttributeType::TChannelContextURL)),
+            context_url2: try!(group.string(AttributeType::TChannelContextURL2)),
+            order: try!(group.integer(AttributeType::TChannelOrder)),
+        })
+    }
+
+    /// Channels of a channel response, sorted by their `order`.
+    pub fn from_packet(packet: &Packet) -> ModelResult<Vec<Self>> {
+        let groups = try!(Group::from_packet(packet,
+                                             &[PacketCode::CChannelResponse],
+                                             AttributeType::TChannel));
+        let mut channels: Vec<Self> = try!(groups.iter().map(Self::from_group).collect());
+        channels.sort_by_key(|channel| channel.order);
+        Ok(channels)
+    }
+
+    pub fn as_attribute(&self) -> Attribute {
+        Attribute::group(AttributeType::TChannel,
+                         vec![Attribute::from_type(AttributeType::TChannelNote, &self.note),
+                              Attribute::from_type(AttributeType::TChannelContextURL,
+                                                   &self.context_url),
+                              Attribute::from_type(AttributeType::TChannelContextURL2,
+                                                   &self.context_url2),
+                              Attribute::from_type(AttributeType::TChannelOrder, &self.order)])
+    }
+}
+
+impl Plugin {
+    fn from_group(group: &Group) -> ModelResult<Self> {
+        Ok(Plugin {
+            name: try!(group.string(AttributeType::TPluginName)),
+            configure_data: try!(group.string(AttributeType::TPluginConfigureData)),
+        })
+    }
+
+    pub fn from_packet(packet: &Packet) -> ModelResult<Vec<Self>> {
+        let groups = try!(Group::from_packet(packet,
+                                             &[PacketCode::CPluginResponse],
+                                             AttributeType::TPlugin));
+        groups.iter().map(Self::from_group).collect()
+    }
+
+    pub fn as_attribute(&self) -> Attribute {
+        Attribute::group(AttributeType::TPlugin,
+                         vec![Attribute::from_type(AttributeType::TPluginName, &self.name),
+                              Attribute::from_type(AttributeType::TPluginConfigureData,
+                                                   &self.configure_data)])
+    }
+}
+
+impl Update {
+    /// The update carried by any response, `None` when the packet has
+    /// neither a version nor a download URL.
+    pub fn from_packet(packet: &Packet) -> ModelResult<Option<Self>> {
+        let version = packet.attribute(AttributeType::TUpdateVersion);
+        let download_url = packet.attribute(AttributeType::TUpdateDownloadURL);
+        if version.is_none() && download_url.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Update {
+            version: try!(string_value(version)),
+            download_url: try!(string_value(download_url)),
+            description: try!(string_value(packet.attribute(AttributeType::TUpdateDescription))),
+        }))
+    }
+
+    pub fn as_attributes(&self) -> Vec<Attribute> {
+        vec![Attribute::from_type(AttributeType::TUpdateVersion, &self.version),
+             Attribute::from_type(AttributeType::TUpdateDownloadURL, &self.download_url),
+             Attribute::from_type(AttributeType::TUpdateDescription, &self.description)]
+    }
+}
@@ -0,0 +1,68 @@
+use std::io;
+
+use common::server::Responder;
+use singlenet::attributes::{Attribute, AttributeType};
+use singlenet::heartbeater::{Packet, PacketAuthenticator, PacketCode};
+use singlenet::models::Update;
+
+const DEFAULT_KEEPALIVE_INTERVAL: u32 = 60;
+
+/// Emulates a SingleNet server, requests with a wrong authorization are
+/// dropped and every other request gets the matching response code.
+pub struct SingleNetServer {
+    authenticator: PacketAuthenticator,
+    keepalive_interval: u32,
+    update: Option<Update>,
+}
+
+impl SingleNetServer {
+    pub fn new(secret: &str) -> Self {
+        SingleNetServer {
+            authenticator: PacketAuthenticator::new(secret),
+            keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+            update: None,
+        }
+    }
+
+    /// Interval in seconds sent with the keep alive responses.
+    pub fn keepalive_interval(&mut self, interval: u32) -> &mut Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    /// Announce `update` in the register and keep alive responses.
+    pub fn update(&mut self, update: Update) -> &mut Self {
+        self.update = Some(update);
+        self
+    }
+}
+
+impl Responder for SingleNetServer {
+    fn respond(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        if !self.authenticator.verify(request) {
+            return None;
+        }
+        let packet = match Packet::from_bytes(&mut io::BufReader::new(request)) {
+            Ok(packet) => packet,
+            Err(_) => return None,
+        };
+
+        let update = self.update.as_ref().map(Update::as_attributes).unwrap_or_default();
+        let (code, attributes) = match packet.code() {
+            PacketCode::CRegisterRequest => (PacketCode::CRegisterResponse, update),
+            PacketCode::CKeepAliveRequest => {
+                let mut attributes = vec![Attribute::from_type(AttributeType::TKeepAliveInterval,
+                                                               &self.keepalive_interval)];
+                attributes.extend(update);
+                (PacketCode::CKeepAliveResponse, attributes)
+            }
+            PacketCode::CBubbleRequest => (PacketCode::CBubbleResponse, vec![]),
+            PacketCode::CChannelRequest => (PacketCode::CChannelResponse, vec![]),
+            PacketCode::CPluginRequest => (PacketCode::CPluginResponse, vec![]),
+            PacketCode::CRealTimeBubbleRequest => (PacketCode::CRealTimeBubbleResponse, vec![]),
+            _ => return None,
+        };
+        let response = Packet::new(code, packet.seq(), None, attributes);
+        Some(response.as_bytes(Some(&self.authenticator)))
+    }
+}
@@ -0,0 +1,198 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use common::daemon::KeepAliveSession;
+use common::transport::Transport;
+use common::clock::{Clock, SystemClock};
+use singlenet::attributes::{AttributeType, KeepaliveDataCalculator};
+use singlenet::client::SingleNetClient;
+use singlenet::heartbeater::{ClientFingerprint, Packet, PacketAuthenticator, PacketCode,
+                             PacketFactoryWin, SinglenetHeartbeatError};
+use singlenet::models::Update;
+use error::{Error, Result};
+
+pub const KEEP_ALIVE_INTERVAL: u64 = 60;
+
+/// Acts as the Windows client: registers, then sends keep alive requests,
+/// each one salted with the keep alive data of the previous one, as often
+/// as the server asks.
+pub struct SingleNetSession<T: Transport> {
+    client: SingleNetClient<T>,
+    context: SessionContext,
+}
+
+/// Builds the requests of a SingleNet session and learns from their
+/// responses, the blocking and the async sessions only carry the packets.
+pub struct SessionContext {
+    username: String,
+    ipaddress: Ipv4Addr,
+    fingerprint: ClientFingerprint,
+    last_keepalive_data: Option<String>,
+    interval: Duration,
+    update: Option<Update>,
+    clock: Box<Clock>,
+}
+
+impl<T> SingleNetSession<T>
+    where T: Transport
+{
+    pub fn new(transport: T,
+               authenticator: PacketAuthenticator,
+               username: &str,
+               ipaddress: Ipv4Addr,
+               fingerprint: ClientFingerprint)
+               -> Self {
+        SingleNetSession {
+            client: SingleNetClient::new(transport, authenticator),
+            context: SessionContext::new(username, ipaddress, fingerprint),
+        }
+    }
+
+    /// Clock of the keep alive timestamps, e.g. an `OffsetClock` following
+    /// the server.
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.context.clock(clock);
+        self
+    }
+
+    pub fn client(&mut self) -> &mut SingleNetClient<T> {
+        &mut self.client
+    }
+
+    pub fn last_keepalive_data(&self) -> Option<&str> {
+        self.context.last_keepalive_data()
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.context.interval()
+    }
+
+    pub fn update(&self) -> Option<&Update> {
+        self.context.update()
+    }
+
+    /// Register the client and restart the keep alive data chain.
+    pub fn register(&mut self) -> Result<()> {
+        let response = try!(self.client.request(&self.context.register_request()));
+        self.context.registered(&response)
+    }
+
+    /// Send a keep alive request at `timestamp`, which defaults to the time
+    /// of the session clock.
+    pub fn keep_alive(&mut self, timestamp: Option<u32>) -> Result<()> {
+        let (packet, keepalive_data) = self.context.keepalive_request(timestamp);
+        let response = try!(self.client.request(&packet));
+        self.context.kept_alive(&response, keepalive_data)
+    }
+}
+
+impl SessionContext {
+    pub fn new(username: &str, ipaddress: Ipv4Addr, fingerprint: ClientFingerprint) -> Self {
+        SessionContext {
+            username: username.to_string(),
+            ipaddress: ipaddress,
+            fingerprint: fingerprint,
+            last_keepalive_data: None,
+            interval: Duration::from_secs(KEEP_ALIVE_INTERVAL),
+            update: None,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    pub fn clock<C>(&mut self, clock: C) -> &mut Self
+        where C: Clock + 'static
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn last_keepalive_data(&self) -> Option<&str> {
+        self.last_keepalive_data.as_ref().map(|data| data.as_str())
+    }
+
+    /// Keep alive interval of the last response, 60 seconds until the server
+    /// tells one.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The latest update announced by the server.
+    pub fn update(&self) -> Option<&Update> {
+        self.update.as_ref()
+    }
+
+    /// Restart the keep alive data chain.
+    pub fn reset(&mut self) {
+        self.last_keepalive_data = None;
+    }
+
+    pub fn register_request(&self) -> Packet {
+        PacketFactoryWin::register_request(&self.username,
+                                           self.ipaddress,
+                                           Some(self.clock.now()),
+                                           None,
+                                           &self.fingerprint)
+    }
+
+    pub fn registered(&mut self, response: &Packet) -> Result<()> {
+        try!(self.handle_response(response, PacketCode::CRegisterResponse));
+        self.reset();
+        Ok(())
+    }
+
+    /// The keep alive request at `timestamp`, with the keep alive data that
+    /// salts the next one once the server answered.
+    pub fn keepalive_request(&self, timestamp: Option<u32>) -> (Packet, String) {
+        let timestamp = timestamp.unwrap_or_else(|| self.clock.now());
+        let last_keepalive_data = self.last_keepalive_data();
+        let packet = PacketFactoryWin::keepalive_request(&self.username,
+                                                         self.ipaddress,
+                                                         Some(timestamp),
+                                                         last_keepalive_data,
+                                                         None);
+        let keepalive_data = KeepaliveDataCalculator::calculate(Some(timestamp),
+                                                                last_keepalive_data);
+        (packet, keepalive_data)
+    }
+
+    pub fn kept_alive(&mut self, response: &Packet, keepalive_data: String) -> Result<()> {
+        try!(self.handle_response(response, PacketCode::CKeepAliveResponse));
+        if let Some(attribute) = response.attribute(AttributeType::TKeepAliveInterval) {
+            let interval = try!(attribute.value()).as_integer().unwrap_or_default();
+            if interval > 0 {
+                self.interval = Duration::from_secs(interval as u64);
+            }
+        }
+        self.last_keepalive_data = Some(keepalive_data);
+        Ok(())
+    }
+
+    fn handle_response(&mut self, response: &Packet, code: PacketCode) -> Result<()> {
+        if response.code() != code {
+            let error = SinglenetHeartbeatError::UnexpectedPacketCode(response.code());
+            return Err(Error::SinglenetHeartbeat(error));
+        }
+        if let Some(update) = try!(Update::from_packet(response)) {
+            self.update = Some(update);
+        }
+        Ok(())
+    }
+}
+
+impl<T> KeepAliveSession for SingleNetSession<T>
+    where T: Transport
+{
+    fn login(&mut self) -> Result<()> {
+        self.register()
+    }
+
+    fn keep_alive(&mut self) -> Result<()> {
+        SingleNetSession::keep_alive(self, None)
+    }
+
+    fn interval(&self) -> Duration {
+        SingleNetSession::interval(self)
+    }
+}
@@ -4,6 +4,9 @@ use std::io::BufReader;
 use std::str::FromStr;
 use std::net::Ipv4Addr;
 use singlenet::heartbeater::{PacketFactoryMac, PacketFactoryWin, PacketAuthenticator, Packet};
+use singlenet::client::SingleNetClient;
+use common::transport::LoopbackTransport;
+use common::clock::FixedClock;
 
 #[test]
 fn test_singlenet_username_encrypt() {
@@ -109,3 +112,466 @@ fn test_bubble_request() {
              100, 58, 98, 49, 58, 100, 53, 58, 57, 53, 58, 99, 97];
     assert_eq!(reg_bytes, real_bytes);
 }
+
+#[test]
+fn test_client_fingerprint() {
+    use singlenet::attributes::AttributeType;
+    use singlenet::heartbeater::{ClientFingerprint, PacketCode};
+
+    let ipaddress = Ipv4Addr::from_str("10.8.0.4").unwrap();
+    let fingerprint = ClientFingerprint {
+        client_type: "Win-Client".to_string(),
+        cpu_info: "AMD Ryzen 5 1600 Six-Core Processor".to_string(),
+        memory_size: 0x4000,
+        os_version: "Windows 10".to_string(),
+        os_language: "zh_CN".to_string(),
+        mac_address: "00-1C-42-2E-60-4A".to_string(),
+        explorer: "".to_string(),
+    };
+
+    let register = PacketFactoryWin::register_request("05802278989@HYXY.XY",
+                                                      ipaddress,
+                                                      Some(1472483020),
+                                                      None,
+                                                      &fingerprint);
+    assert_eq!(register.code(), PacketCode::CRegisterRequest);
+    assert_eq!(register.seq(), 43);
+    assert_eq!(register.attributes().len(), 10);
+    let value = |attribute_type| register.attribute(attribute_type).unwrap().value().unwrap();
+    assert_eq!(value(AttributeType::TClientType).as_str(), Some("Win-Client"));
+    assert_eq!(value(AttributeType::TClientVersion).as_str(), Some("1.2.22.36"));
+    assert_eq!(value(AttributeType::TCPUInfo).as_str(),
+               Some("AMD Ryzen 5 1600 Six-Core Processor"));
+    assert_eq!(value(AttributeType::TMemorySize).as_integer(), Some(0x4000));
+    assert_eq!(value(AttributeType::TOSVersion).as_str(), Some("Windows 10"));
+    assert_eq!(value(AttributeType::TMACAddress).as_str(), Some("00-1C-42-2E-60-4A"));
+
+    let bubble = PacketFactoryWin::bubble_request("05802278989@HYXY.XY",
+                                                  ipaddress,
+                                                  Some(1472483020),
+                                                  Some("1.2.23.0"),
+                                                  &fingerprint);
+    assert_eq!(bubble.code(), PacketCode::CBubbleRequest);
+    assert_eq!(bubble.attributes().len(), 5);
+    let value = |attribute_type| bubble.attribute(attribute_type).unwrap().value().unwrap();
+    assert_eq!(value(AttributeType::TClientVersion).as_str(), Some("1.2.23.0"));
+    assert_eq!(value(AttributeType::TMACAddress).as_str(), Some("00-1C-42-2E-60-4A"));
+
+    // the captured Mac machine reproduces the captured register request
+    let captured = PacketFactoryMac::register_request_with_fingerprint(
+        "05802278989@HYXY.XY",
+        ipaddress,
+        None,
+        &ClientFingerprint::mac("10:dd:b1:d5:95:ca"));
+    let default =
+        PacketFactoryMac::register_request("05802278989@HYXY.XY", ipaddress, None, None, None);
+    assert_eq!(captured.as_bytes(None), default.as_bytes(None));
+
+    let other = PacketFactoryMac::register_request_with_fingerprint(
+        "05802278989@HYXY.XY",
+        ipaddress,
+        None,
+        &ClientFingerprint { explorer: "Safari".to_string(), ..fingerprint.clone() });
+    let value = |attribute_type| other.attribute(attribute_type).unwrap().value().unwrap();
+    assert_eq!(value(AttributeType::TClientType).as_str(), Some("Win-Client"));
+    assert_eq!(value(AttributeType::TDefaultExplorer).as_str(), Some("Safari"));
+    assert_eq!(other.seq(), 1);
+}
+
+#[test]
+fn test_channel_and_plugin_requests() {
+    use singlenet::attributes::AttributeType;
+    use singlenet::heartbeater::{ClientFingerprint, PacketCode};
+
+    let ipaddress = Ipv4Addr::from_str("10.8.0.4").unwrap();
+    // the Windows client was not captured, its fingerprint is always given
+    let fingerprint = ClientFingerprint {
+        client_type: "Win-Client".to_string(),
+        mac_address: "00-1C-42-2E-60-4A".to_string(),
+        ..Default::default()
+    };
+    let requests =
+        vec![(PacketCode::CChannelRequest,
+              PacketFactoryMac::channel_request("05802278989@HYXY.XY", ipaddress, None, None)),
+             (PacketCode::CPluginRequest,
+              PacketFactoryMac::plugin_request("05802278989@HYXY.XY",
+                                               ipaddress,
+                                               None,
+                                               Some("00:1c:42:2e:60:4a"))),
+             (PacketCode::CChannelRequest,
+              PacketFactoryWin::channel_request("05802278989@HYXY.XY",
+                                                ipaddress,
+                                                Some(1472483020),
+                                                None,
+                                                &fingerprint)),
+             (PacketCode::CPluginRequest,
+              PacketFactoryWin::plugin_request("05802278989@HYXY.XY",
+                                               ipaddress,
+                                               Some(1472483020),
+                                               None,
+                                               &fingerprint))];
+    let expected = [("Mac-SingletNet", "1.1.0", "10:dd:b1:d5:95:ca", 1),
+                    ("Mac-SingletNet", "1.1.0", "00:1c:42:2e:60:4a", 1),
+                    ("Win-Client", "1.2.22.36", "00-1C-42-2E-60-4A", 43),
+                    ("Win-Client", "1.2.22.36", "00-1C-42-2E-60-4A", 43)];
+    let authenticator = PacketAuthenticator::new("LLWLXA");
+    for ((code, request), &(client_type, version, mac_address, seq)) in
+        requests.into_iter().zip(&expected) {
+        let bytes = request.as_bytes(Some(&authenticator));
+        assert!(authenticator.verify(&bytes));
+        let parsed = Packet::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+        assert_eq!(parsed.code(), code);
+        assert_eq!(parsed.seq(), seq);
+        assert_eq!(parsed.attributes().len(), 5);
+        let value = |attribute_type| parsed.attribute(attribute_type).unwrap().value().unwrap();
+        assert_eq!(value(AttributeType::TUserName).as_str(), Some("05802278989@HYXY.XY"));
+        assert_eq!(value(AttributeType::TClientIPAddress).as_ipaddress(), Some(ipaddress));
+        assert_eq!(value(AttributeType::TClientType).as_str(), Some(client_type));
+        assert_eq!(value(AttributeType::TClientVersion).as_str(), Some(version));
+        assert_eq!(value(AttributeType::TMACAddress).as_str(), Some(mac_address));
+    }
+}
+
+#[test]
+fn test_response_packet_round_trip() {
+    use singlenet::attributes::{Attribute, AttributeType};
+    use singlenet::heartbeater::PacketCode;
+
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let responses = vec![(PacketCode::CRegisterResponse, vec![]),
+                         (PacketCode::CKeepAliveResponse,
+                          vec![Attribute::from_type(AttributeType::TKeepAliveInterval, &60u32)]),
+                         (PacketCode::CBubbleResponse,
+                          vec![Attribute::from_type(AttributeType::TBubbleTitle,
+                                                    &"title".to_string())]),
+                         (PacketCode::CChannelResponse, vec![]),
+                         (PacketCode::CPluginResponse, vec![]),
+                         (PacketCode::CRealTimeBubbleResponse, vec![])];
+    for (seq, (code, attributes)) in responses.into_iter().enumerate() {
+        let packet = Packet::new(code, seq as u8, None, attributes);
+        for bytes in vec![packet.as_bytes(None), packet.as_bytes(Some(&authenticator))] {
+            let parsed = Packet::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+            assert_eq!(parsed.code() as u8, code as u8);
+            assert_eq!(parsed.seq(), seq as u8);
+            assert_eq!(parsed.as_bytes(None), bytes);
+        }
+    }
+}
+
+#[test]
+fn test_response_models() {
+    use singlenet::heartbeater::PacketCode;
+    use singlenet::models::{Bubble, Channel, Plugin};
+
+    let notice = Bubble {
+        id: 1,
+        title: "notice".to_string(),
+        context: "network maintenance tonight".to_string(),
+        context_url: "http://example.com/notice".to_string(),
+        keep_time: 30,
+        delay_time: 5,
+        bubble_type: 2,
+    };
+    let untitled = Bubble { id: 2, ..Bubble::default() };
+    let packet = Packet::new(PacketCode::CBubbleResponse,
+                             0,
+                             None,
+                             vec![notice.as_attribute(), untitled.as_attribute()]);
+    let bytes = packet.as_bytes(None);
+    let parsed = Packet::from_bytes(&mut BufReader::new(&bytes as &[u8])).unwrap();
+    assert_eq!(Bubble::from_packet(&parsed).unwrap(), vec![notice, untitled]);
+    assert!(Channel::from_packet(&parsed).is_err());
+
+    let home = Channel {
+        note: "home".to_string(),
+        context_url: "http://example.com".to_string(),
+        context_url2: "".to_string(),
+        order: 2,
+    };
+    let mail = Channel { note: "mail".to_string(), order: 1, ..Channel::default() };
+    let packet = Packet::new(PacketCode::CChannelResponse,
+                             0,
+                             None,
+                             vec![home.as_attribute(), mail.as_attribute()]);
+    assert_eq!(Channel::from_packet(&packet).unwrap(), vec![mail, home]);
+
+    let plugin = Plugin {
+        name: "speedtest".to_string(),
+        configure_data: "interval=60".to_string(),
+    };
+    let packet = Packet::new(PacketCode::CPluginResponse, 0, None, vec![plugin.as_attribute()]);
+    assert_eq!(Plugin::from_packet(&packet).unwrap(), vec![plugin]);
+    let empty = Packet::new(PacketCode::CPluginResponse, 0, None, vec![]);
+    assert!(Plugin::from_packet(&empty).unwrap().is_empty());
+}
+
+#[test]
+fn test_singlenet_client() {
+    let transport = LoopbackTransport::new(|bytes| Some(bytes.to_vec()));
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let mut client = SingleNetClient::new(transport, authenticator);
+
+    let ka = PacketFactoryWin::keepalive_request("05802278989@HYXY.XY",
+                                                 Ipv4Addr::from_str("10.0.0.1").unwrap(),
+                                                 Some(1472483020),
+                                                 None,
+                                                 None);
+    let response = client.request(&ka).unwrap();
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    assert_eq!(response.as_bytes(None), ka.as_bytes(Some(&authenticator)));
+}
+
+#[test]
+fn test_singlenet_username_decode() {
+    let dialer = SingleNetDialer::load_from_config(Configuration::Hainan);
+    let decoded = dialer.decode_account("~LL_k6ecvpj2mrjA_05802278989@HYXY.XY").unwrap();
+    assert_eq!(decoded.username, "05802278989@HYXY.XY");
+    assert_eq!(decoded.pin, Some("k6ecvpj2mrjA".to_string()));
+    assert_eq!(decoded.timestamp, Some(1472483020));
+
+    let verified = SingleNetDialer::verify_account("~LL_k6ecvpj2mrjA_05802278989@HYXY.XY",
+                                                   &[Configuration::Hainan])
+        .unwrap();
+    assert_eq!(verified.configuration, Some(Configuration::Hainan));
+    assert!(SingleNetDialer::verify_account("~LL_k6ecvpj2mrjB_05802278989@HYXY.XY",
+                                            &[Configuration::Hainan])
+        .is_err());
+    assert!(dialer.decode_account("~LL_k6ecvpj2mrjA").is_err());
+}
+
+#[test]
+fn test_singlenet_session() {
+    use std::time::Duration;
+    use common::daemon::KeepAliveSession;
+    use common::server::Responder;
+    use error::Error;
+    use singlenet::heartbeater::{ClientFingerprint, PacketCode, SinglenetHeartbeatError};
+    use singlenet::models::Update;
+    use singlenet::server::SingleNetServer;
+    use singlenet::session::SingleNetSession;
+
+    let update = Update {
+        version: "1.2.23.0".to_string(),
+        download_url: "http://example.com/singlenet.exe".to_string(),
+        description: "".to_string(),
+    };
+    let mut server = SingleNetServer::new("LLWLXA_TPSHARESECRET");
+    server.keepalive_interval(30).update(update.clone());
+    let transport = LoopbackTransport::new(move |bytes| server.respond(bytes));
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let ipaddress = Ipv4Addr::from_str("10.0.0.1").unwrap();
+    let fingerprint = ClientFingerprint::mac("00:1c:42:2e:60:4a");
+    let mut session =
+        SingleNetSession::new(transport,
+                              authenticator,
+                              "05802278989@HYXY.XY",
+                              ipaddress,
+                              fingerprint.clone());
+
+    assert_eq!(KeepAliveSession::interval(&session), Duration::from_secs(60));
+    KeepAliveSession::login(&mut session).unwrap();
+    assert_eq!(session.update(), Some(&update));
+    session.keep_alive(Some(1472483020)).unwrap();
+    assert_eq!(session.last_keepalive_data(),
+               Some("ffb0b2af94693fd1ba4c93e6b9aebd3f"));
+    assert_eq!(KeepAliveSession::interval(&session), Duration::from_secs(30));
+    session.keep_alive(Some(1472483020)).unwrap();
+
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let chained = PacketFactoryWin::keepalive_request("05802278989@HYXY.XY",
+                                                      ipaddress,
+                                                      Some(1472483020),
+                                                      Some("ffb0b2af94693fd1ba4c93e6b9aebd3f"),
+                                                      None);
+    assert_eq!(session.client().inner().transport().sent()[2],
+               chained.as_bytes(Some(&authenticator)));
+
+    session.register().unwrap();
+    assert_eq!(session.last_keepalive_data(), None);
+
+    // without a timestamp the session clock tells the time
+    session.clock(FixedClock::new(1472483020));
+    session.keep_alive(None).unwrap();
+    assert_eq!(session.last_keepalive_data(),
+               Some("ffb0b2af94693fd1ba4c93e6b9aebd3f"));
+
+    // responses signed with another secret are rejected
+    let transport = LoopbackTransport::new(|_| {
+        let authenticator = PacketAuthenticator::new("WRONGSECRET");
+        let response = Packet::new(PacketCode::CKeepAliveResponse, 0, None, vec![]);
+        Some(response.as_bytes(Some(&authenticator)))
+    });
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let mut session =
+        SingleNetSession::new(transport,
+                              authenticator,
+                              "05802278989@HYXY.XY",
+                              ipaddress,
+                              fingerprint.clone());
+    match session.keep_alive(None) {
+        Err(Error::SinglenetHeartbeat(SinglenetHeartbeatError::AuthorizationMismatch)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // answering a keep alive request with another code fails
+    let transport = LoopbackTransport::new(|bytes| Some(bytes.to_vec()));
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let mut session =
+        SingleNetSession::new(transport,
+                              authenticator,
+                              "05802278989@HYXY.XY",
+                              ipaddress,
+                              fingerprint.clone());
+    assert!(session.keep_alive(None).is_err());
+    assert_eq!(session.last_keepalive_data(), None);
+}
+
+#[test]
+fn test_singlenet_mock_server() {
+    use std::time::Duration;
+    use common::server::UdpServer;
+    use common::transport::{Transport, UdpTransport};
+    use singlenet::heartbeater::ClientFingerprint;
+    use singlenet::server::SingleNetServer;
+    use singlenet::session::SingleNetSession;
+
+    let server = UdpServer::spawn_local(SingleNetServer::new("LLWLXA_TPSHARESECRET")).unwrap();
+    let connect = |timeout| {
+        let mut transport = UdpTransport::connect("127.0.0.1:0", server.local_addr()).unwrap();
+        transport.set_timeout(Some(timeout)).unwrap();
+        transport
+    };
+    let ipaddress = Ipv4Addr::from_str("10.0.0.1").unwrap();
+    let fingerprint = ClientFingerprint::mac("00:1c:42:2e:60:4a");
+
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let mut session = SingleNetSession::new(connect(Duration::from_secs(5)),
+                                            authenticator,
+                                            "05802278989@HYXY.XY",
+                                            ipaddress,
+                                            fingerprint.clone());
+    session.register().unwrap();
+    assert!(session.update().is_none());
+    session.keep_alive(None).unwrap();
+    session.keep_alive(None).unwrap();
+
+    // requests signed with another secret are dropped
+    let authenticator = PacketAuthenticator::new("WRONGSECRET");
+    let mut session = SingleNetSession::new(connect(Duration::from_millis(200)),
+                                            authenticator,
+                                            "05802278989@HYXY.XY",
+                                            ipaddress,
+                                            fingerprint.clone());
+    session.client().inner().retries(0);
+    assert!(session.keep_alive(None).is_err());
+}
+
+#[cfg(feature="async")]
+#[test]
+fn test_singlenet_async_session() {
+    use std::time::Duration;
+    use futures::Future;
+    use tokio::runtime::current_thread::Runtime;
+    use common::async_transport::AsyncUdpTransport;
+    use common::server::UdpServer;
+    use singlenet::heartbeater::ClientFingerprint;
+    use singlenet::server::SingleNetServer;
+    use singlenet::async_session::AsyncSingleNetSession;
+
+    let server = UdpServer::spawn_local(SingleNetServer::new("LLWLXA_TPSHARESECRET")).unwrap();
+    let local_addr = "127.0.0.1:0".parse().unwrap();
+    let connect = |secret, timeout| {
+        let transport = AsyncUdpTransport::connect(&local_addr, &server.local_addr()).unwrap();
+        let mut session = AsyncSingleNetSession::new(transport,
+                                                     PacketAuthenticator::new(secret),
+                                                     "05802278989@HYXY.XY",
+                                                     Ipv4Addr::from_str("10.0.0.1").unwrap(),
+                                                     ClientFingerprint::mac("00:1c:42:2e:60:4a"));
+        session.client().inner().timeout(timeout).retries(0);
+        session
+    };
+
+    let mut runtime = Runtime::new().unwrap();
+    let session = connect("LLWLXA_TPSHARESECRET", Duration::from_secs(5));
+    let session = runtime.block_on(session.register()
+            .and_then(|session| session.keep_alive(Some(1472483020)))
+            .and_then(|session| {
+                assert_eq!(session.last_keepalive_data(),
+                           Some("ffb0b2af94693fd1ba4c93e6b9aebd3f"));
+                session.keep_alive(None)
+            }))
+        .unwrap();
+    assert!(session.last_keepalive_data() != Some("ffb0b2af94693fd1ba4c93e6b9aebd3f"));
+
+    // requests signed with another secret are dropped
+    let session = connect("WRONGSECRET", Duration::from_millis(200));
+    assert!(runtime.block_on(session.keep_alive(None)).is_err());
+}
+
+#[cfg(feature="async")]
+#[test]
+fn test_singlenet_packet_from_async_read() {
+    use std::io::Cursor;
+    use futures::Future;
+
+    let ka = PacketFactoryWin::keepalive_request("05802278989@HYXY.XY",
+                                                 Ipv4Addr::from_str("10.0.0.1").unwrap(),
+                                                 Some(1472483020),
+                                                 None,
+                                                 None);
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let bytes = ka.as_bytes(Some(&authenticator));
+    let mut stream = bytes.clone();
+    stream.extend(&bytes[..10]);
+
+    let (rest, parsed) = Packet::from_async_read(Cursor::new(stream)).wait().unwrap();
+    assert_eq!(parsed.as_bytes(None), bytes);
+    assert!(Packet::from_async_read(rest).wait().is_err());
+}
+
+#[cfg(feature="async")]
+#[test]
+fn test_singlenet_packet_codec() {
+    use std::io::Cursor;
+    use bytes::BytesMut;
+    use futures::{Future, Stream};
+    use tokio::codec::{Decoder, Encoder, FramedRead};
+    use singlenet::heartbeater::PacketCodec;
+
+    let ka = PacketFactoryWin::keepalive_request("05802278989@HYXY.XY",
+                                                 Ipv4Addr::from_str("10.0.0.1").unwrap(),
+                                                 Some(1472483020),
+                                                 None,
+                                                 None);
+    let authenticator = PacketAuthenticator::new("LLWLXA_TPSHARESECRET");
+    let bytes = ka.as_bytes(Some(&authenticator));
+
+    // nothing is decoded until the last byte of the packet arrives
+    let mut codec = PacketCodec::new(Some(PacketAuthenticator::new("LLWLXA_TPSHARESECRET")));
+    let mut buffer = BytesMut::new();
+    for byte in &bytes[..bytes.len() - 1] {
+        buffer.extend_from_slice(&[*byte]);
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+    }
+    buffer.extend_from_slice(&bytes[bytes.len() - 1..]);
+    let parsed = codec.decode(&mut buffer).unwrap().unwrap();
+    assert_eq!(parsed.as_bytes(None), bytes);
+    assert!(buffer.is_empty());
+
+    codec.encode(ka, &mut buffer).unwrap();
+    assert_eq!(&buffer[..], &bytes[..]);
+
+    let mut stream = bytes.clone();
+    stream.extend(&bytes);
+    let packets = FramedRead::new(Cursor::new(stream), PacketCodec::new(None))
+        .collect()
+        .wait()
+        .unwrap();
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[1].as_bytes(None), bytes);
+
+    // a length shorter than the header is rejected
+    let mut buffer = BytesMut::from(&bytes[..]);
+    buffer[2..4].copy_from_slice(&[0, 1]);
+    assert!(codec.decode(&mut buffer).is_err());
+}
@@ -1,6 +1,8 @@
-use common::dialer::Dialer;
+use common::dialer::{Dialer, DecodedAccount, DecodeError, DecodeResult};
 
-#[derive(Debug)]
+const PREFIX_V20: &'static str = "{SRUN3}\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Configuration {
     TaLiMu,
 }
@@ -21,7 +23,58 @@ impl Srun3kDialer {
         unsafe {
             encrypted_username = String::from_utf8_unchecked(encrypted_bytes);
         };
-        format!("{{SRUN3}}\r\n{}", encrypted_username)
+        format!("{}{}", PREFIX_V20, encrypted_username)
+    }
+
+    /// v20 usernames carry neither timestamp nor PIN, only the username.
+    pub fn decode_account_v20(&self,
+                              encrypted: &str)
+                              -> DecodeResult<DecodedAccount<Configuration>> {
+        if !encrypted.starts_with(PREFIX_V20) {
+            return Err(DecodeError::UnexpectedPrefix(encrypted.to_string()));
+        }
+        let mut decrypted_bytes = Vec::with_capacity(encrypted.len() - PREFIX_V20.len());
+        for c in encrypted[PREFIX_V20.len()..].bytes() {
+            match c.checked_sub(4) {
+                Some(c) => decrypted_bytes.push(c),
+                None => return Err(DecodeError::InvalidFormat(encrypted.to_string())),
+            }
+        }
+        let username = try!(String::from_utf8(decrypted_bytes)
+            .map_err(|_| DecodeError::InvalidFormat(encrypted.to_string())));
+
+        Ok(DecodedAccount {
+            username: username,
+            timestamp: None,
+            pin: None,
+            configuration: None,
+        })
+    }
+
+    pub fn verify_account_v20(encrypted: &str,
+                              configurations: &[Configuration])
+                              -> DecodeResult<DecodedAccount<Configuration>> {
+        for configuration in configurations {
+            let dialer = Srun3kDialer::new(Some(*configuration));
+            let mut decoded = try!(dialer.decode_account_v20(encrypted));
+            if dialer.encrypt_account_v20(&decoded.username) == encrypted {
+                decoded.configuration = Some(*configuration);
+                return Ok(decoded);
+            }
+        }
+        Err(DecodeError::PinMismatch(encrypted.to_string()))
+    }
+}
+
+impl Configuration {
+    pub fn all() -> &'static [Configuration] {
+        &[Configuration::TaLiMu]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Configuration::TaLiMu => "talimu",
+        }
     }
 }
 
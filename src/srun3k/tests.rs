@@ -8,3 +8,17 @@ fn test_srun3k_v20_username_encrypt() {
     let encrypted_result = dialer.encrypt_account_v20(username);
     assert_eq!(encrypted_result, "{SRUN3}\r\nehqmr");
 }
+
+#[test]
+fn test_srun3k_v20_username_decode() {
+    let dialer = Srun3kDialer::load_from_config(Configuration::TaLiMu);
+    let decoded = dialer.decode_account_v20("{SRUN3}\r\nehqmr").unwrap();
+    assert_eq!(decoded.username, "admin");
+    assert_eq!(decoded.timestamp, None);
+
+    let verified = Srun3kDialer::verify_account_v20("{SRUN3}\r\nehqmr", &[Configuration::TaLiMu])
+        .unwrap();
+    assert_eq!(verified.configuration, Some(Configuration::TaLiMu));
+    assert!(dialer.decode_account_v20("{SRUN3}\r\n\x01").is_err());
+    assert!(dialer.decode_account_v20("admin").is_err());
+}